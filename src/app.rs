@@ -2,16 +2,53 @@
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, layout::{Position, Rect}, Terminal};
 use std::io::{self, Stdout};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::ui::Ui;
-use crate::fs::FileSystem;
+use crate::bookmarks::{self, Bookmark};
+use crate::config::Config;
+use crate::keymap::{Action, Keymap};
+use crate::ui::{context_menu_items, ClipboardItem, ClipboardList, CommandPalette, DeleteConfirm, InputKind, InputPrompt, JobList, MenuItem, Mode, OtherPane, Pane, PromptKind, Ui};
+use crate::fs::{Clipboard, ClipboardMode, DirSizeCache, DirectoryWatcher, FileEntry, FileSystem, GitLogCache, JobKind, JobOutcome, JobQueue, Loaded, SelectionFilter, SortDirection, TrashBin};
 use crate::graphics::{GraphicsBackend, ThumbnailCache};
+use crate::graphics::thumbnails::{MAX_THUMBNAIL_SIZE, MIN_THUMBNAIL_SIZE};
+use crate::graphics::icons::{IconManager, IconSlot};
+use crate::url_map::UrlMapping;
+
+/// Longest gap between the clicks of a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// How long the selection must rest on an image before its thumbnail is generated
+const THUMBNAIL_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Each `+` or `-` scales thumbnails by this many quarters
+const THUMBNAIL_SIZE_STEP: u32 = 5;
+
+/// Each wheel notch over the preview zooms the image by this factor
+const ZOOM_STEP: f32 = 1.25;
+
+/// Zoom bounds, relative to the configured thumbnail size
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+
+/// What started the running file job, for the report when it ends
+enum JobOrigin {
+    /// Pasting the clipboard, kept to restore what a cut didn't move
+    Paste(Clipboard),
+    /// Copying or moving to the other pane's directory
+    Transfer(PathBuf),
+    Delete,
+}
+
+/// Lines the preview moves per scroll step
+const PREVIEW_SCROLL_LINES: isize = 3;
 
 /// Main application state
 pub struct App {
@@ -19,46 +56,176 @@ pub struct App {
     terminal: Terminal<CrosstermBackend<Stdout>>,
     /// UI state and rendering
     ui: Ui,
-    /// Filesystem operations
+    /// Filesystem operations for the focused pane
     fs: FileSystem,
+    /// The pane without focus in dual-pane mode, created when first shown
+    second_fs: Option<FileSystem>,
+    /// Side `fs` is shown on in dual-pane mode
+    focused_pane: Pane,
     /// Graphics backend (Kitty, Sixel, or Fallback)
     graphics: GraphicsBackend,
     /// Thumbnail cache for image previews
     thumbnails: ThumbnailCache,
+    /// Graphic file icons for Kitty and iTerm2
+    icons: IconManager,
+    /// Icons currently drawn over the file grid
+    shown_icons: Vec<IconSlot>,
+    /// Reloads the listing when the directory changes on disk (None if unavailable)
+    watcher: Option<DirectoryWatcher>,
+    /// Image whose thumbnail is currently on screen
+    shown_thumbnail: Option<PathBuf>,
+    /// Where the thumbnail was last written and the sequence written, so it
+    /// is only sent again when one of them changes
+    drawn_thumbnail: Option<(Rect, String)>,
+    /// Selected entry, and when the selection last moved to it
+    selection_since: (Option<PathBuf>, Instant),
+    /// How long the selection rests before off-screen Kitty images are freed
+    idle_image_cleanup: Option<Duration>,
+    /// Images were freed since the selection last moved
+    images_released: bool,
+    /// Zoom of the selected image, from the mouse wheel over the preview
+    zoom: f32,
+    /// Path recorded by Copy/Cut for the next Paste
+    clipboard: Option<Clipboard>,
+    /// System clipboard for copied paths, opened on first use
+    system_clipboard: Option<arboard::Clipboard>,
+    /// Recursive directory sizes calculated on request
+    dir_sizes: DirSizeCache,
+    /// The trash's size for the sidebar, and emptying it
+    trash_bin: TrashBin,
+    /// Key bindings for the file list
+    keymap: Keymap,
+    /// Delete skips the trash
+    permanent_delete: bool,
+    /// Copies keep the original's times, permissions, and owner
+    preserve_metadata: bool,
+    /// Copies, moves, and deletes, running in the background one at a time
+    jobs: JobQueue<JobOrigin>,
+    /// Permanent-delete offer for what the trash refused, held until no dialog is open
+    deferred_delete: Option<DeleteConfirm>,
+    /// Terminal emulator command for `!`; empty runs `$SHELL` in place
+    terminal_command: Vec<String>,
+    /// Arguments that open `$EDITOR` at a line, e.g. "+{line}"
+    editor_line_flag: String,
+    /// Web addresses of files in synced folders, for `open_web_url`
+    path_url_map: Vec<UrlMapping>,
+    /// Time and entry of the last left-click, to spot a double-click
+    last_click: Option<(Instant, usize)>,
+    /// The last rename as (old path, new path), until it's undone
+    last_rename: Option<(PathBuf, PathBuf)>,
+    /// Where a shift-click range starts: the last entry clicked or marked without Shift
+    range_anchor: Option<usize>,
+    /// The left button went down on the file grid's scrollbar and is still held
+    dragging_scrollbar: bool,
     /// Whether the app should quit
     should_quit: bool,
 }
 
 impl App {
     /// Create a new application instance
-    pub fn new() -> Result<Self> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend)?;
+    ///
+    /// `minimal` starts with the sidebar and preview hidden (`--minimal`),
+    /// as does `minimal` in the config;
+    /// `start_dir` is the directory given on the command line.
+    pub fn new(minimal: bool, start_dir: Option<PathBuf>) -> Result<Self> {
+        // Read settings while stderr is still visible
+        let config = Config::load();
+
+        // Start in the directory given, else the configured one, else the current one
+        let start_dir = match start_dir.or(config.start_dir) {
+            Some(dir) => dir,
+            None => std::env::current_dir()?,
+        };
+
+        let terminal = Self::setup_terminal()?;
 
         // Detect graphics protocol
-        let graphics = GraphicsBackend::detect();
+        let mut graphics = GraphicsBackend::detect();
+        graphics.tmux_passthrough &= config.tmux_passthrough;
+        log::debug!("tmux passthrough: {}", graphics.tmux_passthrough);
         
         // Create thumbnail cache with the same backend
-        let thumbnails = ThumbnailCache::new(graphics.clone());
+        let mut thumbnails = ThumbnailCache::new(graphics.clone());
+        thumbnails.set_size(config.thumbnail_size);
+        thumbnails.set_quality(config.thumbnail_quality);
+        thumbnails.set_cache_limits(config.thumbnail_cache_entries, config.thumbnail_cache_bytes);
+        let icons = IconManager::new(graphics.clone());
         
-        // Get current directory
-        let current_dir = std::env::current_dir()?;
-        let fs = FileSystem::new(current_dir);
+        let mut fs = FileSystem::new(start_dir);
+        fs.show_hidden = config.show_hidden;
+        fs.auto_descend_single = config.auto_descend_single;
+        fs.image_extensions = config.image_extensions.into();
+        fs.git_log = config.git_column.then(GitLogCache::new);
+
+        let mut ui = Ui::new();
+        ui.show_sidebar = config.show_sidebar;
+        ui.show_preview = config.show_preview;
+        ui.theme = config.theme;
+        ui.hex_preview_bytes = config.hex_preview_bytes;
+        ui.split_extension = config.split_extension;
+        if !config.bookmarks.is_empty() {
+            ui.bookmarks = config.bookmarks;
+        }
+        if minimal || config.minimal {
+            ui.set_minimal(true);
+        }
+        ui.full_view_key = config.keymap.chords_for(Action::ToggleMinimal).first().map(|chord| chord.to_string());
 
         Ok(Self {
             terminal,
-            ui: Ui::new(),
+            ui,
             fs,
+            second_fs: None,
+            focused_pane: Pane::Left,
             graphics,
             thumbnails,
+            icons,
+            shown_icons: Vec::new(),
+            watcher: DirectoryWatcher::new().ok(),
+            shown_thumbnail: None,
+            drawn_thumbnail: None,
+            selection_since: (None, Instant::now()),
+            idle_image_cleanup: config.idle_image_cleanup,
+            images_released: false,
+            zoom: 1.0,
+            clipboard: None,
+            system_clipboard: None,
+            dir_sizes: DirSizeCache::new(),
+            trash_bin: TrashBin::new(),
+            keymap: config.keymap,
+            permanent_delete: config.permanent_delete,
+            preserve_metadata: config.preserve_metadata,
+            jobs: JobQueue::new(),
+            deferred_delete: None,
+            terminal_command: config.terminal,
+            editor_line_flag: config.editor_line_flag,
+            path_url_map: config.path_url_map,
+            last_click: None,
+            range_anchor: None,
+            last_rename: None,
+            dragging_scrollbar: false,
             should_quit: false,
         })
     }
 
+    /// Enter raw mode and the alternate screen, undoing both if a later step fails
+    fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+        enable_raw_mode()?;
+        let terminal = execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+            .and_then(|()| Terminal::new(CrosstermBackend::new(io::stdout())));
+        if terminal.is_err() {
+            Self::restore_terminal();
+        }
+        Ok(terminal?)
+    }
+
+    /// Leave raw mode, the alternate screen, and mouse capture
+    ///
+    /// Safe to call at any point, even if setup only got partway.
+    pub fn restore_terminal() {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+    }
 
     /// Main event loop
     pub async fn run(&mut self) -> Result<()> {
@@ -66,37 +233,138 @@ impl App {
         self.fs.load_directory()?;
 
         loop {
-            // Get thumbnail for current selection if it's an image
-            let thumbnail = if let Some(entry) = self.fs.get_selected(self.ui.selected_index) {
-                if crate::graphics::is_image_file(&entry.path) {
-                    self.thumbnails.get_thumbnail(&entry.path)
-                } else {
-                    None
+            self.apply_loaded_directory();
+            self.watch_current_directory();
+
+            // Pick up thumbnails and directory sizes finished in the background
+            self.thumbnails.poll_completed();
+            for (path, e) in self.dir_sizes.poll_completed() {
+                self.ui.set_error(format!("Cannot calculate size of {}: {}", path.display(), e));
+            }
+            self.poll_job();
+            self.poll_trash();
+            self.fs.poll_git_log();
+            if let Some(other) = self.second_fs.as_mut() {
+                other.poll_git_log();
+            }
+            if self.ui.mode == Mode::Normal {
+                if let Some(confirm) = self.deferred_delete.take() {
+                    self.confirm_delete(confirm);
                 }
-            } else {
-                None
-            };
+            }
+            
+            // Note when the selection moves, so thumbnails wait for it to settle
+            let selected = self.fs.get_selected(self.ui.selected_index).map(|entry| entry.path.clone());
+            if selected != self.selection_since.0 {
+                self.selection_since = (selected, Instant::now());
+                self.images_released = false;
+                // The preview's scroll position belongs to the file it was scrolled on
+                self.ui.preview_scroll = 0;
+                // Zoom belongs to the image it was set on
+                if self.zoom != 1.0 {
+                    self.zoom = 1.0;
+                    self.thumbnails.set_zoom(None);
+                }
+                if self.ui.mode == Mode::Viewer {
+                    self.fit_viewer_image();
+                }
+            }
+            let settled = self.selection_since.1.elapsed() >= THUMBNAIL_DEBOUNCE;
+
+            // Get thumbnail for current selection if it's an image; decoding
+            // waits until the selection settles, so holding Down stays smooth
+            let wants_thumbnail = self.fs.get_selected(self.ui.selected_index).filter(|entry| {
+                entry.is_image
+                    && (self.ui.mode == Mode::Viewer
+                        || (self.ui.preview_visible() && self.ui.content_previews(&self.fs)))
+            }).map(|entry| entry.path.clone());
+            let thumbnail = wants_thumbnail.as_ref()
+                .filter(|path| settled || self.thumbnails.is_cached(path))
+                .and_then(|path| self.thumbnails.get_thumbnail(path));
+            self.ui.thumbnail_loading = thumbnail.is_none()
+                && wants_thumbnail.as_ref()
+                    .is_some_and(|path| !settled || self.thumbnails.is_pending(path));
+            self.ui.thumbnail_failed = wants_thumbnail.as_ref()
+                .is_some_and(|path| self.thumbnails.has_failed(path));
             
             // Render UI
-            self.terminal.draw(|frame| {
-                self.ui.render(frame, &self.fs, &self.graphics, thumbnail.as_deref());
+            let other = self.second_fs.as_ref()
+                .map(|fs| OtherPane { fs, focused: self.focused_pane });
+            let mode = self.ui.mode;
+            self.terminal.draw(|frame| match mode {
+                Mode::Viewer => self.ui.render_viewer(frame, &self.fs, thumbnail.as_deref()),
+                _ => self.ui.render(frame, &self.fs, other, &self.graphics, thumbnail.as_deref(), &self.dir_sizes),
             })?;
             
-            // After frame render, output thumbnail escape sequence for Kitty
-            if let Some(ref thumb_seq) = thumbnail {
-                // Position cursor at preview pane location and output image
-                // For now, output after the frame (Kitty will handle positioning)
+            // Remove the previous image once it is no longer the one to show
+            let showing = thumbnail.as_ref()
+                .and_then(|_| self.fs.get_selected(self.ui.selected_index))
+                .map(|entry| entry.path.clone());
+            let mut cleared = false;
+            if showing != self.shown_thumbnail {
+                if self.shown_thumbnail.is_some() {
+                    use std::io::Write;
+                    let _ = std::io::stdout().write_all(self.graphics.clear_images().as_bytes());
+                    let _ = std::io::stdout().flush();
+                    // The image was written into cells ratatui thinks are blank,
+                    // and isn't sent again every frame to paint over what follows
+                    if self.graphics.draws_into_cells() {
+                        let _ = self.terminal.clear();
+                    }
+                    cleared = true;
+                }
+                self.shown_thumbnail = showing;
+                self.drawn_thumbnail = None;
+            }
+
+            // Redraw icons when the grid moved (or the clear above removed them)
+            if cleared || self.ui.icon_slots != self.shown_icons {
                 use std::io::Write;
-                let _ = std::io::stdout().write_all(thumb_seq.as_bytes());
+                let sequence = self.icons.render_slots(&self.ui.icon_slots);
+                let _ = std::io::stdout().write_all(sequence.as_bytes());
                 let _ = std::io::stdout().flush();
+                self.shown_icons = self.ui.icon_slots.clone();
+            }
+
+            // After the frame, draw the thumbnail where the preview left room for it
+            let drawn = thumbnail.zip(self.ui.thumbnail_area).map(|(sequence, area)| (area, sequence));
+            if let Some((area, thumb_seq)) = drawn.as_ref().filter(|&drawn| Some(drawn) != self.drawn_thumbnail.as_ref()) {
+                use std::io::Write;
+                // Every protocol draws at the cursor; save it, move it, and put it back
+                let mut stdout = std::io::stdout();
+                let _ = write!(stdout, "\x1b7\x1b[{};{}H{}\x1b8", area.y + 1, area.x + 1, thumb_seq);
+                let _ = stdout.flush();
+            }
+            self.drawn_thumbnail = drawn;
+
+            // Thumbnails the cache let go of still hold their data in the terminal
+            let dropped = self.thumbnails.take_dropped();
+            if !dropped.is_empty() {
+                use std::io::Write;
+                let _ = io::stdout().write_all(dropped.as_bytes());
+                let _ = io::stdout().flush();
+            }
+
+            // Once the selection rests, free the images that aren't on screen
+            if !self.images_released
+                && self.idle_image_cleanup.is_some_and(|after| self.selection_since.1.elapsed() >= after)
+            {
+                self.release_idle_images();
             }
 
             // Handle events
             if event::poll(std::time::Duration::from_millis(16))? {
                 match event::read()? {
-                    Event::Key(key) => self.handle_key(key.code),
+                    Event::Key(key) => self.handle_key(key.code, key.modifiers),
                     Event::Mouse(mouse) => self.handle_mouse(mouse),
-                    Event::Resize(_, _) => {} // Ratatui handles resize automatically
+                    Event::Resize(_, _) => {
+                        // Ratatui redraws the text itself; the graphics go with the old screen
+                        self.shown_icons.clear();
+                        self.drawn_thumbnail = None;
+                        if self.ui.mode == Mode::Viewer {
+                            self.fit_viewer_image();
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -109,74 +377,1637 @@ impl App {
         Ok(())
     }
 
+    /// Free the Kitty data of icons and thumbnails not on screen
+    ///
+    /// Each transmitted image stays in the terminal's memory until deleted,
+    /// so browsing a large photo folder would otherwise keep every thumbnail
+    /// seen. What is freed is transmitted again when next shown.
+    fn release_idle_images(&mut self) {
+        use std::io::Write;
+        self.images_released = true;
+        let mut sequence = self.icons.release_unused(&self.shown_icons);
+        sequence.push_str(&self.thumbnails.release_transmitted(self.shown_thumbnail.as_deref()));
+        if !sequence.is_empty() {
+            let _ = io::stdout().write_all(sequence.as_bytes());
+            let _ = io::stdout().flush();
+        }
+    }
+
+    /// Show a directory once its background load finishes
+    fn apply_loaded_directory(&mut self) {
+        match self.fs.poll_loaded() {
+            Some(Ok(Loaded::Entered)) => {
+                self.ui.marked.clear();
+                self.reset_selection();
+            }
+            Some(Ok(Loaded::Reloaded(previous))) => self.apply_reload(&previous),
+            Some(Err(e)) => self.ui.set_error(format!("Cannot open directory: {}", e)),
+            None => {}
+        }
+    }
+
+    /// After the shown directory was read again, select the entry selected
+    /// in `previous` if it still exists
+    ///
+    /// Thumbnails of files modified since the last read are dropped so they
+    /// are generated afresh.
+    fn apply_reload(&mut self, previous: &[FileEntry]) {
+        let modified: HashMap<&Path, Option<SystemTime>> = previous
+            .iter()
+            .map(|entry| (entry.path.as_path(), entry.modified))
+            .collect();
+        for entry in &self.fs.entries {
+            if modified.get(entry.path.as_path()).is_some_and(|before| *before != entry.modified) {
+                self.thumbnails.forget(&entry.path);
+            }
+        }
+
+        let selected = previous.get(self.ui.selected_index).map(|entry| &entry.path);
+        if let Some(index) = selected.and_then(|path| self.fs.entries.iter().position(|e| &e.path == path)) {
+            self.ui.selected_index = index;
+        }
+        self.ui.clamp_selection(self.fs.entries.len());
+        self.ui.ensure_visible(self.ui.visible_height());
+    }
+
+    /// Follow navigation in both panes with the watcher and reload after external changes
+    fn watch_current_directory(&mut self) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        if self.fs.is_loading() || self.second_fs.as_ref().is_some_and(|other| other.is_loading()) {
+            return;
+        }
+        let mut paths = vec![self.fs.current_path.as_path()];
+        if let Some(other) = &self.second_fs {
+            paths.push(other.current_path.as_path());
+        }
+        // Some filesystems (e.g. /proc) can't be watched; the listing just stays static
+        let _ = watcher.watch(&paths);
+
+        let changed = watcher.poll_changed();
+        for path in &changed {
+            self.dir_sizes.invalidate(path);
+        }
+        if changed.contains(&self.fs.current_path) {
+            self.fs.reload();
+        }
+        if self.second_fs.as_ref().is_some_and(|other| changed.contains(&other.current_path)) {
+            self.reload_other_pane();
+        }
+    }
+
     /// Handle keyboard input
-    fn handle_key(&mut self, key: KeyCode) {
-        // Close context menu on any key if open
-        if self.ui.show_context_menu {
-            match key {
-                KeyCode::Esc => self.ui.show_context_menu = false,
-                KeyCode::Up => {
-                    if self.ui.context_menu_selected > 0 {
-                        self.ui.context_menu_selected -= 1;
+    fn handle_key(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        // Status messages last until the next key press (errors until they time out)
+        self.ui.clear_status();
+
+        match self.ui.mode {
+            Mode::Normal => self.handle_normal_key(key, modifiers),
+            Mode::ContextMenu => self.handle_context_menu_key(key),
+            Mode::Input(InputKind::Prompt) => self.handle_prompt_key(key),
+            Mode::Input(InputKind::ConfirmDelete) => self.handle_delete_confirm_key(key),
+            Mode::Input(InputKind::ConfirmCleanup) => self.handle_cleanup_confirm_key(key),
+            Mode::Input(InputKind::ConfirmQuit) => self.handle_quit_confirm_key(key),
+            Mode::Search => self.handle_filter_key(key),
+            Mode::Viewer => self.handle_viewer_key(key),
+            Mode::Help => self.handle_palette_key(key, modifiers),
+            Mode::Jobs => self.handle_jobs_key(key),
+            Mode::Clipboard => self.handle_clipboard_key(key),
+        }
+    }
+
+    /// Switch to `mode`, dropping what the old one showed
+    ///
+    /// Entering or leaving the viewer takes the old screen's images down.
+    fn set_mode(&mut self, mode: Mode) {
+        if mode == self.ui.mode {
+            return;
+        }
+        match self.ui.mode {
+            Mode::Normal | Mode::ContextMenu | Mode::Search | Mode::Viewer => {}
+            Mode::Input(InputKind::ConfirmQuit) => {}
+            Mode::Input(InputKind::Prompt) => self.ui.input_prompt = None,
+            Mode::Input(InputKind::ConfirmDelete) => self.ui.pending_delete = None,
+            Mode::Input(InputKind::ConfirmCleanup) => self.ui.cleanup_preview = None,
+            Mode::Help => self.ui.command_palette = None,
+            Mode::Jobs => self.ui.job_list = None,
+            Mode::Clipboard => self.ui.clipboard_list = None,
+        }
+
+        let leaving_viewer = self.ui.mode == Mode::Viewer;
+        if leaving_viewer || mode == Mode::Viewer {
+            {
+                use std::io::Write;
+                let _ = io::stdout().write_all(self.graphics.clear_images().as_bytes());
+                let _ = io::stdout().flush();
+            }
+            // The image was written into cells ratatui thinks are blank
+            if self.graphics.draws_into_cells() {
+                let _ = self.terminal.clear();
+            }
+            self.shown_thumbnail = None;
+        }
+        // Overlays are drawn over the thumbnail, and closing one blanks the cells again
+        self.drawn_thumbnail = None;
+        self.ui.mode = mode;
+        if leaving_viewer {
+            self.zoom = 1.0;
+            self.thumbnails.set_zoom(None);
+            self.ui.ensure_visible(self.ui.visible_height());
+        } else if mode == Mode::Viewer {
+            self.fit_viewer_image();
+        }
+    }
+
+    /// Keys on the file list, looked up in the keymap
+    fn handle_normal_key(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        let Some(action) = self.keymap.action_for(key, modifiers) else {
+            return;
+        };
+
+        // The listing is about to be replaced; only quitting and layout keys apply
+        if self.fs.is_loading() && !action.allowed_while_loading() {
+            return;
+        }
+
+        self.run_action(action);
+    }
+
+    /// Move through the context menu, running the highlighted item on Enter
+    fn handle_context_menu_key(&mut self, key: KeyCode) {
+        let items = context_menu_items(self.ui.context_menu_entry(&self.fs));
+        match key {
+            KeyCode::Esc => self.set_mode(Mode::Normal),
+            KeyCode::Up if self.ui.context_menu_selected > 0 => {
+                self.ui.context_menu_selected -= 1;
+            }
+            KeyCode::Down if self.ui.context_menu_selected + 1 < items.len() => {
+                self.ui.context_menu_selected += 1;
+            }
+            KeyCode::Enter => self.choose_context_menu_item(),
+            _ => {}
+        }
+    }
+
+    /// Close the context menu and run its highlighted item
+    fn choose_context_menu_item(&mut self) {
+        let items = context_menu_items(self.ui.context_menu_entry(&self.fs));
+        self.set_mode(Mode::Normal);
+        if let Some(&item) = items.get(self.ui.context_menu_selected) {
+            self.execute_context_action(item);
+        }
+    }
+
+    /// Answer the empty-directory cleanup: y or Enter removes them, any other key cancels
+    fn handle_cleanup_confirm_key(&mut self, key: KeyCode) {
+        let dirs = self.ui.cleanup_preview.take();
+        self.set_mode(Mode::Normal);
+        if let (Some(dirs), KeyCode::Char('y') | KeyCode::Enter) = (dirs, key) {
+            let removed = crate::fs::remove_empty_dirs(&dirs);
+            self.ui.set_status(format!("Removed {} empty directories", removed));
+            self.reload();
+            self.reset_selection();
+        }
+    }
+
+    /// Only y quits while a job is running; any other key keeps going
+    fn handle_quit_confirm_key(&mut self, key: KeyCode) {
+        self.set_mode(Mode::Normal);
+        if matches!(key, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            if let Some(job) = self.jobs.running() {
+                job.cancel();
+            }
+            self.should_quit = true;
+        }
+    }
+
+    /// Perform a normal-mode action
+    fn run_action(&mut self, action: Action) {
+        if self.jobs.running().is_some() && action.changes_files() && !action.starts_job() {
+            self.ui.set_status("Wait for the current operation to finish");
+            return;
+        }
+        // Copying out of a disk image extracts; nothing else may change it
+        let writes_here = action.changes_files() && !matches!(action, Action::CopyToOtherPane | Action::EmptyTrash);
+        if writes_here && crate::fs::inside_image(&self.fs.current_path) {
+            self.ui.set_error("Disk images are read-only; copy files out with Copy and Paste");
+            return;
+        }
+        let total = self.fs.entries.len();
+        match action {
+            Action::Cancel if !self.fs.filter.is_empty() => {
+                self.fs.set_filter("");
+                self.reset_selection();
+            }
+            Action::Cancel if !self.ui.marked.is_empty() => self.ui.marked.clear(),
+            // Quitting mid-job asks first; without one it stays instant
+            Action::Quit | Action::Cancel if self.jobs.running().is_some() => {
+                self.set_mode(Mode::Input(InputKind::ConfirmQuit));
+            }
+            Action::Quit | Action::Cancel => self.should_quit = true,
+            // Space opens and closes directories in the tree; files are still marked
+            Action::Mark if self.fs.is_tree() && self.fs.get_selected(self.ui.selected_index).is_some_and(|entry| entry.is_dir) => {
+                let expanded = self.fs.tree_rows.get(self.ui.selected_index).is_some_and(|row| row.expanded);
+                self.run_action(if expanded { Action::Collapse } else { Action::Expand });
+            }
+            Action::Mark => {
+                if let Some(entry) = self.fs.get_selected(self.ui.selected_index) {
+                    self.ui.toggle_mark(&entry.path);
+                    self.range_anchor = Some(self.ui.selected_index);
+                    self.ui.move_selection(1, total);
+                }
+            }
+            Action::Filter => {
+                self.set_mode(Mode::Search);
+            }
+            Action::ShowMarkedOnly => self.toggle_selection_filter(false),
+            Action::HideMarked => self.toggle_selection_filter(true),
+            // A row up or down: one entry in the list, a row of icons in the icon view
+            Action::Up => self.ui.move_selection(-(self.ui.columns() as i32), total),
+            Action::Down => self.ui.move_selection(self.ui.columns() as i32, total),
+            Action::ToggleSidebar => self.ui.toggle_sidebar(),
+            Action::TogglePreview => self.ui.toggle_preview(),
+            Action::PreviewUp => self.ui.scroll_preview(&self.fs, -PREVIEW_SCROLL_LINES),
+            Action::PreviewDown => self.ui.scroll_preview(&self.fs, PREVIEW_SCROLL_LINES),
+            Action::ToggleMinimal => self.ui.toggle_minimal(),
+            Action::Open | Action::ViewImage if self.selected_image().is_some() => self.set_mode(Mode::Viewer),
+            Action::ViewImage => self.ui.set_status("Select an image to view it"),
+            // Disk images open like directories
+            Action::Open => {
+                if let Some(entry) = self.fs.get_selected(self.ui.selected_index) {
+                    if entry.is_dir || crate::fs::is_disk_image(&entry.path) {
+                        let path = entry.path.clone();
+                        self.fs.enter_directory(&path);
                     }
                 }
-                KeyCode::Down => {
-                    if self.ui.context_menu_selected < 5 {
-                        self.ui.context_menu_selected += 1;
+            }
+            Action::ToggleTree => {
+                let selected = self.fs.get_selected(self.ui.selected_index).map(|e| e.path.clone());
+                self.fs.toggle_tree();
+                self.ui.selected_index = 0;
+                if let Some(path) = selected {
+                    self.select_path(&path);
+                }
+                self.ui.clamp_selection(self.fs.entries.len());
+            }
+            // The icon view has no tree, so the side arrows move along the row
+            Action::Expand if self.ui.icon_grid.is_some() => self.ui.move_selection(1, total),
+            Action::Collapse if self.ui.icon_grid.is_some() => self.ui.move_selection(-1, total),
+            Action::ToggleIconView => {
+                self.ui.icon_view = !self.ui.icon_view;
+                if self.ui.icon_view && self.fs.is_tree() {
+                    self.ui.set_status("Icon view applies once the tree view is off");
+                }
+            }
+            Action::ThumbnailLarger => self.resize_thumbnails(true),
+            Action::ThumbnailSmaller => self.resize_thumbnails(false),
+            Action::Expand => {
+                if let Err(e) = self.fs.expand(self.ui.selected_index) {
+                    self.ui.set_error(format!("Cannot expand: {}", e));
+                }
+            }
+            Action::Collapse => {
+                if let Some(index) = self.fs.collapse(self.ui.selected_index) {
+                    self.ui.selected_index = index;
+                }
+                self.ui.clamp_selection(self.fs.entries.len());
+            }
+            Action::GoUp => {
+                match self.fs.go_up() {
+                    Ok(true) => {}
+                    Ok(false) => self.ui.set_status("Already at root"),
+                    Err(e) => self.ui.set_error(format!("Cannot go up: {}", e)),
+                }
+            }
+            Action::Back => {
+                if !self.fs.back() {
+                    self.ui.set_status("No earlier directory");
+                }
+            }
+            Action::Forward => {
+                if !self.fs.forward() {
+                    self.ui.set_status("No later directory");
+                }
+            }
+            Action::ToggleWritable => self.toggle_selected_flag(false),
+            Action::ToggleImmutable => self.toggle_selected_flag(true),
+            Action::CycleSort => {
+                self.fs.set_sort(self.fs.sort_mode.next());
+                self.reset_selection();
+            }
+            Action::ReverseSort => {
+                self.fs.toggle_reverse();
+                self.reset_selection();
+            }
+            Action::SortAscending => {
+                self.fs.set_sort_direction(SortDirection::Ascending);
+                self.reset_selection();
+            }
+            Action::SortDescending => {
+                self.fs.set_sort_direction(SortDirection::Descending);
+                self.reset_selection();
+            }
+            Action::ToggleHidden => self.fs.toggle_hidden(),
+            Action::ToggleContentPreviews => {
+                self.ui.toggle_content_previews(&self.fs);
+                let state = if self.ui.content_previews(&self.fs) { "on" } else { "off" };
+                self.ui.set_status(format!("Content previews {}", state));
+            }
+            Action::RemoveEmptyDirs => {
+                let dirs = crate::fs::find_empty_dirs(&self.fs.current_path);
+                if dirs.is_empty() {
+                    self.ui.set_status("No empty directories");
+                } else {
+                    self.set_mode(Mode::Input(InputKind::ConfirmCleanup));
+                    self.ui.cleanup_preview = Some(dirs);
+                }
+            }
+            Action::GoTo => {
+                self.open_prompt(InputPrompt::new(PromptKind::GoTo, "Go to (Tab completes)", String::new()));
+            }
+            Action::DescendSingle => {
+                self.fs.descend_single_children();
+            }
+            Action::NewFile => {
+                self.open_prompt(InputPrompt::new(PromptKind::NewFile, "New file", String::new()));
+            }
+            Action::NewDirectory => {
+                self.open_prompt(InputPrompt::new(PromptKind::NewDirectory, "New directory", String::new()));
+            }
+            Action::ToggleDualPane => self.toggle_dual_pane(),
+            Action::SwitchPane => {
+                if self.ui.dual_pane {
+                    self.switch_pane();
+                }
+            }
+            Action::DirectorySize => {
+                let dirs: Vec<PathBuf> = self.action_targets().into_iter().filter(|path| path.is_dir()).collect();
+                if dirs.is_empty() {
+                    self.ui.set_status("Select a directory to calculate its size");
+                }
+                for dir in &dirs {
+                    self.dir_sizes.request(dir);
+                }
+            }
+            Action::Copy => self.copy_to_clipboard(ClipboardMode::Copy),
+            Action::Cut => self.copy_to_clipboard(ClipboardMode::Cut),
+            Action::Paste => self.paste(),
+            Action::Delete => {
+                let paths = self.action_targets();
+                if !paths.is_empty() {
+                    self.confirm_delete(DeleteConfirm::new(paths, self.permanent_delete));
+                }
+            }
+            Action::Rename => self.start_rename(),
+            Action::UndoRename => self.undo_rename(),
+            Action::Refresh => {
+                self.dir_sizes.invalidate(&self.fs.current_path);
+                self.fs.reload();
+                self.ui.set_status("Refreshed");
+            }
+            Action::CopyPath => self.copy_selected_to_clipboard(true),
+            Action::CopyName => self.copy_selected_to_clipboard(false),
+            Action::CommandPalette => {
+                self.set_mode(Mode::Help);
+                self.ui.command_palette = Some(CommandPalette::new(&self.keymap));
+            }
+            Action::ShowJobs => {
+                self.set_mode(Mode::Jobs);
+                self.ui.job_list = Some(JobList::default());
+                self.refresh_job_list();
+            }
+            Action::ShowClipboard => {
+                if self.clipboard.is_none() {
+                    self.ui.set_status("Clipboard is empty");
+                    return;
+                }
+                self.set_mode(Mode::Clipboard);
+                self.ui.clipboard_list = Some(ClipboardList::default());
+                self.refresh_clipboard_list();
+            }
+            Action::AddBookmark => self.add_bookmark(),
+            Action::RemoveBookmark => self.remove_bookmark(),
+            Action::OpenTerminal => self.open_terminal(),
+            Action::OpenWebUrl => self.open_web_url(),
+            Action::Edit => self.edit_selected(),
+            Action::EmptyTrash => self.start_empty_trash(),
+            Action::CopyToOtherPane => self.transfer_to_other_pane(ClipboardMode::Copy),
+            Action::MoveToOtherPane => self.transfer_to_other_pane(ClipboardMode::Cut),
+            Action::Home => {
+                self.ui.selected_index = 0;
+                self.ui.ensure_visible(self.ui.visible_height());
+            }
+            Action::End => {
+                self.ui.selected_index = total.saturating_sub(1);
+                self.ui.ensure_visible(self.ui.visible_height());
+            }
+        }
+    }
+
+    /// Show or hide the second pane, opening it on the current directory the first time
+    fn toggle_dual_pane(&mut self) {
+        self.ui.dual_pane = !self.ui.dual_pane;
+        if !self.ui.dual_pane || self.second_fs.is_some() {
+            return;
+        }
+
+        let mut other = FileSystem::new(self.fs.current_path.clone());
+        other.show_hidden = self.fs.show_hidden;
+        other.auto_descend_single = self.fs.auto_descend_single;
+        other.image_extensions = self.fs.image_extensions.clone();
+        other.git_log = self.fs.git_log.is_some().then(GitLogCache::new);
+        if let Err(e) = other.load_directory() {
+            self.ui.set_error(format!("Cannot open directory: {}", e));
+        }
+        self.second_fs = Some(other);
+        self.ui.other_pane = Default::default();
+    }
+
+    /// Move focus to the other pane, bringing its listing and selection forward
+    fn switch_pane(&mut self) {
+        let Some(other) = self.second_fs.as_mut() else {
+            return;
+        };
+        std::mem::swap(&mut self.fs, other);
+        self.ui.swap_pane_state();
+        self.focused_pane = self.focused_pane.other();
+        self.range_anchor = None;
+        // The filter belongs to the pane being left
+        if self.ui.mode == Mode::Search {
+            self.set_mode(Mode::Normal);
+        }
+        self.ui.clamp_selection(self.fs.entries.len());
+    }
+
+    /// Copy or move the targets of the focused pane into the other pane's directory
+    fn transfer_to_other_pane(&mut self, mode: ClipboardMode) {
+        let Some(dest) = self.second_fs.as_ref()
+            .filter(|_| self.ui.dual_pane)
+            .map(|other| other.current_path.clone())
+        else {
+            self.ui.set_status("Dual-pane mode is off (F3)");
+            return;
+        };
+
+        let paths = self.action_targets();
+        if paths.is_empty() {
+            return;
+        }
+        let kind = if mode == ClipboardMode::Copy { JobKind::Copy } else { JobKind::Move };
+        self.ui.marked.clear();
+        self.start_job(kind, paths, dest.clone(), JobOrigin::Transfer(dest));
+    }
+
+    /// Answer the delete confirmation: y/Enter on Yes deletes, n/Esc/Enter on No cancels
+    fn handle_delete_confirm_key(&mut self, key: KeyCode) {
+        let Some(confirm) = self.ui.pending_delete.as_mut() else {
+            return;
+        };
+
+        match key {
+            KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                confirm.yes_selected = !confirm.yes_selected;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.delete_confirmed(),
+            KeyCode::Enter if confirm.yes_selected => self.delete_confirmed(),
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.set_mode(Mode::Normal);
+            }
+            _ => {}
+        }
+    }
+
+    /// Trash (or delete) the entries the confirmation was shown for, in the background
+    fn delete_confirmed(&mut self) {
+        let Some(confirm) = self.ui.pending_delete.take() else {
+            return;
+        };
+        self.set_mode(Mode::Normal);
+
+        let kind = if confirm.permanent { JobKind::Delete } else { JobKind::Trash };
+        self.ui.marked.clear();
+        self.start_job(kind, confirm.paths, self.fs.current_path.clone(), JobOrigin::Delete);
+    }
+
+    /// Narrow the view by the marked entries, or show everything again if that view is on
+    ///
+    /// The marks are taken as they are now; marking more later doesn't change the view.
+    fn toggle_selection_filter(&mut self, hide: bool) {
+        let active = match self.fs.selection_filter {
+            Some(SelectionFilter::Only(_)) => !hide,
+            Some(SelectionFilter::Hide(_)) => hide,
+            None => false,
+        };
+        if active {
+            self.fs.set_selection_filter(None);
+        } else if self.ui.marked.is_empty() {
+            self.ui.set_status("Nothing is marked");
+            return;
+        } else {
+            let marked = self.ui.marked.clone();
+            let filter = if hide { SelectionFilter::Hide(marked) } else { SelectionFilter::Only(marked) };
+            self.fs.set_selection_filter(Some(filter));
+        }
+        self.reset_selection();
+    }
+
+    /// Paths an action applies to: the marked entries if any, else the selection
+    fn action_targets(&self) -> Vec<PathBuf> {
+        if !self.ui.marked.is_empty() {
+            let mut paths: Vec<PathBuf> = self.ui.marked.iter().cloned().collect();
+            paths.sort();
+            return paths;
+        }
+        self.fs.get_selected(self.ui.selected_index)
+            .map(|entry| vec![entry.path.clone()])
+            .unwrap_or_default()
+    }
+
+    /// "1 item" / "3 items"
+    fn count_label(count: usize) -> String {
+        if count == 1 { "1 item".to_string() } else { format!("{} items", count) }
+    }
+
+    /// Re-read the current directory after changing it, reporting failures
+    fn reload(&mut self) {
+        self.dir_sizes.invalidate(&self.fs.current_path);
+        if let Err(e) = self.fs.load_directory() {
+            self.ui.set_error(format!("Reload failed: {}", e));
+        }
+        self.ui.clamp_selection(self.fs.entries.len());
+    }
+
+    /// Select the first entry after the listing was replaced
+    fn reset_selection(&mut self) {
+        self.ui.selected_index = 0;
+        self.range_anchor = None;
+        self.ui.clamp_selection(self.fs.entries.len());
+    }
+
+    /// Enter the selected directory, or open the selected file in its default app
+    fn open_selected(&mut self) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index) else {
+            return;
+        };
+        if entry.is_dir || crate::fs::is_disk_image(&entry.path) {
+            self.run_action(Action::Open);
+        } else {
+            let path = entry.path.clone();
+            self.open_externally(&path);
+        }
+    }
+
+    /// Open a file with its default application
+    fn open_externally(&mut self, path: &Path) {
+        let Some(path) = self.openable_path(path) else {
+            return;
+        };
+        if let Err(e) = crate::fs::open_external(&path) {
+            self.ui.set_error(e.to_string());
+        }
+    }
+
+    /// A path another program can open; a file in a disk image is
+    /// extracted to the temp directory first
+    fn openable_path(&mut self, path: &Path) -> Option<PathBuf> {
+        if crate::fs::member_of_image(path).is_none() {
+            return Some(path.to_path_buf());
+        }
+        match crate::fs::extract_to_temp(path) {
+            Ok(extracted) => Some(extracted),
+            Err(e) => {
+                self.ui.set_error(format!("Cannot extract {}: {:#}", path.display(), e));
+                None
+            }
+        }
+    }
+
+    /// Run a context menu item on the entry the menu was opened on
+    ///
+    /// Right-clicking selects the entry first, so items shared with the
+    /// keymap run as the same action on the selection.
+    fn execute_context_action(&mut self, item: MenuItem) {
+        if let Some(action) = item.action() {
+            self.run_action(action);
+            return;
+        }
+        let selected = self.ui.context_menu_entry(&self.fs).cloned();
+
+        match (item, selected) {
+            (MenuItem::Enter, Some(entry)) => self.fs.enter_directory(&entry.path),
+            (MenuItem::Open | MenuItem::OpenExternally, Some(entry)) => self.open_externally(&entry.path),
+            (MenuItem::Preview, Some(_)) => {
+                self.ui.show_preview = true;
+                if !self.ui.content_previews(&self.fs) {
+                    self.ui.toggle_content_previews(&self.fs);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Put the action targets on the clipboard
+    fn copy_to_clipboard(&mut self, mode: ClipboardMode) {
+        let paths = self.action_targets();
+        let what = match paths.as_slice() {
+            [] => return,
+            [path] => path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            _ => Self::count_label(paths.len()),
+        };
+        self.ui.set_status(format!(
+            "{} {}",
+            if mode == ClipboardMode::Copy { "Copied" } else { "Cut" },
+            what
+        ));
+        self.clipboard = Some(Clipboard { paths, mode });
+        self.ui.marked.clear();
+    }
+
+    /// Paste the clipboard into the current directory
+    fn paste(&mut self) {
+        let Some(clipboard) = self.clipboard.take() else {
+            self.ui.set_status("Clipboard is empty");
+            return;
+        };
+
+        let kind = if clipboard.mode == ClipboardMode::Copy { JobKind::Copy } else { JobKind::Move };
+        let paths = clipboard.paths.clone();
+        self.start_job(kind, paths, self.fs.current_path.clone(), JobOrigin::Paste(clipboard));
+    }
+
+    /// Run a copy, move, or delete in the background; `poll_job` reports the end
+    ///
+    /// While another job runs this one is queued behind it, so only one
+    /// operation works the disk at a time.
+    fn start_job(&mut self, kind: JobKind, paths: Vec<PathBuf>, dest: PathBuf, origin: JobOrigin) {
+        let count = Self::count_label(paths.len());
+        let label = match kind {
+            JobKind::Copy | JobKind::Move => format!("{} {} to {}", kind.label(), count, dest.display()),
+            JobKind::Trash | JobKind::Delete => format!("{} {}", kind.label(), count),
+        };
+        let status = format!("Queued: {}", label);
+        if self.jobs.push(kind, paths, dest, self.preserve_metadata, origin, label) {
+            self.ui.set_status(status);
+        }
+        self.refresh_job_list();
+    }
+
+    /// Show the running job's progress, and its result once it is done
+    ///
+    /// A finished job hands over to the next queued one.
+    fn poll_job(&mut self) {
+        self.ui.queued_jobs = self.jobs.queued_len();
+        let Some((outcome, origin, _)) = self.jobs.poll() else {
+            self.ui.job_progress = self.jobs.running().map(|job| (job.kind, job.progress));
+            return;
+        };
+        self.ui.job_progress = None;
+        self.finish_job(outcome, origin);
+        self.jobs.start_next();
+        self.refresh_job_list();
+    }
+
+    /// Update the operation queue overlay, if it is open, from the running and queued jobs
+    fn refresh_job_list(&mut self) {
+        let Some(list) = self.ui.job_list.as_mut() else {
+            return;
+        };
+        list.active = self.jobs.running_label().map(str::to_string);
+        list.pending = self.jobs.queued_labels().map(str::to_string).collect();
+        list.completed = self.jobs.finished().cloned().collect();
+        list.move_selection(0);
+    }
+
+    /// Keys for the operation queue: move between queued jobs, cancel one, or close
+    fn handle_jobs_key(&mut self, key: KeyCode) {
+        let Some(list) = self.ui.job_list.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => list.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => list.move_selection(1),
+            KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x') => {
+                let index = list.selected;
+                if let Some((origin, label)) = self.jobs.remove_queued(index) {
+                    // A cancelled paste gives its clipboard back
+                    if let JobOrigin::Paste(clipboard) = origin {
+                        self.clipboard = Some(clipboard);
                     }
+                    self.ui.set_status(format!("Cancelled: {}", label));
+                    self.refresh_job_list();
                 }
-                KeyCode::Enter => {
-                    // TODO: Execute selected action
-                    self.ui.show_context_menu = false;
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('J') | KeyCode::Enter => self.set_mode(Mode::Normal),
+            _ => {}
+        }
+    }
+
+    /// Update the clipboard overlay, if it is open, from the clipboard
+    ///
+    /// Directories are totalled in the background, as `u` does.
+    fn refresh_clipboard_list(&mut self) {
+        let Some(list) = self.ui.clipboard_list.as_mut() else {
+            return;
+        };
+        let Some(clipboard) = &self.clipboard else {
+            self.set_mode(Mode::Normal);
+            return;
+        };
+        list.cut = clipboard.mode == ClipboardMode::Cut;
+        list.items = clipboard.paths.iter().map(|path| {
+            let metadata = std::fs::symlink_metadata(path).ok();
+            let is_dir = metadata.as_ref().is_some_and(|metadata| metadata.is_dir());
+            ClipboardItem {
+                path: path.clone(),
+                is_dir,
+                size: metadata.as_ref().filter(|_| !is_dir).map(|metadata| metadata.len()),
+                modified: metadata.as_ref().and_then(|metadata| metadata.modified().ok()),
+                missing: metadata.is_none(),
+            }
+        }).collect();
+        list.move_selection(0);
+        for item in list.items.iter().filter(|item| item.is_dir) {
+            self.dir_sizes.request(&item.path);
+        }
+    }
+
+    /// Keys for the clipboard overlay: move between items, drop one, or close
+    fn handle_clipboard_key(&mut self, key: KeyCode) {
+        let Some(list) = self.ui.clipboard_list.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => list.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => list.move_selection(1),
+            KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x') => {
+                let index = list.selected;
+                let Some(clipboard) = self.clipboard.as_mut() else {
+                    return;
+                };
+                if index >= clipboard.paths.len() {
+                    return;
+                }
+                let path = clipboard.paths.remove(index);
+                let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string();
+                if clipboard.paths.is_empty() {
+                    self.clipboard = None;
+                    self.ui.set_status(format!("Removed {}; clipboard is empty", name));
+                } else {
+                    self.ui.set_status(format!("Removed {} from the clipboard", name));
+                }
+                self.refresh_clipboard_list();
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('C') | KeyCode::Enter => self.set_mode(Mode::Normal),
+            _ => {}
+        }
+    }
+
+    /// Report a finished job and reload the listings it touched
+    ///
+    /// Entries the trash refuses are offered for permanent deletion, without
+    /// taking the place of a prompt opened while the job ran.
+    fn finish_job(&mut self, outcome: JobOutcome, origin: JobOrigin) {
+        let done = outcome.done.len();
+        if outcome.kind == JobKind::Trash {
+            self.trash_bin.invalidate();
+        }
+        match origin {
+            JobOrigin::Paste(mut clipboard) => {
+                let verb = if clipboard.mode == ClipboardMode::Cut { "Moved" } else { "Pasted" };
+                // A cut can only be pasted once; keep whatever didn't move
+                if clipboard.mode == ClipboardMode::Cut {
+                    clipboard.paths.retain(|path| !outcome.done.contains(path));
+                }
+                if !clipboard.paths.is_empty() {
+                    self.clipboard = Some(clipboard);
+                }
+                match outcome.error {
+                    None => self.ui.set_status(format!("{} {}", verb, Self::count_label(done))),
+                    Some(e) => self.ui.set_error(format!("{} {}, then failed: {}", verb, Self::count_label(done), e)),
+                }
+            }
+            JobOrigin::Transfer(dest) => {
+                let verb = if outcome.kind == JobKind::Copy { "Copied" } else { "Moved" };
+                match outcome.error {
+                    None => self.ui.set_status(format!("{} {} to {}", verb, Self::count_label(done), dest.display())),
+                    Some(e) => self.ui.set_error(format!("{} {}, then failed: {}", verb, Self::count_label(done), e)),
+                }
+            }
+            JobOrigin::Delete => {
+                let permanent = outcome.kind == JobKind::Delete;
+                let summary = if permanent {
+                    format!("Deleted {}", Self::count_label(done))
+                } else {
+                    format!("Moved {} to Trash", Self::count_label(done))
+                };
+                match outcome.error {
+                    None => self.ui.set_status(summary),
+                    Some(e) if !permanent => {
+                        self.ui.set_error(format!("{}; {}", summary, e));
+                        let mut retry = DeleteConfirm::new(outcome.failed, true);
+                        retry.trash_failed = true;
+                        // Asked once whatever is open now has been dealt with
+                        self.deferred_delete = Some(retry);
+                    }
+                    Some(e) => self.ui.set_error(format!("{}, failed: {}", summary, e)),
                 }
-                _ => {}
             }
+        }
+
+        self.reload();
+        self.reload_other_pane();
+    }
+
+    /// Re-read the unfocused pane's listing, keeping its selection in range
+    fn reload_other_pane(&mut self) {
+        let Some(other) = self.second_fs.as_mut() else {
             return;
+        };
+        if let Err(e) = other.load_directory() {
+            self.ui.set_error(format!("Reload failed: {}", e));
         }
+        let len = other.entries.len();
+        let state = &mut self.ui.other_pane;
+        state.selected_index = state.selected_index.min(len.saturating_sub(1));
+    }
+
+    /// Open the rename prompt for the selected entry
+    fn start_rename(&mut self) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index) else {
+            return;
+        };
+        let mut prompt = InputPrompt::new(PromptKind::Rename, "Rename", entry.name.clone());
+        prompt.target = Some(entry.path.clone());
+        // Start before the extension, so typing replaces the stem; not for ".bashrc"
+        if let Some(dot) = entry.name.rfind('.').filter(|&dot| dot > 0 && !entry.is_dir) {
+            prompt.cursor = entry.name[..dot].chars().count();
+        }
+        self.open_prompt(prompt);
+    }
+
+    /// Show `prompt` and send keys to it
+    fn open_prompt(&mut self, prompt: InputPrompt) {
+        self.set_mode(Mode::Input(InputKind::Prompt));
+        self.ui.input_prompt = Some(prompt);
+    }
+
+    /// Ask `confirm`'s question, in place of whatever else is open
+    fn confirm_delete(&mut self, confirm: DeleteConfirm) {
+        self.set_mode(Mode::Input(InputKind::ConfirmDelete));
+        self.ui.pending_delete = Some(confirm);
+    }
+
+    /// Copy the selection's absolute path, or just its name, to the system clipboard
+    fn copy_selected_to_clipboard(&mut self, full_path: bool) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index) else {
+            return;
+        };
+        let text = if full_path {
+            std::path::absolute(&entry.path)
+                .unwrap_or_else(|_| entry.path.clone())
+                .display()
+                .to_string()
+        } else {
+            entry.name.clone()
+        };
+
+        // Kept open: on X11 the copied text is served by this process and
+        // goes away with the last `Clipboard`
+        if self.system_clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.system_clipboard = Some(clipboard),
+                Err(e) => {
+                    self.ui.set_error(format!("No system clipboard: {}", e));
+                    return;
+                }
+            }
+        }
+        let Some(clipboard) = self.system_clipboard.as_mut() else {
+            return;
+        };
+        match clipboard.set_text(text) {
+            Ok(()) => self.ui.set_status(if full_path { "Copied path to clipboard" } else { "Copied name to clipboard" }),
+            Err(e) => self.ui.set_error(format!("Copy to clipboard failed: {}", e)),
+        }
+    }
+
+    /// Measure the trash while the sidebar shows it, and report when emptying is done
+    fn poll_trash(&mut self) {
+        if self.ui.show_sidebar {
+            self.trash_bin.request();
+        }
+        if let Some(result) = self.trash_bin.poll() {
+            match result {
+                Ok(freed) => self.ui.set_status(format!(
+                    "Emptied the trash: {} deleted, {} freed",
+                    Self::count_label(freed.items),
+                    crate::fs::format_size(freed.bytes),
+                )),
+                Err(e) => self.ui.set_error(format!("Cannot empty the trash: {}", e)),
+            }
+        }
+        self.ui.trash_size = self.trash_bin.summary.map(|summary| {
+            format!("{}, {}", Self::count_label(summary.items), crate::fs::format_size(summary.bytes))
+        });
+    }
+
+    /// Ask for "empty" to be typed out before the trash is emptied
+    ///
+    /// Nothing in the trash can be brought back afterwards, so a y/n isn't enough.
+    fn start_empty_trash(&mut self) {
+        if !TrashBin::SUPPORTED {
+            self.ui.set_error("Emptying the trash isn't supported on this platform");
+            return;
+        }
+        if self.trash_bin.emptying {
+            self.ui.set_status("The trash is already being emptied");
+            return;
+        }
+        let title = match self.trash_bin.summary {
+            Some(summary) if summary.items == 0 => {
+                self.ui.set_status("The trash is empty");
+                return;
+            }
+            Some(summary) => format!(
+                "Type \"empty\" to delete {} ({}) for good",
+                Self::count_label(summary.items),
+                crate::fs::format_size(summary.bytes),
+            ),
+            None => "Type \"empty\" to delete everything in the trash for good".to_string(),
+        };
+        self.open_prompt(InputPrompt::new(PromptKind::EmptyTrash, title, String::new()));
+    }
+
+    /// Empty the trash if the prompt got "empty"; anything else leaves it
+    fn empty_trash(&mut self, typed: &str) {
+        self.set_mode(Mode::Normal);
+        if typed.trim() != "empty" {
+            self.ui.set_status("Trash left as it is");
+            return;
+        }
+        self.ui.set_status("Emptying the trash…");
+        self.trash_bin.empty();
+    }
+
+    /// Open the selected file in `$EDITOR`, at the line the preview was scrolled to
+    ///
+    /// Without `$EDITOR` the file goes to its default application instead.
+    fn edit_selected(&mut self) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index).filter(|entry| !entry.is_dir) else {
+            return;
+        };
+        let path = entry.path.clone();
+        let Some(editor) = std::env::var("EDITOR").ok().filter(|editor| !editor.trim().is_empty()) else {
+            self.open_externally(&path);
+            return;
+        };
+        // Edits to a file in a disk image only change the extracted copy
+        let Some(path) = self.openable_path(&path) else {
+            return;
+        };
+        let line = self.ui.preview_line(&self.fs);
+        let command = crate::fs::editor_command(&editor, &self.editor_line_flag, &path, line);
+        let dir = match path.parent() {
+            Some(parent) if crate::fs::inside_image(&self.fs.current_path) => parent.to_path_buf(),
+            _ => self.fs.current_path.clone(),
+        };
+        self.run_in_terminal(&command, &dir, None);
+    }
+
+    /// Open the selected entry's web address, from `path_url_map`, in the browser
+    fn open_web_url(&mut self) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index) else {
+            return;
+        };
+        let Some(url) = crate::url_map::web_url(&self.path_url_map, &entry.path) else {
+            self.ui.set_status(format!("No path_url_map entry covers {}", entry.name));
+            return;
+        };
+        match crate::fs::open_external(&url) {
+            Ok(()) => self.ui.set_status(format!("Opened {}", url)),
+            Err(e) => self.ui.set_error(format!("Cannot open {}: {}", url, e)),
+        }
+    }
+
+    /// Open the configured terminal in the current directory, else a subshell
+    ///
+    /// With neither `terminal` nor `$SHELL` set, `$TERMINAL` is tried as a
+    /// terminal emulator.
+    fn open_terminal(&mut self) {
+        let dir = self.fs.current_path.clone();
+        let window = if !self.terminal_command.is_empty() {
+            Some(self.terminal_command.clone())
+        } else if std::env::var_os("SHELL").is_some_and(|shell| !shell.is_empty()) {
+            None
+        } else {
+            std::env::var("TERMINAL").ok().filter(|terminal| !terminal.is_empty()).map(|terminal| vec![terminal])
+        };
+
+        match window {
+            Some(command) => match crate::fs::open_terminal(&command, &dir) {
+                Ok(()) => self.ui.set_status(format!("Opened {} in {}", command[0], dir.display())),
+                Err(e) => self.ui.set_error(format!("{:#}", e)),
+            },
+            None => match std::env::var_os("SHELL").filter(|shell| !shell.is_empty()) {
+                Some(shell) => self.run_in_terminal(&[shell], &dir, Some("Type `exit` to return to GraphTerm")),
+                None => self.ui.set_error("No shell found: set $SHELL, or `terminal` in the config"),
+            },
+        }
+    }
+
+    /// Hand the terminal to `command` (a shell or an editor) in `dir` until it
+    /// exits, then come back; `notice` is printed first
+    fn run_in_terminal(&mut self, command: &[std::ffi::OsString], dir: &Path, notice: Option<&str>) {
+        let Some((program, args)) = command.split_first() else {
+            return;
+        };
+        {
+            use std::io::Write;
+            let _ = io::stdout().write_all(self.graphics.clear_images().as_bytes());
+        }
+        Self::restore_terminal();
+        if let Some(notice) = notice {
+            println!("{}", notice);
+        }
+        let status = std::process::Command::new(program).args(args).current_dir(dir).status();
+
+        let resumed = enable_raw_mode()
+            .and_then(|()| execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture))
+            .and_then(|()| self.terminal.clear());
+        if let Err(e) = resumed {
+            self.ui.set_error(format!("Cannot restore the screen: {}", e));
+        }
+        // The screen was wiped, so draw the graphics again
+        self.shown_icons.clear();
+        self.shown_thumbnail = None;
+        self.drawn_thumbnail = None;
+
+        match status {
+            Ok(_) => {
+                // The shell or editor may well have changed things here
+                self.fs.reload();
+            }
+            Err(e) => self.ui.set_error(format!("Cannot start {}: {}", program.to_string_lossy(), e)),
+        }
+    }
+
+    /// The selected entry, if it is an image
+    fn selected_image(&self) -> Option<PathBuf> {
+        self.fs.get_selected(self.ui.selected_index)
+            .filter(|entry| entry.is_image)
+            .map(|entry| entry.path.clone())
+    }
+
+    /// Make the viewer's image as large as the screen inside its frame
+    ///
+    /// Run when the viewer opens, the selection changes or the screen is
+    /// resized. Leaves the viewer once the selection is no longer an image,
+    /// e.g. after the file was deleted.
+    fn fit_viewer_image(&mut self) {
+        let Some(path) = self.selected_image() else {
+            self.set_mode(Mode::Normal);
+            return;
+        };
+        let Ok(screen) = self.terminal.size() else {
+            return;
+        };
+        let (cell_w, cell_h) = self.graphics.cell_size;
+        let fits = (screen.width.saturating_sub(2) as u32 * cell_w as u32)
+            .min(screen.height.saturating_sub(2) as u32 * cell_h as u32);
+        let size = fits.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+        self.thumbnails.set_zoom(Some((path.as_path(), size)));
+    }
+
+    /// Keys in the viewer: arrows flip through the directory's images, q or Esc goes back
+    fn handle_viewer_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => self.set_mode(Mode::Normal),
+            KeyCode::Left | KeyCode::Up | KeyCode::Backspace => self.step_image(-1),
+            KeyCode::Right | KeyCode::Down | KeyCode::Char(' ') => self.step_image(1),
+            _ => {}
+        }
+    }
+
+    /// Select the next image after (or, with a negative `delta`, before) the selected entry
+    fn step_image(&mut self, delta: i32) {
+        let current = self.ui.selected_index;
+        let found = if delta > 0 {
+            self.fs.entries.iter().enumerate().skip(current + 1).find(|(_, entry)| entry.is_image)
+        } else {
+            self.fs.entries.iter().enumerate().take(current).rev().find(|(_, entry)| entry.is_image)
+        };
+        match found {
+            Some((index, _)) => self.ui.selected_index = index,
+            None => self.ui.set_status(if delta > 0 { "Last image" } else { "First image" }),
+        }
+    }
+
+    /// Whether `position` is on the preview pane while it shows an image
+    fn over_image_preview(&self, position: Position) -> bool {
+        self.ui.preview_area.is_some_and(|area| area.contains(position))
+            && self.fs.get_selected(self.ui.selected_index).is_some_and(|entry| entry.is_image)
+    }
+
+    /// Zoom the selected image in or out a step, regenerating its preview
+    ///
+    /// Zooming in stops once the image fills the space below the text.
+    fn zoom_preview(&mut self, zoom_in: bool) {
+        let Some(path) = self.fs.get_selected(self.ui.selected_index).map(|entry| entry.path.clone()) else {
+            return;
+        };
+        let base = self.thumbnails.size() as f32;
+        let zoom = if zoom_in { self.zoom * ZOOM_STEP } else { self.zoom / ZOOM_STEP };
+        let mut zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        if let Some(area) = self.ui.thumbnail_area.filter(|_| zoom > 1.0) {
+            let (cell_w, cell_h) = self.graphics.cell_size;
+            let fits = (area.width * cell_w).min(area.height * cell_h) as f32;
+            zoom = zoom.min((fits / base).max(1.0));
+        }
+        if zoom == self.zoom {
+            return;
+        }
+
+        self.zoom = zoom;
+        let size = ((base * zoom).round() as u32).clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+        self.thumbnails.set_zoom((zoom != 1.0).then_some((path.as_path(), size)));
+        self.ui.set_status(format!("Zoom {}%", (zoom * 100.0).round()));
+    }
+
+    /// Scale thumbnails up or down a step; the one shown is regenerated at once
+    fn resize_thumbnails(&mut self, larger: bool) {
+        let size = self.thumbnails.size();
+        let size = if larger {
+            size * THUMBNAIL_SIZE_STEP / 4
+        } else {
+            size * 4 / THUMBNAIL_SIZE_STEP
+        };
+        let size = size.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+        if size == self.thumbnails.size() {
+            let limit = if larger { "largest" } else { "smallest" };
+            self.ui.set_status(format!("Thumbnails are at their {} ({} px)", limit, size));
+            return;
+        }
+        self.thumbnails.set_size(size);
+        self.ui.set_status(format!("Thumbnails {} px", size));
+    }
+
+    /// Add the current directory to the sidebar and save the list
+    fn add_bookmark(&mut self) {
+        let bookmark = Bookmark::new(self.fs.current_path.clone());
+        if self.ui.bookmarks.iter().any(|existing| existing.path == bookmark.path) {
+            self.ui.set_status(format!("{} is already bookmarked", bookmark.name));
+            return;
+        }
+        let message = format!("Bookmarked {}", bookmark.name);
+        self.ui.bookmarks.push(bookmark);
+        self.save_bookmarks(message);
+    }
+
+    /// Remove the highlighted sidebar entry: the bookmark the current directory is in
+    fn remove_bookmark(&mut self) {
+        let Some(index) = self.ui.current_bookmark(&self.fs) else {
+            self.ui.set_status("No bookmark contains the current directory");
+            return;
+        };
+        let removed = self.ui.bookmarks.remove(index);
+        self.save_bookmarks(format!("Removed bookmark {}", removed.name));
+    }
+
+    /// Write the sidebar to the config file, reporting `message` once saved
+    ///
+    /// An empty sidebar is saved as no bookmarks, and shows the standard folders again.
+    fn save_bookmarks(&mut self, message: String) {
+        let result = bookmarks::save(&self.ui.bookmarks);
+        if self.ui.bookmarks.is_empty() {
+            self.ui.bookmarks = Bookmark::defaults();
+        }
+        match result {
+            Ok(()) => self.ui.set_status(message),
+            Err(e) => self.ui.set_error(format!("Cannot save bookmarks: {:#}", e)),
+        }
+    }
+
+    /// Handle typing into the command palette, running the highlighted action on Enter
+    fn handle_palette_key(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        let Some(palette) = self.ui.command_palette.as_mut() else {
+            return;
+        };
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
 
-        let total = self.fs.entries.len();
         match key {
-            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
-            KeyCode::Up | KeyCode::Char('k') => self.ui.move_selection(-1, total),
-            KeyCode::Down | KeyCode::Char('j') => self.ui.move_selection(1, total),
-            KeyCode::F(1) => self.ui.toggle_sidebar(),
-            KeyCode::F(2) => self.ui.toggle_preview(),
+            KeyCode::Esc => self.set_mode(Mode::Normal),
+            KeyCode::Char('p') if ctrl => self.set_mode(Mode::Normal),
+            KeyCode::Up => palette.move_selection(-1),
+            KeyCode::Down => palette.move_selection(1),
+            KeyCode::Char('k') if ctrl => palette.move_selection(-1),
+            KeyCode::Char('j') if ctrl => palette.move_selection(1),
+            KeyCode::Backspace => palette.pop(),
+            KeyCode::Char(c) if !ctrl => palette.push(c),
             KeyCode::Enter => {
-                if let Some(entry) = self.fs.get_selected(self.ui.selected_index) {
-                    if entry.is_dir {
-                        let name = entry.name.clone();
-                        let _ = self.fs.enter_directory(&name);
-                        self.ui.selected_index = 0;
+                let action = palette.selected_action();
+                self.set_mode(Mode::Normal);
+                match action {
+                    Some(action) if self.fs.is_loading() && !action.allowed_while_loading() => {
+                        self.ui.set_status("Still loading the directory");
                     }
+                    Some(action) => self.run_action(action),
+                    None => {}
                 }
             }
-            KeyCode::Backspace => {
-                let _ = self.fs.go_up();
-                self.ui.selected_index = 0;
+            _ => {}
+        }
+    }
+
+    /// Handle typing into the active input prompt
+    fn handle_prompt_key(&mut self, key: KeyCode) {
+        let Some(prompt) = self.ui.input_prompt.as_mut() else {
+            return;
+        };
+
+        match key {
+            KeyCode::Esc => self.set_mode(Mode::Normal),
+            KeyCode::Backspace => prompt.backspace(),
+            KeyCode::Delete => prompt.delete(),
+            KeyCode::Left => prompt.move_left(),
+            KeyCode::Right => prompt.move_right(),
+            KeyCode::Home => prompt.move_home(),
+            KeyCode::End => prompt.move_end(),
+            KeyCode::Char(c) => prompt.insert(c),
+            KeyCode::Tab if prompt.kind == PromptKind::GoTo => {
+                match crate::fs::complete_dir_path(&prompt.value, &self.fs.current_path) {
+                    Some(completed) => prompt.set_value(completed),
+                    None => self.ui.set_status("No matching directory"),
+                }
+            }
+            KeyCode::Enter => {
+                let value = prompt.value.clone();
+                match prompt.kind {
+                    PromptKind::Rename => self.rename_selected(&value),
+                    PromptKind::GoTo => self.go_to_path(&value),
+                    PromptKind::NewFile | PromptKind::NewDirectory => self.create_entry(&value),
+                    PromptKind::EmptyTrash => self.empty_trash(&value),
+                }
             }
-            KeyCode::Home => self.ui.selected_index = 0,
-            KeyCode::End => self.ui.selected_index = total.saturating_sub(1),
             _ => {}
         }
     }
 
+    /// Handle typing into the `/` filter, narrowing the listing live
+    fn handle_filter_key(&mut self, key: KeyCode) {
+        let mut query = self.fs.filter.clone();
+        match key {
+            // Esc drops the filter, Enter keeps it and returns to navigation
+            KeyCode::Esc => {
+                self.set_mode(Mode::Normal);
+                query.clear();
+            }
+            KeyCode::Enter => {
+                self.set_mode(Mode::Normal);
+                return;
+            }
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(c) => query.push(c),
+            _ => return,
+        }
+        self.fs.set_filter(&query);
+        self.reset_selection();
+    }
+
+    /// Rename the entry the prompt was opened on and select it under its new name
+    ///
+    /// The prompt stays open on failure so the name can be corrected.
+    fn rename_selected(&mut self, new_name: &str) {
+        let Some(target) = self.ui.input_prompt.as_ref().and_then(|prompt| prompt.target.clone()) else {
+            self.set_mode(Mode::Normal);
+            return;
+        };
+        if target.file_name().is_some_and(|name| name.to_string_lossy() == new_name.trim()) {
+            self.set_mode(Mode::Normal);
+            return;
+        }
+
+        match crate::fs::rename(&target, new_name) {
+            Ok(path) => {
+                self.last_rename = Some((target, path.clone()));
+                self.set_mode(Mode::Normal);
+                self.ui.set_status(format!("Renamed to {}", new_name.trim()));
+                self.reload();
+                self.select_path(&path);
+            }
+            Err(e) => self.ui.set_error(format!("Rename failed: {}", e)),
+        }
+    }
+
+    /// Rename the last renamed entry back, if nothing has taken its place
+    ///
+    /// The new name has to still exist and the old one has to be free;
+    /// otherwise the rename is kept and can't be undone.
+    fn undo_rename(&mut self) {
+        let Some((old, new)) = self.last_rename.take() else {
+            self.ui.set_status("Nothing to undo");
+            return;
+        };
+        let exists = |path: &Path| path.symlink_metadata().is_ok();
+        let old_name = old.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !exists(&new) || exists(&old) {
+            self.ui.set_error(format!("Cannot undo the rename of {}: the files have changed since", old_name));
+            return;
+        }
+
+        match crate::fs::rename(&new, &old_name) {
+            Ok(path) => {
+                self.ui.set_status(format!("Renamed back to {}", old_name));
+                self.reload();
+                self.select_path(&path);
+            }
+            Err(e) => self.ui.set_error(format!("Cannot undo the rename: {}", e)),
+        }
+    }
+
+    /// Create the file or directory named in the prompt and select it
+    ///
+    /// Like rename, the prompt stays open on failure.
+    fn create_entry(&mut self, name: &str) {
+        let Some(kind) = self.ui.input_prompt.as_ref().map(|prompt| prompt.kind) else {
+            return;
+        };
+        let result = if kind == PromptKind::NewDirectory {
+            crate::fs::create_dir(&self.fs.current_path, name)
+        } else {
+            crate::fs::create_file(&self.fs.current_path, name)
+        };
+
+        match result {
+            Ok(path) => {
+                self.set_mode(Mode::Normal);
+                self.ui.set_status(format!("Created {}", name.trim()));
+                // A new dotfile stays hidden until hidden files are shown
+                self.reload();
+                self.select_path(&path);
+            }
+            Err(e) => self.ui.set_error(format!("{:#}", e)),
+        }
+    }
+
+    /// Select the entry at `path`, if it is listed
+    fn select_path(&mut self, path: &std::path::Path) {
+        if let Some(index) = self.fs.entries.iter().position(|e| e.path == path) {
+            self.ui.selected_index = index;
+            self.ui.ensure_visible(self.ui.visible_height());
+        }
+    }
+
+    /// Jump to a typed path; the prompt stays open if it isn't a directory
+    fn go_to_path(&mut self, input: &str) {
+        let input = input.trim();
+        if input.is_empty() {
+            self.set_mode(Mode::Normal);
+            return;
+        }
+
+        let path = self.fs.current_path.join(crate::fs::expand_tilde(input));
+        match path.canonicalize() {
+            Ok(path) if path.is_dir() => {
+                self.set_mode(Mode::Normal);
+                self.fs.enter_directory(&path);
+            }
+            Ok(_) => self.ui.set_error(format!("Not a directory: {}", input)),
+            Err(e) => self.ui.set_error(format!("Cannot go to {}: {}", input, e)),
+        }
+    }
+
+    /// Toggle writability (or the immutable flag) of the selected entry
+    fn toggle_selected_flag(&mut self, immutable: bool) {
+        let Some(entry) = self.fs.get_selected(self.ui.selected_index) else {
+            return;
+        };
+
+        let result = if immutable {
+            match crate::fs::toggle_immutable(&entry.path) {
+                Ok(true) => Ok(format!("{} is now immutable", entry.name)),
+                Ok(false) => Ok(format!("{} is no longer immutable", entry.name)),
+                Err(e) => Err(format!("Cannot change immutable flag: {}", e)),
+            }
+        } else {
+            match crate::fs::toggle_writable(&entry.path) {
+                Ok(true) => Ok(format!("{} is now writable", entry.name)),
+                Ok(false) => Ok(format!("{} is now read-only", entry.name)),
+                Err(e) => Err(format!("Cannot change permissions: {}", e)),
+            }
+        };
+        match result {
+            Ok(message) => {
+                // The preview shows the flags as they were when the directory was read
+                self.ui.forget_immutable_flag();
+                self.fs.reload();
+                self.ui.set_status(message);
+            }
+            Err(message) => self.ui.set_error(message),
+        }
+    }
+
     /// Handle mouse input
     fn handle_mouse(&mut self, mouse: event::MouseEvent) {
+        // Entries on screen are stale until the load finishes
+        if self.fs.is_loading() {
+            return;
+        }
+
+        match self.ui.mode {
+            Mode::Normal => self.handle_normal_mouse(mouse),
+            Mode::ContextMenu => self.handle_context_menu_mouse(mouse),
+            // A click would move the selection out from under the dialog
+            Mode::Input(_) => {}
+            Mode::Search => {
+                // Clicking elsewhere keeps the filter, as Enter does
+                if matches!(mouse.kind, MouseEventKind::Down(_)) {
+                    self.set_mode(Mode::Normal);
+                }
+                self.handle_normal_mouse(mouse);
+            }
+            Mode::Viewer => self.handle_viewer_mouse(mouse),
+            Mode::Help => self.handle_palette_mouse(mouse),
+            // The queue and clipboard are driven from the keyboard; a click outside closes them
+            Mode::Jobs | Mode::Clipboard => {
+                if matches!(mouse.kind, MouseEventKind::Down(_)) {
+                    self.set_mode(Mode::Normal);
+                }
+            }
+        }
+    }
+
+    /// Hovering highlights a menu item and clicking runs it; a click outside closes the menu
+    ///
+    /// A right-click outside opens a fresh menu where it landed.
+    fn handle_context_menu_mouse(&mut self, mouse: event::MouseEvent) {
+        let item = self.ui.context_menu_item_at(mouse.row, mouse.column);
+        match (mouse.kind, item) {
+            (MouseEventKind::Moved, Some(index)) => self.ui.context_menu_selected = index,
+            (MouseEventKind::Down(event::MouseButton::Left), Some(index)) => {
+                self.ui.context_menu_selected = index;
+                self.choose_context_menu_item();
+            }
+            (MouseEventKind::Down(button), None) => {
+                self.set_mode(Mode::Normal);
+                if button == event::MouseButton::Right {
+                    self.handle_normal_mouse(mouse);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The wheel moves the palette's highlight; a click outside closes it
+    fn handle_palette_mouse(&mut self, mouse: event::MouseEvent) {
+        let Some(palette) = self.ui.command_palette.as_mut() else {
+            return;
+        };
+        match mouse.kind {
+            MouseEventKind::ScrollUp => palette.move_selection(-1),
+            MouseEventKind::ScrollDown => palette.move_selection(1),
+            MouseEventKind::Down(_) => {
+                let inside = self.ui.palette_area
+                    .is_some_and(|area| area.contains(Position::new(mouse.column, mouse.row)));
+                if !inside {
+                    self.set_mode(Mode::Normal);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The wheel flips through images in the viewer; nothing else is clickable
+    fn handle_viewer_mouse(&mut self, mouse: event::MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.step_image(-1),
+            MouseEventKind::ScrollDown => self.step_image(1),
+            _ => {}
+        }
+    }
+
+    /// Clicks, the wheel, and hovering on the file list, sidebar, and preview
+    fn handle_normal_mouse(&mut self, mouse: event::MouseEvent) {
+        // Clicking the other pane gives it focus before the click is handled
+        if self.ui.dual_pane
+            && matches!(mouse.kind, MouseEventKind::Down(_))
+            && self.ui.other_grid_area.contains(Position::new(mouse.column, mouse.row))
+        {
+            self.switch_pane();
+            // Hit-testing uses the focused grid area, which is only updated on render
+            std::mem::swap(&mut self.ui.file_grid_area, &mut self.ui.other_grid_area);
+            std::mem::swap(&mut self.ui.icon_grid, &mut self.ui.other_icon_grid);
+            self.last_click = None;
+        }
+
+        if mouse.kind == MouseEventKind::Moved {
+            self.ui.sidebar_hover = self.ui.bookmark_at(mouse.row, mouse.column);
+            return;
+        }
+
+        // Pressing on the scrollbar jumps there, and the view follows while the button is held
+        match mouse.kind {
+            MouseEventKind::Down(event::MouseButton::Left) => {
+                let position = Position::new(mouse.column, mouse.row);
+                self.dragging_scrollbar = self.ui.scrollbar_area.is_some_and(|track| track.contains(position));
+            }
+            MouseEventKind::Up(event::MouseButton::Left) => self.dragging_scrollbar = false,
+            _ => {}
+        }
+        if self.dragging_scrollbar
+            && matches!(mouse.kind, MouseEventKind::Down(event::MouseButton::Left) | MouseEventKind::Drag(event::MouseButton::Left))
+        {
+            self.ui.scroll_to_track_row(mouse.row, self.fs.entries.len());
+            return;
+        }
+
+        // Clicking a bookmark goes there; the selection resets once it has loaded
+        if let (MouseEventKind::Down(event::MouseButton::Left), Some(index)) =
+            (mouse.kind, self.ui.bookmark_at(mouse.row, mouse.column))
+        {
+            let path = self.ui.bookmarks[index].path.clone();
+            self.fs.enter_directory(&path);
+            return;
+        }
+
+        // Clicking the sort indicator in the grid's title moves on to the next sort mode
+        if mouse.kind == MouseEventKind::Down(event::MouseButton::Left)
+            && self.ui.sort_indicator_at(&self.fs, mouse.row, mouse.column)
+        {
+            self.fs.set_sort(self.fs.sort_mode.next());
+            self.reset_selection();
+            return;
+        }
+
         match mouse.kind {
             MouseEventKind::Down(event::MouseButton::Left) => {
                 // Calculate which file was clicked based on mouse position
                 let clicked_index = self.ui.get_item_at_position(mouse.row, mouse.column);
-                if let Some(index) = clicked_index {
+                if let Some(index) = clicked_index.filter(|&i| i < self.fs.entries.len()) {
+                    // Shift-click marks everything from the anchor to the click; Ctrl-click
+                    // marks or unmarks just the clicked entry
+                    if mouse.modifiers.contains(KeyModifiers::SHIFT) {
+                        let anchor = self.range_anchor
+                            .filter(|&anchor| anchor < self.fs.entries.len())
+                            .unwrap_or(self.ui.selected_index);
+                        let (start, end) = (anchor.min(index), anchor.max(index));
+                        for entry in self.fs.entries.iter().take(end + 1).skip(start) {
+                            self.ui.marked.insert(entry.path.clone());
+                        }
+                        self.range_anchor = Some(anchor);
+                    } else {
+                        if mouse.modifiers.contains(KeyModifiers::CONTROL) {
+                            self.ui.toggle_mark(&self.fs.entries[index].path);
+                        }
+                        self.range_anchor = Some(index);
+                    }
                     self.ui.selected_index = index;
+                    if mouse.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::CONTROL) {
+                        self.last_click = None;
+                        return;
+                    }
+
+                    // A second click on the same entry opens it
+                    let double_click = self.last_click
+                        .is_some_and(|(at, last)| last == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL);
+                    if double_click {
+                        self.last_click = None;
+                        self.open_selected();
+                    } else {
+                        self.last_click = Some((Instant::now(), index));
+                    }
+                } else {
+                    self.last_click = None;
                 }
             }
             MouseEventKind::Down(event::MouseButton::Right) => {
-                // TODO: Context menu
-                self.ui.show_context_menu = true;
+                // The menu applies to the entry under the pointer; empty space gets a reduced menu
+                let clicked = self.ui.get_item_at_position(mouse.row, mouse.column)
+                    .filter(|&i| i < self.fs.entries.len());
+                if let Some(index) = clicked {
+                    self.ui.selected_index = index;
+                }
+                self.ui.context_menu_target = clicked.map(|index| self.fs.entries[index].path.clone());
+                self.ui.context_menu_selected = 0;
+                self.set_mode(Mode::ContextMenu);
                 self.ui.context_menu_pos = (mouse.column, mouse.row);
             }
-            MouseEventKind::ScrollUp => self.ui.scroll(-3),
-            MouseEventKind::ScrollDown => self.ui.scroll(3),
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown if self.over_image_preview(Position::new(mouse.column, mouse.row)) => {
+                self.zoom_preview(mouse.kind == MouseEventKind::ScrollUp);
+            }
+            // Over a text preview the wheel scrolls the file instead of the list
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                if self.ui.preview_area.is_some_and(|area| area.contains(Position::new(mouse.column, mouse.row))) =>
+            {
+                let action = if mouse.kind == MouseEventKind::ScrollUp { Action::PreviewUp } else { Action::PreviewDown };
+                self.run_action(action);
+            }
+            MouseEventKind::ScrollUp => self.ui.scroll(-3, self.fs.entries.len(), self.ui.visible_height()),
+            MouseEventKind::ScrollDown => self.ui.scroll(3, self.fs.entries.len(), self.ui.visible_height()),
             _ => {}
         }
     }
@@ -184,13 +2015,14 @@ impl App {
 
 impl Drop for App {
     fn drop(&mut self) {
+        // Images outlive the alternate screen in Kitty, so remove them first
+        use std::io::Write;
+        let backend = self.terminal.backend_mut();
+        let _ = backend.write_all(self.graphics.release_images().as_bytes());
+        let _ = backend.flush();
+
         // Restore terminal state
-        let _ = disable_raw_mode();
-        let _ = execute!(
-            self.terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        );
+        Self::restore_terminal();
         let _ = self.terminal.show_cursor();
     }
 }
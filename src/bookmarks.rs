@@ -0,0 +1,142 @@
+//! Sidebar bookmarks: the user's own from the config file, else the standard folders
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable};
+
+use crate::config::Config;
+
+/// A directory listed in the sidebar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Bookmark {
+    /// Bookmark `path`, named after its last component
+    pub fn new(path: PathBuf) -> Self {
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Self { name, path }
+    }
+
+    /// Home and the standard folders the platform knows about
+    pub fn defaults() -> Vec<Self> {
+        let folders = [
+            ("Home", dirs::home_dir()),
+            ("Documents", dirs::document_dir()),
+            ("Downloads", dirs::download_dir()),
+            ("Pictures", dirs::picture_dir()),
+            ("Music", dirs::audio_dir()),
+            ("Desktop", dirs::desktop_dir()),
+        ];
+        folders
+            .into_iter()
+            .filter_map(|(name, path)| Some(Self { name: name.to_string(), path: path? }))
+            .collect()
+    }
+
+    /// Emoji for the sidebar; standard folders keep their own
+    pub fn icon(&self) -> &'static str {
+        let is = |dir: Option<PathBuf>| dir.is_some_and(|dir| dir == self.path);
+        if is(dirs::home_dir()) {
+            "🏠"
+        } else if is(dirs::document_dir()) {
+            "📄"
+        } else if is(dirs::download_dir()) {
+            "⬇️"
+        } else if is(dirs::picture_dir()) {
+            "🖼️"
+        } else if is(dirs::audio_dir()) {
+            "🎵"
+        } else if is(dirs::desktop_dir()) {
+            "💻"
+        } else {
+            "📁"
+        }
+    }
+}
+
+/// Write `bookmarks` to the config file
+///
+/// Only the `bookmarks` key is replaced, so the rest of the file, comments
+/// included, stays as the user wrote it. An empty list removes the key,
+/// which brings back the defaults.
+pub fn save(bookmarks: &[Bookmark]) -> Result<()> {
+    let path = Config::path().context("no config directory on this platform")?;
+    save_to(&path, bookmarks)
+}
+
+fn save_to(path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context("cannot read config"),
+    };
+    let mut document: DocumentMut = text.parse().context("cannot parse config")?;
+
+    if bookmarks.is_empty() {
+        document.remove("bookmarks");
+    } else {
+        let mut array = Array::new();
+        for bookmark in bookmarks {
+            let mut table = InlineTable::new();
+            table.insert("name", bookmark.name.as_str().into());
+            table.insert("path", contract_tilde(&bookmark.path).into());
+            array.push(table);
+        }
+        // One bookmark per line
+        for value in array.iter_mut() {
+            value.decor_mut().set_prefix("\n    ");
+        }
+        array.set_trailing("\n");
+        array.set_trailing_comma(true);
+        document["bookmarks"] = toml_edit::value(array);
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("cannot create config directory")?;
+    }
+    std::fs::write(path, document.to_string()).context("cannot write config")
+}
+
+/// `path` with the home directory written as `~`, the inverse of `expand_tilde`
+fn contract_tilde(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_save_keeps_the_rest_of_the_config() {
+        let dir = TempDir::new("bookmarks");
+        let root = dir.path();
+        let path = root.join("config.toml");
+        std::fs::write(&path, "# mine\nshow_hidden = true\n\n[colors]\ndirectory = \"cyan\"\n").unwrap();
+
+        let bookmarks = vec![
+            Bookmark::new(PathBuf::from("/srv/projects")),
+            Bookmark { name: "Logs".to_string(), path: PathBuf::from("/var/log") },
+        ];
+        save_to(&path, &bookmarks).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# mine\nshow_hidden = true\n"));
+
+        let (config, warnings) = Config::parse(&text).unwrap();
+        assert!(warnings.is_empty());
+        assert!(config.show_hidden);
+        assert_eq!(config.bookmarks, bookmarks);
+
+        save_to(&path, &[]).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("bookmarks"));
+    }
+}
@@ -0,0 +1,451 @@
+//! User settings from `config.toml`
+//!
+//! Read once at startup from `dirs::config_dir()/graphterm/config.toml`. A
+//! missing file means the defaults; a file that doesn't parse is reported on
+//! stderr and ignored, so a typo never keeps the app from starting.
+//! Bookmarks edited in the app are written back by `bookmarks::save`.
+
+use anyhow::{Context, Result};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::bookmarks::Bookmark;
+use crate::graphics::thumbnails::{ResizeQuality, DEFAULT_CACHE_BYTES, DEFAULT_CACHE_ENTRIES, DEFAULT_THUMBNAIL_SIZE, IMAGE_EXTENSIONS, MAX_THUMBNAIL_SIZE, MIN_THUMBNAIL_SIZE};
+use crate::keymap::Keymap;
+use crate::url_map::UrlMapping;
+use crate::ui::Theme;
+
+/// Seconds the selection rests before off-screen Kitty images are freed
+const DEFAULT_IDLE_CLEANUP_SECS: u64 = 60;
+
+/// Settings after validation, with defaults filled in
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory to open instead of the working directory
+    pub start_dir: Option<PathBuf>,
+    pub show_hidden: bool,
+    pub show_sidebar: bool,
+    pub show_preview: bool,
+    /// Start with just the file list, as with `--minimal`
+    pub minimal: bool,
+    /// Longest thumbnail side, in pixels
+    pub thumbnail_size: u32,
+    pub thumbnail_quality: ResizeQuality,
+    /// Thumbnails kept in memory at most
+    pub thumbnail_cache_entries: usize,
+    /// Memory budget for cached thumbnails, in bytes
+    pub thumbnail_cache_bytes: usize,
+    /// Bytes of a binary file shown in its hex preview
+    pub hex_preview_bytes: usize,
+    /// Show extensions in their own column in the file list
+    pub split_extension: bool,
+    /// Show each entry's last commit in git repositories
+    pub git_column: bool,
+    /// Extensions of files previewed and listed as images, lowercase without the dot
+    pub image_extensions: Vec<String>,
+    /// Walk through directories with a single subdirectory on Enter
+    pub auto_descend_single: bool,
+    /// Delete removes files for good instead of moving them to the trash
+    pub permanent_delete: bool,
+    /// Copies keep the modification time, permissions, and (where allowed) owner of the original
+    pub preserve_metadata: bool,
+    /// Wrap graphics for tmux when running inside it; off for tmux without `allow-passthrough`
+    pub tmux_passthrough: bool,
+    /// Free the Kitty images that aren't on screen once the selection has
+    /// rested this long; None keeps them until exit
+    pub idle_image_cleanup: Option<Duration>,
+    /// Sidebar entries; empty shows the standard folders
+    pub bookmarks: Vec<Bookmark>,
+    /// Terminal emulator and arguments for `!`; empty runs `$SHELL` in place
+    pub terminal: Vec<String>,
+    /// Arguments that make `$EDITOR` open at a line; `{line}` and `{path}` are filled in
+    pub editor_line_flag: String,
+    /// Local folders and the web addresses their files have, for `open_web_url`
+    pub path_url_map: Vec<UrlMapping>,
+    pub keymap: Keymap,
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            start_dir: None,
+            show_hidden: false,
+            show_sidebar: true,
+            show_preview: true,
+            minimal: false,
+            thumbnail_size: DEFAULT_THUMBNAIL_SIZE,
+            thumbnail_quality: ResizeQuality::Fast,
+            thumbnail_cache_entries: DEFAULT_CACHE_ENTRIES,
+            thumbnail_cache_bytes: DEFAULT_CACHE_BYTES,
+            hex_preview_bytes: crate::fs::DEFAULT_HEX_PREVIEW_BYTES,
+            split_extension: false,
+            git_column: false,
+            image_extensions: IMAGE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            auto_descend_single: false,
+            permanent_delete: false,
+            preserve_metadata: true,
+            tmux_passthrough: true,
+            idle_image_cleanup: Some(Duration::from_secs(DEFAULT_IDLE_CLEANUP_SECS)),
+            bookmarks: Vec::new(),
+            terminal: Vec::new(),
+            editor_line_flag: "+{line}".to_string(),
+            path_url_map: Vec::new(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// The file as written; every field is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    start_dir: Option<String>,
+    show_hidden: Option<bool>,
+    show_sidebar: Option<bool>,
+    show_preview: Option<bool>,
+    minimal: Option<bool>,
+    thumbnail_size: Option<u32>,
+    thumbnail_quality: Option<ResizeQuality>,
+    thumbnail_cache_entries: Option<usize>,
+    /// Megabytes
+    thumbnail_cache_mb: Option<usize>,
+    hex_preview_bytes: Option<usize>,
+    split_extension: Option<bool>,
+    git_column: Option<bool>,
+    image_extensions: Option<Vec<String>>,
+    auto_descend_single: Option<bool>,
+    permanent_delete: Option<bool>,
+    preserve_metadata: Option<bool>,
+    tmux_passthrough: Option<bool>,
+    idle_image_cleanup: Option<bool>,
+    idle_image_cleanup_secs: Option<u64>,
+    bookmarks: Vec<BookmarkFile>,
+    terminal: Option<CommandLine>,
+    editor_line_flag: Option<String>,
+    /// Local prefix to URL template
+    path_url_map: BTreeMap<String, String>,
+    colors: ColorsFile,
+    /// Action name to one chord or a list of chords
+    keys: BTreeMap<String, Chords>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColorsFile {
+    selection_bg: Option<String>,
+    selection_fg: Option<String>,
+    marked_bg: Option<String>,
+    directory: Option<String>,
+    file: Option<String>,
+    path: Option<String>,
+    dialog_bg: Option<String>,
+}
+
+/// `{ name = "Projects", path = "~/projects" }`; the name defaults to the last component
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BookmarkFile {
+    name: Option<String>,
+    path: String,
+}
+
+/// `quit = "q"` or `quit = ["q", "ctrl+c"]`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Chords {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `terminal = "foot -e fish"`, split at whitespace, or a list of arguments
+/// taken as written, for ones containing spaces
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CommandLine {
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl Config {
+    /// Location of the config file, if the platform has a config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("graphterm").join("config.toml"))
+    }
+
+    /// Load the config file, printing problems to stderr
+    ///
+    /// Must run before the terminal enters the alternate screen, or the
+    /// messages are lost.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match Self::load_from(&path) {
+            Ok((config, warnings)) => {
+                for warning in warnings {
+                    eprintln!("[GraphTerm] {}: {}", path.display(), warning);
+                }
+                config
+            }
+            Err(e) => {
+                eprintln!("[GraphTerm] Ignoring {}: {:#}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Read and parse `path`; a missing file gives the defaults
+    fn load_from(path: &Path) -> Result<(Self, Vec<String>)> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok((Self::default(), Vec::new())),
+            Err(e) => Err(e).context("cannot read config"),
+        }
+    }
+
+    /// Parse config text, returning the config and warnings for skipped values
+    pub(crate) fn parse(text: &str) -> Result<(Self, Vec<String>)> {
+        let file: ConfigFile = toml::from_str(text)?;
+        let defaults = Self::default();
+        let mut warnings = Vec::new();
+
+        let start_dir = file.start_dir.and_then(|dir| {
+            let path = crate::fs::expand_tilde(&dir);
+            if path.is_dir() {
+                Some(path)
+            } else {
+                warnings.push(format!("start_dir `{}` is not a directory", dir));
+                None
+            }
+        });
+
+        let thumbnail_size = match file.thumbnail_size {
+            Some(size @ MIN_THUMBNAIL_SIZE..=MAX_THUMBNAIL_SIZE) => size,
+            Some(size) => {
+                warnings.push(format!(
+                    "thumbnail_size {} is outside {}–{}",
+                    size, MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE
+                ));
+                defaults.thumbnail_size
+            }
+            None => defaults.thumbnail_size,
+        };
+
+        let thumbnail_cache_entries = match file.thumbnail_cache_entries {
+            Some(0) => {
+                warnings.push("thumbnail_cache_entries must be at least 1".to_string());
+                defaults.thumbnail_cache_entries
+            }
+            Some(entries) => entries,
+            None => defaults.thumbnail_cache_entries,
+        };
+        let thumbnail_cache_bytes = file.thumbnail_cache_mb
+            .map_or(defaults.thumbnail_cache_bytes, |mb| mb.saturating_mul(1024 * 1024));
+
+        let hex_preview_bytes = match file.hex_preview_bytes {
+            Some(bytes @ 16..=65536) => bytes,
+            Some(bytes) => {
+                warnings.push(format!("hex_preview_bytes {} is outside 16–65536", bytes));
+                defaults.hex_preview_bytes
+            }
+            None => defaults.hex_preview_bytes,
+        };
+
+        // "PNG" and ".png" both mean png
+        let image_extensions = match file.image_extensions {
+            Some(extensions) => extensions
+                .into_iter()
+                .filter_map(|ext| {
+                    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+                    if ext.is_empty() {
+                        warnings.push("image_extensions has an empty entry".to_string());
+                    }
+                    (!ext.is_empty()).then_some(ext)
+                })
+                .collect(),
+            None => defaults.image_extensions,
+        };
+
+        let idle_cleanup_secs = match file.idle_image_cleanup_secs {
+            Some(0) => {
+                warnings.push("idle_image_cleanup_secs must be at least 1".to_string());
+                DEFAULT_IDLE_CLEANUP_SECS
+            }
+            Some(secs) => secs,
+            None => DEFAULT_IDLE_CLEANUP_SECS,
+        };
+        let idle_image_cleanup = file.idle_image_cleanup
+            .unwrap_or(defaults.idle_image_cleanup.is_some())
+            .then(|| Duration::from_secs(idle_cleanup_secs));
+
+        let bookmarks = file.bookmarks
+            .into_iter()
+            .map(|bookmark| {
+                let path = crate::fs::expand_tilde(&bookmark.path);
+                match bookmark.name {
+                    Some(name) => Bookmark { name, path },
+                    None => Bookmark::new(path),
+                }
+            })
+            .collect();
+
+        let mut terminal: Vec<String> = match &file.terminal {
+            Some(CommandLine::Line(command)) => command.split_whitespace().map(str::to_string).collect(),
+            Some(CommandLine::Args(args)) => args.clone(),
+            None => Vec::new(),
+        };
+        if file.terminal.is_some() && terminal.first().is_none_or(|program| program.trim().is_empty()) {
+            warnings.push("terminal is empty".to_string());
+            terminal.clear();
+        }
+
+        let path_url_map = file.path_url_map
+            .into_iter()
+            .map(|(prefix, template)| {
+                if !template.contains("{path}") {
+                    warnings.push(format!("path_url_map entry for {} has no {{path}}", prefix));
+                }
+                UrlMapping { prefix: crate::fs::expand_tilde(&prefix), template }
+            })
+            .collect();
+
+        let overrides: Vec<(String, Vec<String>)> = file.keys
+            .into_iter()
+            .map(|(action, chords)| match chords {
+                Chords::One(chord) => (action, vec![chord]),
+                Chords::Many(chords) => (action, chords),
+            })
+            .collect();
+        let (keymap, key_warnings) = Keymap::with_overrides(&overrides);
+        warnings.extend(key_warnings);
+
+        let theme = file.colors.apply(defaults.theme, &mut warnings);
+
+        Ok((
+            Self {
+                start_dir,
+                show_hidden: file.show_hidden.unwrap_or(defaults.show_hidden),
+                show_sidebar: file.show_sidebar.unwrap_or(defaults.show_sidebar),
+                show_preview: file.show_preview.unwrap_or(defaults.show_preview),
+                minimal: file.minimal.unwrap_or(defaults.minimal),
+                thumbnail_size,
+                thumbnail_quality: file.thumbnail_quality.unwrap_or(defaults.thumbnail_quality),
+                thumbnail_cache_entries,
+                thumbnail_cache_bytes,
+                hex_preview_bytes,
+                split_extension: file.split_extension.unwrap_or(defaults.split_extension),
+                git_column: file.git_column.unwrap_or(defaults.git_column),
+                image_extensions,
+                auto_descend_single: file.auto_descend_single.unwrap_or(defaults.auto_descend_single),
+                permanent_delete: file.permanent_delete.unwrap_or(defaults.permanent_delete),
+                preserve_metadata: file.preserve_metadata.unwrap_or(defaults.preserve_metadata),
+                tmux_passthrough: file.tmux_passthrough.unwrap_or(defaults.tmux_passthrough),
+                idle_image_cleanup,
+                bookmarks,
+                terminal,
+                editor_line_flag: file.editor_line_flag.unwrap_or(defaults.editor_line_flag),
+                path_url_map,
+                keymap,
+                theme,
+            },
+            warnings,
+        ))
+    }
+}
+
+impl ColorsFile {
+    /// Override `theme` with the colors that parse
+    ///
+    /// Accepts names (`cyan`, `lightred`), `#rrggbb`, and 0–255 indexes.
+    fn apply(self, mut theme: Theme, warnings: &mut Vec<String>) -> Theme {
+        let fields = [
+            ("selection_bg", self.selection_bg, &mut theme.selection_bg),
+            ("selection_fg", self.selection_fg, &mut theme.selection_fg),
+            ("marked_bg", self.marked_bg, &mut theme.marked_bg),
+            ("directory", self.directory, &mut theme.directory),
+            ("file", self.file, &mut theme.file),
+            ("path", self.path, &mut theme.path),
+            ("dialog_bg", self.dialog_bg, &mut theme.dialog_bg),
+        ];
+        for (name, value, slot) in fields {
+            let Some(value) = value else {
+                continue;
+            };
+            match value.parse::<Color>() {
+                Ok(color) => *slot = color,
+                Err(_) => warnings.push(format!("invalid color `{}` for colors.{}", value, name)),
+            }
+        }
+        theme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Action;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_parse_config() {
+        let (config, warnings) = Config::parse(
+            r##"
+            show_hidden = true
+            minimal = true
+            preserve_metadata = false
+            idle_image_cleanup_secs = 5
+            image_extensions = [".PNG", "tiff", " "]
+            thumbnail_size = 5
+            thumbnail_quality = "high"
+            terminal = " foot  -e fish "
+
+            [colors]
+            directory = "#ff8800"
+            file = "not-a-color"
+
+            [keys]
+            quit = ["ctrl+q", "Q"]
+            "##,
+        )
+        .unwrap();
+
+        assert!(config.show_hidden);
+        assert!(config.show_sidebar);
+        assert!(config.minimal);
+        assert!(!config.preserve_metadata);
+        assert_eq!(config.idle_image_cleanup, Some(Duration::from_secs(5)));
+        assert_eq!(config.image_extensions, ["png", "tiff"]);
+        assert_eq!(config.thumbnail_size, DEFAULT_THUMBNAIL_SIZE);
+        assert_eq!(config.thumbnail_quality, ResizeQuality::High);
+        assert_eq!(config.terminal, ["foot", "-e", "fish"]);
+        assert_eq!(config.theme.directory, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(config.theme.file, Theme::default().file);
+        assert_eq!(config.keymap.action_for(KeyCode::Char('Q'), KeyModifiers::SHIFT), Some(Action::Quit));
+        assert_eq!(warnings.len(), 3);
+
+        assert!(Config::parse("show_hiden = true").is_err());
+
+        let (config, _) = Config::parse(r#"terminal = ["wezterm", "start", "--class", "My Term"]"#).unwrap();
+        assert_eq!(config.terminal, ["wezterm", "start", "--class", "My Term"]);
+
+        let (config, warnings) = Config::parse(
+            r#"
+            [path_url_map]
+            "/srv/drive" = "https://drive.example/{path}"
+            "/srv/fixed" = "https://example.com"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.path_url_map[0], UrlMapping {
+            prefix: PathBuf::from("/srv/drive"),
+            template: "https://drive.example/{path}".to_string(),
+        });
+        assert_eq!(warnings, ["path_url_map entry for /srv/fixed has no {path}"]);
+    }
+}
@@ -0,0 +1,227 @@
+//! File permission and attribute flags
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Check whether the owner may write to the file `metadata` describes
+pub fn is_writable(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o200 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        !metadata.permissions().readonly()
+    }
+}
+
+/// Flip the owner write bit (readonly attribute on Windows)
+///
+/// Returns the new writable state.
+pub fn toggle_writable(path: &Path) -> Result<bool> {
+    let mut permissions = std::fs::metadata(path)?.permissions();
+
+    #[cfg(unix)]
+    let writable = {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode() ^ 0o200;
+        permissions.set_mode(mode);
+        mode & 0o200 != 0
+    };
+    #[cfg(not(unix))]
+    let writable = {
+        let readonly = !permissions.readonly();
+        permissions.set_readonly(readonly);
+        !readonly
+    };
+
+    std::fs::set_permissions(path, permissions)?;
+    Ok(writable)
+}
+
+/// `FS_IMMUTABLE_FL` from linux/fs.h (`chattr +i`)
+#[cfg(target_os = "linux")]
+const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+
+/// Read the inode flags; the kernel reads and writes an int despite the ioctl's `long`
+#[cfg(target_os = "linux")]
+fn inode_flags(file: &std::fs::File) -> std::io::Result<libc::c_int> {
+    use std::os::fd::AsRawFd;
+
+    let mut flags: libc::c_int = 0;
+    // SAFETY: the fd is valid for the lifetime of `file` and `flags` outlives the call
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(flags)
+}
+
+/// Check the immutable flag, or `None` if the filesystem doesn't support it
+#[cfg(target_os = "linux")]
+pub fn is_immutable(path: &Path) -> Option<bool> {
+    let file = std::fs::File::open(path).ok()?;
+    inode_flags(&file).ok().map(|flags| flags & FS_IMMUTABLE_FL != 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_immutable(_path: &Path) -> Option<bool> {
+    None
+}
+
+/// Flip the immutable flag, returning the new state
+///
+/// Changing it requires root (`CAP_LINUX_IMMUTABLE`).
+#[cfg(target_os = "linux")]
+pub fn toggle_immutable(path: &Path) -> Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    let file = std::fs::File::open(path)?;
+    let flags = inode_flags(&file)? ^ FS_IMMUTABLE_FL;
+
+    // SAFETY: as in `inode_flags`
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) };
+    if ret < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EPERM) {
+            bail!("Changing the immutable flag requires root");
+        }
+        return Err(err.into());
+    }
+    Ok(flags & FS_IMMUTABLE_FL != 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn toggle_immutable(_path: &Path) -> Result<bool> {
+    bail!("Immutable flag is not supported on this platform")
+}
+
+/// Permission bits and file type (`st_mode`), or the file attributes on Windows
+pub fn metadata_mode(metadata: &std::fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.mode()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_attributes()
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Owning user and group ids; zero where the platform has none
+pub fn metadata_owner(metadata: &std::fs::Metadata) -> (u32, u32) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.uid(), metadata.gid())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        (0, 0)
+    }
+}
+
+/// `ls -l` style permission string, e.g. `rwxr-xr-x`
+#[cfg(unix)]
+pub fn format_permissions(mode: u32) -> String {
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    // Execute slot shows setuid/setgid/sticky: lowercase if also executable
+    let special = |exec: u32, flag: u32, set: char| match (mode & exec != 0, mode & flag != 0) {
+        (true, true) => set,
+        (false, true) => set.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+
+    [
+        bit(0o400, 'r'), bit(0o200, 'w'), special(0o100, 0o4000, 's'),
+        bit(0o040, 'r'), bit(0o020, 'w'), special(0o010, 0o2000, 's'),
+        bit(0o004, 'r'), bit(0o002, 'w'), special(0o001, 0o1000, 't'),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Name of the user with `uid`, or `None` if it has no passwd entry
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    lookup_name(|buf| {
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: every pointer is valid for the call and `buf.len()` is its real size
+        let ret = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        (ret, (!result.is_null()).then_some(pwd.pw_name))
+    })
+}
+
+/// Name of the group with `gid`, or `None` if it has no group entry
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    lookup_name(|buf| {
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: as in `user_name`
+        let ret = unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+        (ret, (!result.is_null()).then_some(grp.gr_name))
+    })
+}
+
+/// Run a `get*_r` lookup, growing the buffer while it reports `ERANGE`
+///
+/// `lookup` returns the error code and, when found, the name pointer into `buf`.
+#[cfg(unix)]
+fn lookup_name(mut lookup: impl FnMut(&mut Vec<libc::c_char>) -> (libc::c_int, Option<*mut libc::c_char>)) -> Option<String> {
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        match lookup(&mut buf) {
+            (libc::ERANGE, _) if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            (0, Some(name)) => {
+                // SAFETY: on success the name is a NUL-terminated string inside `buf`
+                let name = unsafe { std::ffi::CStr::from_ptr(name) };
+                return Some(name.to_string_lossy().into_owned());
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Labels for the readonly, hidden, and system attributes that are set
+#[cfg(windows)]
+pub fn attribute_labels(attributes: u32) -> Vec<&'static str> {
+    const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+    [
+        (FILE_ATTRIBUTE_READONLY, "readonly"),
+        (FILE_ATTRIBUTE_HIDDEN, "hidden"),
+        (FILE_ATTRIBUTE_SYSTEM, "system"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| attributes & flag != 0)
+    .map(|(_, label)| label)
+    .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_permissions() {
+        assert_eq!(format_permissions(0o100755), "rwxr-xr-x");
+        assert_eq!(format_permissions(0o644), "rw-r--r--");
+        assert_eq!(format_permissions(0o4755), "rwsr-xr-x");
+        assert_eq!(format_permissions(0o1777), "rwxrwxrwt");
+        assert_eq!(format_permissions(0o2640), "rw-r-S---");
+    }
+}
@@ -0,0 +1,91 @@
+//! Recursive directory sizes, computed on request in the background
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use super::{get_directory_size, DirectorySize};
+
+/// What a job sends back: the directory, its mtime when the walk started, and the total
+type SizeResult = (PathBuf, Option<SystemTime>, Result<DirectorySize, String>);
+
+/// Directory totals, kept until the directory changes
+///
+/// A total is dropped when the directory's own mtime moves on, or when
+/// `invalidate` is called for it or anything below it. Changes deep inside a
+/// tree don't touch the top directory's mtime, so a stale total is possible
+/// until the next `invalidate`.
+pub struct DirSizeCache {
+    sizes: HashMap<PathBuf, (Option<SystemTime>, DirectorySize)>,
+    pending: HashSet<PathBuf>,
+    sender: UnboundedSender<SizeResult>,
+    receiver: UnboundedReceiver<SizeResult>,
+}
+
+impl DirSizeCache {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            sizes: HashMap::new(),
+            pending: HashSet::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Start totalling `path` on a blocking worker, unless already cached or running
+    pub fn request(&mut self, path: &Path) {
+        if self.pending.contains(path) || self.get(path, dir_mtime(path)).is_some() {
+            return;
+        }
+        self.pending.insert(path.to_path_buf());
+
+        let path = path.to_path_buf();
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let modified = dir_mtime(&path);
+            let size = std::panic::catch_unwind(|| get_directory_size(&path))
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("failed unexpectedly")))
+                .map_err(|e| e.to_string());
+            let _ = sender.send((path, modified, size));
+        });
+    }
+
+    /// Cached total for `path`, if taken when it was last `modified`
+    ///
+    /// Pass the time from the listing (`FileEntry::modified`), so drawing a
+    /// row doesn't stat the directory.
+    pub fn get(&self, path: &Path, modified: Option<SystemTime>) -> Option<DirectorySize> {
+        let (taken_at, size) = self.sizes.get(path)?;
+        (*taken_at == modified).then_some(*size)
+    }
+
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.pending.contains(path)
+    }
+
+    /// Move finished jobs into the cache, returning the ones that failed
+    pub fn poll_completed(&mut self) -> Vec<(PathBuf, String)> {
+        let mut failed = Vec::new();
+        while let Ok((path, modified, size)) = self.receiver.try_recv() {
+            self.pending.remove(&path);
+            match size {
+                Ok(size) => {
+                    self.sizes.insert(path, (modified, size));
+                }
+                Err(e) => failed.push((path, e)),
+            }
+        }
+        failed
+    }
+
+    /// Forget totals that include `path`: its own and its ancestors'
+    pub fn invalidate(&mut self, path: &Path) {
+        self.sizes.retain(|dir, _| !path.starts_with(dir));
+    }
+}
+
+fn dir_mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,212 @@
+//! Each entry's last commit, for the git column, read from `git log` in the background
+//!
+//! One `git log` per directory finds the newest commit touching each entry;
+//! a directory's commit is the newest touching anything inside it. The walk
+//! is bounded, so entries unchanged for longer than `MAX_COMMITS` commits
+//! show no commit. Results are kept per directory until its repository's
+//! HEAD moves, which is checked again every `RECHECK_INTERVAL`.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Commits walked per directory at most
+const MAX_COMMITS: usize = 1000;
+/// How often a shown directory's HEAD is compared with the cached one
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Short hash, a space, and the longest age label ("11mo ago")
+pub const GIT_COLUMN_WIDTH: usize = 16;
+
+/// The newest commit that touched an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastCommit {
+    pub hash: String,
+    pub time: SystemTime,
+}
+
+impl LastCommit {
+    /// "a1b2c3d 3d ago"
+    pub fn label(&self) -> String {
+        format!("{} {}", self.hash, format_age(self.time, SystemTime::now()))
+    }
+}
+
+/// What is known of one directory
+enum DirLog {
+    NotARepo,
+    Loaded {
+        head: String,
+        /// By entry name
+        commits: HashMap<String, LastCommit>,
+    },
+}
+
+/// A finished job: the directory, and its log, or None when HEAD hadn't moved
+type LogResult = (PathBuf, Option<DirLog>);
+
+/// Last commits by directory, looked up lazily while a directory is shown
+pub struct GitLogCache {
+    logs: HashMap<PathBuf, (Instant, DirLog)>,
+    pending: HashSet<PathBuf>,
+    sender: UnboundedSender<LogResult>,
+    receiver: UnboundedReceiver<LogResult>,
+}
+
+impl GitLogCache {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { logs: HashMap::new(), pending: HashSet::new(), sender, receiver }
+    }
+
+    /// Read `dir`'s log on a blocking worker, unless it was checked recently
+    ///
+    /// Cheap enough to call every frame for the directories on screen.
+    pub fn request(&mut self, dir: &Path) {
+        if self.pending.contains(dir) {
+            return;
+        }
+        let known_head = match self.logs.get(dir) {
+            Some((checked, _)) if checked.elapsed() < RECHECK_INTERVAL => return,
+            Some((_, DirLog::Loaded { head, .. })) => Some(head.clone()),
+            _ => None,
+        };
+        self.pending.insert(dir.to_path_buf());
+
+        let dir = dir.to_path_buf();
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let log = read_log(&dir, known_head.as_deref());
+            let _ = sender.send((dir, log));
+        });
+    }
+
+    /// Move finished jobs into the cache
+    pub fn poll_completed(&mut self) {
+        while let Ok((dir, log)) = self.receiver.try_recv() {
+            self.pending.remove(&dir);
+            match log {
+                Some(log) => {
+                    self.logs.insert(dir, (Instant::now(), log));
+                }
+                None => {
+                    if let Some((checked, _)) = self.logs.get_mut(&dir) {
+                        *checked = Instant::now();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether `dir` gets a git column: it is in a repository, or not known yet
+    pub fn shows_column(&self, dir: &Path) -> bool {
+        !matches!(self.logs.get(dir), Some((_, DirLog::NotARepo)))
+    }
+
+    /// The column text for `name` in `dir`: its commit, "…" until the log is
+    /// read, or nothing when the bounded walk didn't reach it
+    pub fn label(&self, dir: &Path, name: &str) -> String {
+        match self.logs.get(dir) {
+            Some((_, DirLog::Loaded { commits, .. })) => commits.get(name).map(LastCommit::label).unwrap_or_default(),
+            Some((_, DirLog::NotARepo)) => String::new(),
+            None => "…".to_string(),
+        }
+    }
+}
+
+/// `dir`'s log, None if HEAD is still `known_head`
+///
+/// Shells out to `git`: there is no libgit2 binding among the dependencies.
+/// A missing `git`, like a directory outside any repository, means no column.
+fn read_log(dir: &Path, known_head: Option<&str>) -> Option<DirLog> {
+    if !dir.ancestors().any(|ancestor| ancestor.join(".git").exists()) {
+        return Some(DirLog::NotARepo);
+    }
+    let Some(head) = git(dir, &["rev-parse", "HEAD"]).map(|out| out.trim().to_string()) else {
+        return Some(DirLog::NotARepo);
+    };
+    if known_head == Some(head.as_str()) {
+        return None;
+    }
+
+    let max_count = format!("--max-count={}", MAX_COMMITS);
+    // -z ends every name with NUL; %x1e starts each commit
+    let args = ["-c", "core.quotePath=false", "log", &max_count, "--format=%x1e%h %ct", "--name-only", "--relative", "-z", "--", "."];
+    let commits = git(dir, &args).map(|out| parse_log(&out)).unwrap_or_default();
+    Some(DirLog::Loaded { head, commits })
+}
+
+/// Run git in `dir`, returning its output if it succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| log::debug!("Cannot run git: {}", e))
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The newest commit per top-level name in `git log` output, newest first
+fn parse_log(output: &str) -> HashMap<String, LastCommit> {
+    let mut commits = HashMap::new();
+    for record in output.split('\x1e').filter(|record| !record.is_empty()) {
+        let mut fields = record.split('\0');
+        let Some((hash, time)) = fields.next().and_then(|header| header.trim().split_once(' ')) else {
+            continue;
+        };
+        let Ok(seconds) = time.parse::<u64>() else {
+            continue;
+        };
+        let commit = LastCommit { hash: hash.to_string(), time: UNIX_EPOCH + Duration::from_secs(seconds) };
+        for path in fields.map(|field| field.trim_start_matches('\n')).filter(|field| !field.is_empty()) {
+            let name = path.split('/').next().unwrap_or(path);
+            commits.entry(name.to_string()).or_insert_with(|| commit.clone());
+        }
+    }
+    commits
+}
+
+/// A short age like "5m ago" or "3mo ago", to keep the column narrow
+fn format_age(time: SystemTime, now: SystemTime) -> String {
+    const HOUR: u64 = 3600;
+    const DAY: u64 = 24 * HOUR;
+    let secs = now.duration_since(time).map(|age| age.as_secs()).unwrap_or(0);
+    let age = match secs {
+        0..60 => return "just now".to_string(),
+        60..HOUR => format!("{}m", secs / 60),
+        HOUR..DAY => format!("{}h", secs / HOUR),
+        _ if secs < 14 * DAY => format!("{}d", secs / DAY),
+        _ if secs < 60 * DAY => format!("{}w", secs / (7 * DAY)),
+        _ if secs < 365 * DAY => format!("{}mo", secs / (30 * DAY)),
+        _ => format!("{}y", secs / (365 * DAY)),
+    };
+    format!("{} ago", age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_keeps_the_newest_commit_per_entry() {
+        let output = "\x1e9f8e7d6 1700000200\0\nsrc/main.rs\0README.md\0\
+                      \x1ea1b2c3d 1700000100\0\nsrc/lib.rs\0notes.txt\0";
+        let commits = parse_log(output);
+        let hash = |name: &str| commits.get(name).map(|commit| commit.hash.as_str());
+        assert_eq!(hash("src"), Some("9f8e7d6"));
+        assert_eq!(hash("README.md"), Some("9f8e7d6"));
+        assert_eq!(hash("notes.txt"), Some("a1b2c3d"));
+        assert_eq!(commits["notes.txt"].time, UNIX_EPOCH + Duration::from_secs(1_700_000_100));
+        assert_eq!(commits.len(), 3);
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let ago = |secs| format_age(now - Duration::from_secs(secs), now);
+        assert_eq!((ago(30), ago(300), ago(3 * 86400), ago(90 * 86400)), (
+            "just now".to_string(), "5m ago".to_string(), "3d ago".to_string(), "3mo ago".to_string()
+        ));
+    }
+}
@@ -0,0 +1,500 @@
+//! Read-only browsing of ISO 9660 disk images
+//!
+//! A path under an image, like `/dl/install.iso/boot/grub.cfg`, is listed
+//! and read from the image itself, so it can be browsed like a directory
+//! without mounting. Joliet names are used when the image has them; Rock
+//! Ridge extensions are not read, so such images show their plain ISO names.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{FileEntry, FileType};
+
+const SECTOR_SIZE: u64 = 2048;
+/// Volume descriptors start after the 32 KiB system area
+const FIRST_DESCRIPTOR: u64 = 16;
+/// Stop looking for the terminator after this many descriptors
+const MAX_DESCRIPTORS: u64 = 64;
+/// Directory flag bits
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+/// Largest directory read; real ones are a few sectors, so beyond this the image is damaged
+const MAX_DIRECTORY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Extensions opened as disk images, lowercase
+const IMAGE_EXTENSIONS: [&str; 2] = ["iso", "img"];
+
+/// Whether `path` is named like a disk image
+pub fn is_disk_image(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|image| ext.eq_ignore_ascii_case(image)))
+}
+
+/// The image containing `path` and the path inside it, for paths strictly below an image
+pub fn member_of_image(path: &Path) -> Option<(&Path, &Path)> {
+    locate(path).filter(|(_, inner)| !inner.as_os_str().is_empty())
+}
+
+/// Whether `dir` is an image or a directory in one, so nothing can be written there
+pub fn inside_image(dir: &Path) -> bool {
+    locate(dir).is_some()
+}
+
+/// The image at or above `path`, and the rest of the path inside it
+///
+/// Only ancestors named like an image are looked at on disk; nothing
+/// below an image exists there, so at most one of them is a file.
+pub fn locate(path: &Path) -> Option<(&Path, &Path)> {
+    let image = path.ancestors()
+        .filter(|ancestor| is_disk_image(ancestor))
+        .find(|ancestor| ancestor.is_file())?;
+    Some((image, path.strip_prefix(image).ok()?))
+}
+
+/// List the directory `inner` of `image` as entries under `image.join(inner)`
+pub fn read_entries(image: &Path, inner: &Path, show_hidden: bool) -> Result<Vec<FileEntry>> {
+    let iso = IsoImage::open(image)?;
+    let dir = iso.lookup(inner)?;
+    if !dir.is_dir {
+        bail!("{} is not a directory", inner.display());
+    }
+    let parent = image.join(inner);
+    Ok(iso.read_dir(&dir)?
+        .into_iter()
+        .filter(|record| show_hidden || !record.name.starts_with('.'))
+        .map(|record| FileEntry {
+            path: parent.join(&record.name),
+            size: record.size(),
+            name: record.name,
+            is_dir: record.is_dir,
+            file_type: if record.is_dir { FileType::Directory } else { FileType::Regular },
+            modified: record.modified,
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
+            // Read-only, with the file type bits as in `st_mode`
+            mode: if record.is_dir { 0o040555 } else { 0o100444 },
+            uid: 0,
+            gid: 0,
+            // Thumbnails are decoded from disk, where these files aren't
+            is_image: false,
+            writable: false,
+        })
+        .collect())
+}
+
+/// Up to `max_bytes` from the start of a file in an image
+pub fn read_prefix(path: &Path, max_bytes: usize) -> Result<Vec<u8>> {
+    let (image, inner) = member_of_image(path).context("Not in a disk image")?;
+    let iso = IsoImage::open(image)?;
+    let record = iso.lookup(inner)?;
+    let mut buf = Vec::with_capacity(max_bytes.min(8192));
+    iso.reader(&record).take(max_bytes as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Copy a file or directory out of an image to `target`, which must not exist
+///
+/// `on_file` is called with the size of each file written, as for `paste`.
+pub fn extract(path: &Path, target: &Path, on_file: &mut dyn FnMut(u64) -> Result<()>) -> Result<()> {
+    let (image, inner) = member_of_image(path).context("Not in a disk image")?;
+    let iso = IsoImage::open(image)?;
+    let record = iso.lookup(inner)?;
+    iso.extract(&record, target, &mut HashSet::new(), on_file)
+}
+
+/// Extract a file to the temp directory, for opening it in another program
+///
+/// Each image gets its own folder there, so files of the same name in
+/// different images or directories don't overwrite each other.
+pub fn extract_to_temp(path: &Path) -> Result<PathBuf> {
+    let (image, inner) = member_of_image(path).context("Not in a disk image")?;
+    if !inner.components().all(|component| matches!(component, Component::Normal(_))) {
+        bail!("{} is not a path inside the image", inner.display());
+    }
+    let image_name = image.file_name().context("Image has no name")?;
+    let target = std::env::temp_dir()
+        .join(format!("graphterm-{}", std::process::id()))
+        .join(image_name)
+        .join(inner);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Cannot create {}", parent.display()))?;
+    }
+    // Opened before: the copy in temp is as good as a fresh one
+    if target.exists() {
+        return Ok(target);
+    }
+    extract(path, &target, &mut |_| Ok(()))?;
+    Ok(target)
+}
+
+/// A directory record: a file or directory in the image
+#[derive(Debug, Clone)]
+struct Record {
+    name: String,
+    is_dir: bool,
+    /// Byte offset and length of each part; files over 4 GiB have several
+    extents: Vec<(u64, u64)>,
+    modified: Option<SystemTime>,
+}
+
+impl Record {
+    fn size(&self) -> u64 {
+        self.extents.iter().map(|(_, len)| len).sum()
+    }
+}
+
+/// An open image and the root of the directory tree to read
+struct IsoImage {
+    file: File,
+    root: Record,
+    /// Names are UCS-2, from a Joliet supplementary descriptor
+    joliet: bool,
+}
+
+impl IsoImage {
+    /// Read the volume descriptors, preferring Joliet's tree over the primary one
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        let mut primary = None;
+        let mut joliet = None;
+        let mut sector = [0u8; SECTOR_SIZE as usize];
+        for index in FIRST_DESCRIPTOR..FIRST_DESCRIPTOR + MAX_DESCRIPTORS {
+            file.seek(SeekFrom::Start(index * SECTOR_SIZE))?;
+            if file.read_exact(&mut sector).is_err() || &sector[1..6] != b"CD001" {
+                break;
+            }
+            match sector[0] {
+                1 => primary = Some(sector[156..190].to_vec()),
+                // Joliet marks itself with a UCS-2 escape sequence
+                2 if matches!(&sector[88..91], b"%/@" | b"%/C" | b"%/E") => joliet = Some(sector[156..190].to_vec()),
+                255 => break,
+                _ => {}
+            }
+        }
+
+        let (root, is_joliet) = match (joliet, primary) {
+            (Some(root), _) => (root, true),
+            (None, Some(root)) => (root, false),
+            (None, None) => bail!("{} is not an ISO 9660 image", path.display()),
+        };
+        let (root, _) = parse_record(&root, is_joliet)?.context("The image's root directory is damaged")?;
+        Ok(Self { file, root: Record { is_dir: true, ..root }, joliet: is_joliet })
+    }
+
+    /// The record at `inner`, walking down from the root
+    fn lookup(&self, inner: &Path) -> Result<Record> {
+        let mut record = self.root.clone();
+        let mut visited = HashSet::new();
+        for component in inner.components() {
+            let Component::Normal(name) = component else {
+                bail!("{} is not a path inside the image", inner.display());
+            };
+            if !record.is_dir {
+                bail!("{} is not a directory", record.name);
+            }
+            if !visited.insert(record.extents.first().copied()) {
+                bail!("The image is damaged: {} contains itself", record.name);
+            }
+            record = self.read_dir(&record)?
+                .into_iter()
+                .find(|child| name.to_str() == Some(child.name.as_str()))
+                .with_context(|| format!("No {} in the image", inner.display()))?;
+        }
+        Ok(record)
+    }
+
+    /// The records of a directory, without its `.` and `..`
+    fn read_dir(&self, dir: &Record) -> Result<Vec<Record>> {
+        if dir.size() > MAX_DIRECTORY_BYTES {
+            bail!("The image is damaged: directory {} claims {} bytes", dir.name, dir.size());
+        }
+        let mut data = Vec::new();
+        self.reader(dir).read_to_end(&mut data)?;
+
+        let mut records: Vec<Record> = Vec::new();
+        let mut continues = false;
+        let mut offset = 0;
+        while offset < data.len() {
+            // Records don't cross sectors; a zero length pads to the next one
+            if data[offset] == 0 {
+                offset = (offset / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+                continue;
+            }
+            let Some((record, flags)) = parse_record(&data[offset..], self.joliet)? else {
+                break;
+            };
+            offset += data[offset] as usize;
+
+            // The next record carries on a multi-extent file
+            match records.last_mut().filter(|_| continues) {
+                Some(last) => last.extents.extend(record.extents),
+                None if record.name.is_empty() => {}
+                None => records.push(record),
+            }
+            continues = flags & FLAG_MULTI_EXTENT != 0;
+        }
+        Ok(records)
+    }
+
+    /// The contents of a record, read extent by extent
+    fn reader<'a>(&'a self, record: &'a Record) -> ExtentReader<'a> {
+        ExtentReader { file: &self.file, extents: &record.extents, position: 0 }
+    }
+
+    /// Write `record` to `target`, removing nothing on failure; `paste` cleans up
+    ///
+    /// `visited` holds the directories extracted so far, by their first
+    /// extent, so a record pointing back at one stops the walk.
+    fn extract(
+        &self,
+        record: &Record,
+        target: &Path,
+        visited: &mut HashSet<Option<(u64, u64)>>,
+        on_file: &mut dyn FnMut(u64) -> Result<()>,
+    ) -> Result<()> {
+        if std::fs::symlink_metadata(target).is_ok() {
+            bail!("{} already exists", target.display());
+        }
+        if record.is_dir {
+            if !visited.insert(record.extents.first().copied()) {
+                bail!("The image is damaged: {} contains itself", record.name);
+            }
+            std::fs::create_dir(target).with_context(|| format!("Cannot create {}", target.display()))?;
+            for child in self.read_dir(record)? {
+                self.extract(&child, &target.join(&child.name), visited, on_file)?;
+            }
+            return Ok(());
+        }
+
+        let mut out = File::create(target).with_context(|| format!("Cannot create {}", target.display()))?;
+        let size = std::io::copy(&mut self.reader(record), &mut out)
+            .with_context(|| format!("Cannot extract {}", record.name))?;
+        drop(out);
+        if let Some(modified) = record.modified {
+            let _ = filetime::set_file_mtime(target, filetime::FileTime::from_system_time(modified));
+        }
+        on_file(size)
+    }
+}
+
+/// Reads the extents of a record one after the other
+///
+/// Seeks before every read, so several can share the image's file.
+struct ExtentReader<'a> {
+    file: &'a File,
+    /// Extents not read to the end yet
+    extents: &'a [(u64, u64)],
+    /// Bytes read of the first of them
+    position: u64,
+}
+
+impl Read for ExtentReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while let Some(&(start, len)) = self.extents.first() {
+            if self.position == len {
+                self.extents = &self.extents[1..];
+                self.position = 0;
+                continue;
+            }
+            let mut file = self.file;
+            file.seek(SeekFrom::Start(start + self.position))?;
+            let read = file.take(len - self.position).read(buf)?;
+            if read == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            self.position += read as u64;
+            return Ok(read);
+        }
+        Ok(0)
+    }
+}
+
+/// One directory record from the start of `bytes`, with its flags, or
+/// None if too short for one
+///
+/// `.` and `..` come back with an empty name. Names lose their `;1`
+/// version and the dot ISO 9660 adds to names without an extension.
+/// Any other name that isn't a single path component is an error, since
+/// it would be joined onto paths outside the image.
+fn parse_record(bytes: &[u8], joliet: bool) -> Result<Option<(Record, u8)>> {
+    let Some(&len) = bytes.first() else {
+        return Ok(None);
+    };
+    let len = len as usize;
+    if len < 34 || bytes.len() < len {
+        return Ok(None);
+    }
+    let name_len = bytes[32] as usize;
+    let Some(name) = bytes.get(33..33 + name_len) else {
+        return Ok(None);
+    };
+    let flags = bytes[25];
+    let is_self_or_parent = matches!(name, [0] | [1]);
+
+    let name = match name {
+        [0] | [1] => String::new(),
+        _ if joliet => {
+            let units: Vec<u16> = name.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(name).into_owned(),
+    };
+    let name = match name.rsplit_once(';') {
+        Some((base, version)) if version.bytes().all(|b| b.is_ascii_digit()) => base.to_string(),
+        _ => name,
+    };
+    let name = match name.strip_suffix('.') {
+        Some(base) if flags & FLAG_DIRECTORY == 0 && !base.is_empty() => base.to_string(),
+        _ => name,
+    };
+
+    if !is_self_or_parent && (matches!(name.as_str(), "" | "." | "..") || name.contains(['/', '\0'])) {
+        bail!("The image is damaged: a record is named {:?}", name);
+    }
+
+    let start = u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]) as u64 * SECTOR_SIZE;
+    let size = u32::from_le_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]) as u64;
+    Ok(Some((
+        Record {
+            name,
+            is_dir: flags & FLAG_DIRECTORY != 0,
+            extents: vec![(start, size)],
+            modified: record_time(&bytes[18..25]),
+        },
+        flags,
+    )))
+}
+
+/// A recording date: years since 1900, month, day, hour, minute, second,
+/// and the offset from UTC in quarter hours
+fn record_time(date: &[u8]) -> Option<SystemTime> {
+    let &[year, month, day, hour, minute, second, offset] = date else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(1900 + year as i64, month as i64, day as i64);
+    let local = days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+    let utc = local - (offset as i8) as i64 * 15 * 60;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(utc).ok()?))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// A directory record for `name` at `sector`
+    fn record(name: &[u8], sector: u32, size: u32, flags: u8) -> Vec<u8> {
+        let len = 33 + name.len() + (name.len() + 1) % 2;
+        let mut bytes = vec![0u8; len];
+        bytes[0] = len as u8;
+        bytes[2..6].copy_from_slice(&sector.to_le_bytes());
+        bytes[10..14].copy_from_slice(&size.to_le_bytes());
+        // 2024-03-01 12:00:00 UTC
+        bytes[18..25].copy_from_slice(&[124, 3, 1, 12, 0, 0, 0]);
+        bytes[25] = flags;
+        bytes[32] = name.len() as u8;
+        bytes[33..33 + name.len()].copy_from_slice(name);
+        bytes
+    }
+
+    /// An image with README.TXT and BOOT/GRUB.CFG, in plain ISO 9660 names
+    fn build_image(path: &Path) {
+        let sector = SECTOR_SIZE as usize;
+        let mut image = vec![0u8; 24 * sector];
+        let mut put = |at: usize, bytes: &[u8]| image[at..at + bytes.len()].copy_from_slice(bytes);
+
+        let mut pvd = vec![1u8];
+        pvd.extend_from_slice(b"CD001\x01");
+        put(16 * sector, &pvd);
+        put(16 * sector + 156, &record(&[0], 18, sector as u32, FLAG_DIRECTORY));
+        put(17 * sector, b"\xffCD001\x01");
+
+        let mut root = [record(&[0], 18, sector as u32, FLAG_DIRECTORY), record(&[1], 18, sector as u32, FLAG_DIRECTORY)].concat();
+        root.extend(record(b"BOOT", 19, sector as u32, FLAG_DIRECTORY));
+        root.extend(record(b"README.TXT;1", 20, 6, 0));
+        put(18 * sector, &root);
+
+        let mut boot = [record(&[0], 19, sector as u32, FLAG_DIRECTORY), record(&[1], 18, sector as u32, FLAG_DIRECTORY)].concat();
+        boot.extend(record(b"GRUB.CFG;1", 21, 9, 0));
+        put(19 * sector, &boot);
+        put(20 * sector, b"hello\n");
+        put(21 * sector, b"timeout=5");
+        std::fs::write(path, image).unwrap();
+    }
+
+    #[test]
+    fn test_browse_and_extract_an_iso() {
+        let dir = TempDir::new("iso");
+        let image = dir.path().join("disk.iso");
+        build_image(&image);
+
+        assert!(inside_image(&image) && inside_image(&image.join("BOOT")));
+        assert!(member_of_image(&image).is_none() && !inside_image(dir.path()));
+
+        let root = read_entries(&image, Path::new(""), false).unwrap();
+        let names: Vec<_> = root.iter().map(|entry| (entry.name.as_str(), entry.is_dir, entry.size)).collect();
+        assert_eq!(names, [("BOOT", true, SECTOR_SIZE), ("README.TXT", false, 6)]);
+        assert_eq!(root[1].path, image.join("README.TXT"));
+        assert_eq!(root[1].modified, Some(UNIX_EPOCH + Duration::from_secs(1_709_294_400)));
+
+        let boot = read_entries(&image, Path::new("BOOT"), false).unwrap();
+        assert_eq!(boot[0].name, "GRUB.CFG");
+        assert_eq!(read_prefix(&boot[0].path, 7).unwrap(), b"timeout");
+
+        let target = dir.path().join("boot");
+        let mut sizes = Vec::new();
+        extract(&image.join("BOOT"), &target, &mut |size| {
+            sizes.push(size);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(std::fs::read(target.join("GRUB.CFG")).unwrap(), b"timeout=5");
+        assert_eq!(sizes, [9]);
+
+        assert!(read_entries(&image, Path::new("MISSING"), false).is_err());
+    }
+
+    #[test]
+    fn test_damaged_images_are_refused() {
+        let dir = TempDir::new("iso-damaged");
+        let image = dir.path().join("disk.iso");
+        let sector = SECTOR_SIZE as usize;
+        // README.TXT's record follows `.`, `..` and BOOT in the root; GRUB.CFG follows `.` and `..`
+        let readme_name = 18 * sector + 34 + 34 + 38 + 33;
+        let grub = 19 * sector + 34 + 34;
+
+        build_image(&image);
+        let mut bytes = std::fs::read(&image).unwrap();
+        bytes[readme_name..readme_name + 12].copy_from_slice(b"../../X.TX;1");
+        std::fs::write(&image, &bytes).unwrap();
+        assert!(read_entries(&image, Path::new(""), false).is_err());
+
+        // GRUB.CFG turned into a directory pointing back at the root
+        build_image(&image);
+        let mut bytes = std::fs::read(&image).unwrap();
+        bytes[grub + 2..grub + 6].copy_from_slice(&18u32.to_le_bytes());
+        bytes[grub + 10..grub + 14].copy_from_slice(&(sector as u32).to_le_bytes());
+        bytes[grub + 25] = FLAG_DIRECTORY;
+        std::fs::write(&image, &bytes).unwrap();
+        assert!(extract(&image.join("BOOT"), &dir.path().join("out"), &mut |_| Ok(())).is_err());
+        assert!(read_entries(&image, Path::new("BOOT/GRUB.CFG/BOOT/GRUB.CFG"), false).is_err());
+    }
+}
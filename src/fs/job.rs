@@ -0,0 +1,475 @@
+//! Copy, move, and delete jobs run in the background with progress reports
+
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use super::{delete, move_to_trash, paste, ClipboardMode};
+
+/// What a job does to each of its paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// Copy into the destination directory
+    Copy,
+    /// Move into the destination directory
+    Move,
+    Trash,
+    /// Delete permanently
+    Delete,
+}
+
+impl JobKind {
+    /// Shown next to the progress bar
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Copy => "Copying",
+            Self::Move => "Moving",
+            Self::Trash => "Moving to Trash",
+            Self::Delete => "Deleting",
+        }
+    }
+
+    /// Copies and moves stop at the first failure; deletes try every path
+    fn stops_on_failure(self) -> bool {
+        matches!(self, Self::Copy | Self::Move)
+    }
+}
+
+/// Throughput is averaged over this much of the recent past
+const RATE_WINDOW: Duration = Duration::from_secs(3);
+/// No rate is shown until the job has run this long; the first files skew it
+const RATE_WARMUP: Duration = Duration::from_secs(1);
+
+/// Files and bytes handled so far, out of the totals counted up front
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JobProgress {
+    pub files: u64,
+    pub total_files: u64,
+    pub bytes: u64,
+    pub total_bytes: u64,
+    /// Bytes per second over the last few seconds; `None` while warming up
+    pub bytes_per_second: Option<u64>,
+    /// Time left at that rate; `None` without a rate, or while nothing moves
+    pub eta: Option<Duration>,
+}
+
+impl JobProgress {
+    /// Fraction done, by bytes, or by files when there are no bytes to speak of
+    pub fn ratio(&self) -> f64 {
+        let ratio = if self.total_bytes > 0 {
+            self.bytes as f64 / self.total_bytes as f64
+        } else if self.total_files > 0 {
+            self.files as f64 / self.total_files as f64
+        } else {
+            0.0
+        };
+        ratio.clamp(0.0, 1.0)
+    }
+
+    /// "12.3 MB/s, 2m 05s left", once there is a rate to show
+    pub fn throughput_label(&self) -> Option<String> {
+        let rate = self.bytes_per_second?;
+        let mut label = format!("{}/s", super::format_size(rate));
+        if let Some(eta) = self.eta {
+            label.push_str(&format!(", {} left", format_eta(eta)));
+        }
+        Some(label)
+    }
+}
+
+/// "45s", "2m 05s", or "1h 02m"
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// How a finished job went
+#[derive(Debug)]
+pub struct JobOutcome {
+    pub kind: JobKind,
+    /// Paths handled successfully, in order
+    pub done: Vec<PathBuf>,
+    /// Paths that failed; copies and moves leave the rest untried, as does `cancel`
+    pub failed: Vec<PathBuf>,
+    /// The last error, if any
+    pub error: Option<String>,
+}
+
+enum JobMessage {
+    Progress(JobProgress),
+    Finished(JobOutcome),
+}
+
+/// A running job; poll it from the event loop until it hands back its outcome
+pub struct FileJob {
+    pub kind: JobKind,
+    pub progress: JobProgress,
+    receiver: UnboundedReceiver<JobMessage>,
+    cancelled: Arc<AtomicBool>,
+    started: Instant,
+    /// When each recent progress report arrived, with the bytes done by then
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl FileJob {
+    /// Start `kind` on `paths` on a blocking worker
+    ///
+    /// `dest` is the directory copies and moves go to; deletes ignore it.
+    /// `preserve_metadata` is passed on to `paste` for copies.
+    pub fn start(kind: JobKind, paths: Vec<PathBuf>, dest: PathBuf, preserve_metadata: bool) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        tokio::task::spawn_blocking(move || {
+            let ran = std::panic::catch_unwind(AssertUnwindSafe(|| run(kind, paths, &dest, preserve_metadata, &flag, &sender)));
+            // Without a Finished message the job would look busy forever
+            if ran.is_err() {
+                let error = Some("Stopped unexpectedly; see the log".to_string());
+                let _ = sender.send(JobMessage::Finished(JobOutcome { kind, done: Vec::new(), failed: Vec::new(), error }));
+            }
+        });
+        Self {
+            kind,
+            progress: JobProgress::default(),
+            receiver,
+            cancelled,
+            started: Instant::now(),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Stop after the file being copied, removing what was copied of its path
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Take in the latest progress, returning the outcome once the job is done
+    pub fn poll(&mut self) -> Option<JobOutcome> {
+        let now = Instant::now();
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                JobMessage::Progress(progress) => {
+                    self.progress = progress;
+                    self.samples.push_back((now, progress.bytes));
+                }
+                JobMessage::Finished(outcome) => return Some(outcome),
+            }
+        }
+        self.update_rate(now);
+        None
+    }
+
+    /// Average the bytes moved since the oldest report in the window
+    ///
+    /// Measured up to now rather than to the last report, so the rate falls
+    /// while a job stalls instead of holding its last value.
+    fn update_rate(&mut self, now: Instant) {
+        // Keep one report older than the window, as the baseline
+        while self.samples.len() > 1 && self.samples.get(1).is_some_and(|&(at, _)| now - at >= RATE_WINDOW) {
+            self.samples.pop_front();
+        }
+        let Some(&(since, bytes_then)) = self.samples.front() else {
+            return;
+        };
+        let elapsed = (now - since).as_secs_f64();
+        if now - self.started < RATE_WARMUP || elapsed <= 0.0 {
+            return;
+        }
+        let rate = (self.progress.bytes.saturating_sub(bytes_then)) as f64 / elapsed;
+        let remaining = self.progress.total_bytes.saturating_sub(self.progress.bytes);
+        self.progress.bytes_per_second = Some(rate as u64);
+        self.progress.eta = (rate >= 1.0).then(|| Duration::from_secs_f64(remaining as f64 / rate));
+    }
+}
+
+/// Finished jobs kept for the operation queue overlay
+const JOB_HISTORY: usize = 10;
+
+/// A job waiting for the running one to finish
+struct QueuedJob<T> {
+    kind: JobKind,
+    paths: Vec<PathBuf>,
+    dest: PathBuf,
+    preserve_metadata: bool,
+    origin: T,
+    label: String,
+}
+
+/// File jobs run one at a time, in the order they were started
+///
+/// `T` is what started each job, handed back with its outcome; labels are
+/// what the operation queue shows, e.g. "Copying 3 items to /tmp".
+pub struct JobQueue<T> {
+    running: Option<(FileJob, T, String)>,
+    queued: VecDeque<QueuedJob<T>>,
+    /// Labels of the last few finished jobs, and whether each succeeded
+    finished: VecDeque<(String, bool)>,
+}
+
+impl<T> JobQueue<T> {
+    pub fn new() -> Self {
+        Self { running: None, queued: VecDeque::new(), finished: VecDeque::new() }
+    }
+
+    /// Start a job, or queue it behind the running one; true if it was queued
+    pub fn push(&mut self, kind: JobKind, paths: Vec<PathBuf>, dest: PathBuf, preserve_metadata: bool, origin: T, label: String) -> bool {
+        if self.running.is_some() {
+            self.queued.push_back(QueuedJob { kind, paths, dest, preserve_metadata, origin, label });
+            return true;
+        }
+        self.running = Some((FileJob::start(kind, paths, dest, preserve_metadata), origin, label));
+        false
+    }
+
+    pub fn running(&self) -> Option<&FileJob> {
+        self.running.as_ref().map(|(job, ..)| job)
+    }
+
+    pub fn running_label(&self) -> Option<&str> {
+        self.running.as_ref().map(|(.., label)| label.as_str())
+    }
+
+    pub fn queued_labels(&self) -> impl Iterator<Item = &str> {
+        self.queued.iter().map(|job| job.label.as_str())
+    }
+
+    pub fn queued_len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn finished(&self) -> impl Iterator<Item = &(String, bool)> {
+        self.finished.iter()
+    }
+
+    /// Drop the queued job at `index`, returning what started it and its label
+    pub fn remove_queued(&mut self, index: usize) -> Option<(T, String)> {
+        self.queued.remove(index).map(|job| (job.origin, job.label))
+    }
+
+    /// Poll the running job, handing it back with its outcome once it is done
+    ///
+    /// The next queued job waits for `start_next`, so the finished one can
+    /// be reported first.
+    pub fn poll(&mut self) -> Option<(JobOutcome, T, String)> {
+        let outcome = self.running.as_mut()?.0.poll()?;
+        let (_, origin, label) = self.running.take()?;
+        if self.finished.len() == JOB_HISTORY {
+            self.finished.pop_front();
+        }
+        self.finished.push_back(match &outcome.error {
+            None => (format!("{}: done", label), true),
+            Some(e) => (format!("{}: {}", label, e), false),
+        });
+        Some((outcome, origin, label))
+    }
+
+    /// Start the first queued job, if none is running
+    pub fn start_next(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        if let Some(next) = self.queued.pop_front() {
+            self.running = Some((FileJob::start(next.kind, next.paths, next.dest, next.preserve_metadata), next.origin, next.label));
+        }
+    }
+}
+
+fn run(
+    kind: JobKind,
+    paths: Vec<PathBuf>,
+    dest: &Path,
+    preserve_metadata: bool,
+    cancelled: &AtomicBool,
+    sender: &UnboundedSender<JobMessage>,
+) {
+    // Count everything first so the bar has something to fill
+    let sizes: Vec<(u64, u64)> = paths.iter().map(|path| measure(path)).collect();
+    let mut progress = JobProgress {
+        total_files: sizes.iter().map(|(files, _)| files).sum(),
+        total_bytes: sizes.iter().map(|(_, bytes)| bytes).sum(),
+        ..JobProgress::default()
+    };
+    let _ = sender.send(JobMessage::Progress(progress));
+
+    let mut outcome = JobOutcome { kind, done: Vec::new(), failed: Vec::new(), error: None };
+    for (path, (files, bytes)) in paths.into_iter().zip(sizes) {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let before = progress;
+        // Copies, and moves that have to copy across filesystems, report file
+        // by file; the rest only once each path is done
+        // Checked after every file, so a large tree can be stopped partway
+        let mut on_file = |size| {
+            progress.files += 1;
+            progress.bytes += size;
+            let _ = sender.send(JobMessage::Progress(progress));
+            if cancelled.load(Ordering::Relaxed) {
+                anyhow::bail!("Cancelled");
+            }
+            Ok(())
+        };
+        let result = match kind {
+            JobKind::Copy => paste(&path, ClipboardMode::Copy, dest, preserve_metadata, &mut on_file).map(drop),
+            JobKind::Move => paste(&path, ClipboardMode::Cut, dest, preserve_metadata, &mut on_file).map(drop),
+            JobKind::Trash => move_to_trash(&path),
+            JobKind::Delete => delete(&path),
+        };
+        progress = JobProgress { files: before.files + files, bytes: before.bytes + bytes, ..before };
+        let _ = sender.send(JobMessage::Progress(progress));
+
+        match result {
+            Ok(()) => outcome.done.push(path),
+            Err(e) => {
+                outcome.failed.push(path);
+                outcome.error = Some(format!("{:#}", e));
+                if kind.stops_on_failure() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = sender.send(JobMessage::Finished(outcome));
+}
+
+/// Files and bytes in `path`, counting a directory's whole tree; links are not followed
+fn measure(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if !metadata.is_dir() {
+        return (1, metadata.len());
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .map(|entry| measure(&entry.path()))
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[tokio::test]
+    async fn test_job_reports_progress_and_outcome() {
+        let dir = TempDir::new("job");
+        let root = dir.path();
+        let (src, dest) = (root.join("src"), root.join("dest"));
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(src.join("a"), "1234").unwrap();
+        std::fs::write(src.join("b"), "123456").unwrap();
+        std::fs::write(dest.join("b"), "taken").unwrap();
+
+        let paths = vec![src.join("a"), src.join("b"), src.join("c")];
+        let mut job = FileJob::start(JobKind::Move, paths, dest.clone(), true);
+        let outcome = loop {
+            if let Some(outcome) = job.poll() {
+                break outcome;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        };
+
+        // `b` collides, so the move stops there and `c` is never tried
+        assert_eq!(outcome.done, vec![src.join("a")]);
+        assert_eq!(outcome.failed, vec![src.join("b")]);
+        assert!(outcome.error.is_some_and(|e| e.contains("already exists")));
+        assert_eq!(job.progress, JobProgress { files: 2, total_files: 2, bytes: 10, total_bytes: 10, ..job.progress });
+        assert_eq!(std::fs::read_to_string(dest.join("a")).unwrap(), "1234");
+    }
+
+    #[tokio::test]
+    async fn test_queued_job_starts_after_the_running_one() {
+        async fn finish(queue: &mut JobQueue<i32>) -> (JobOutcome, i32, String) {
+            loop {
+                if let Some(done) = queue.poll() {
+                    return done;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }
+
+        let dir = TempDir::new("job-queue");
+        let root = dir.path();
+        for name in ["a", "b", "first", "second"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+        }
+        let mut queue = JobQueue::new();
+        assert!(!queue.push(JobKind::Copy, vec![root.join("a")], root.join("first"), false, 1, "first".to_string()));
+        assert!(queue.push(JobKind::Copy, vec![root.join("b")], root.join("second"), false, 2, "second".to_string()));
+        assert_eq!(queue.queued_labels().collect::<Vec<_>>(), ["second"]);
+
+        let (outcome, origin, label) = finish(&mut queue).await;
+        assert_eq!((origin, label.as_str(), outcome.error), (1, "first", None));
+        // Only `start_next` hands over, after the first is reported
+        assert!(queue.running().is_none());
+        queue.start_next();
+        assert_eq!(queue.running_label(), Some("second"));
+
+        let (_, origin, _) = finish(&mut queue).await;
+        assert_eq!(origin, 2);
+        assert!(root.join("first/a").is_dir() && root.join("second/b").is_dir());
+        assert_eq!(queue.finished().count(), 2);
+    }
+
+    #[test]
+    fn test_rate_averages_over_the_window() {
+        let (_sender, receiver) = mpsc::unbounded_channel();
+        let start = Instant::now();
+        let mut job = FileJob {
+            kind: JobKind::Copy,
+            progress: JobProgress { bytes: 30_000_000, total_bytes: 90_000_000, ..JobProgress::default() },
+            receiver,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            started: start,
+            samples: [(start, 0), (start + Duration::from_secs(1), 10_000_000), (start + Duration::from_secs(3), 30_000_000)].into(),
+        };
+
+        // Reports older than the window drop out, but one stays as the baseline
+        job.update_rate(start + Duration::from_secs(5));
+        assert_eq!(job.samples.len(), 2);
+        assert_eq!(job.progress.bytes_per_second, Some(5_000_000));
+        assert_eq!(job.progress.eta, Some(Duration::from_secs(12)));
+        assert_eq!(job.progress.throughput_label().unwrap(), "4.8 MB/s, 12s left");
+
+        // Nothing moving: the rate falls to nothing and there's no estimate
+        job.update_rate(start + Duration::from_secs(60));
+        assert_eq!(job.progress.bytes_per_second, Some(0));
+        assert_eq!(job.progress.eta, None);
+    }
+
+    #[test]
+    fn test_cancelled_copy_leaves_nothing_behind() {
+        let dir = TempDir::new("cancel");
+        let root = dir.path();
+        let (src, dest) = (root.join("src"), root.join("dest"));
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(src.join(name), name).unwrap();
+        }
+
+        // Cancelled as the first file lands: the walk stops and the partial copy goes
+        let mut copied = 0;
+        let mut on_file = |_| {
+            copied += 1;
+            anyhow::bail!("Cancelled")
+        };
+        let result = paste(&src, ClipboardMode::Copy, &dest, true, &mut on_file);
+        assert!(result.is_err_and(|e| e.to_string() == "Cancelled"));
+        assert_eq!(copied, 1);
+        assert!(!dest.join("src").exists());
+    }
+}
@@ -1,21 +1,105 @@
 //! Directory listing utilities
 
-use std::path::Path;
-use anyhow::Result;
+use std::cmp::Ordering;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{bail, Context, Result};
+
+/// Maximum number of bytes read from a file for previews
+pub const PREVIEW_MAX_BYTES: usize = 64 * 1024;
+
+/// Expand a leading `~` to the home directory
+pub fn expand_tilde(input: &str) -> PathBuf {
+    let home = || dirs::home_dir().unwrap_or_default();
+    if input == "~" {
+        home()
+    } else if let Some(rest) = input.strip_prefix("~/") {
+        home().join(rest)
+    } else {
+        PathBuf::from(input)
+    }
+}
+
+/// Tab-complete the last component of a typed directory path
+///
+/// Relative input is resolved against `base`. Returns `input` extended by
+/// the prefix shared by every matching subdirectory, plus a `/` once only one
+/// matches, or `None` when nothing matches.
+pub fn complete_dir_path(input: &str, base: &Path) -> Option<String> {
+    if input == "~" {
+        return Some("~/".to_string());
+    }
+
+    let (dir_part, partial) = match input.rfind('/') {
+        Some(i) => input.split_at(i + 1),
+        None => ("", input),
+    };
+    let dir = base.join(expand_tilde(dir_part));
+
+    let matches: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        // Follow symlinks so linked directories complete too
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(partial))
+        .filter(|name| partial.starts_with('.') || !name.starts_with('.'))
+        .collect();
+
+    let first = matches.first()?;
+    let common_len = matches[1..].iter().fold(first.len(), |len, name| {
+        first[..len]
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(name.len()), |((i, _), _)| i)
+    });
+
+    let mut completed = format!("{}{}", dir_part, &first[..common_len]);
+    if matches.len() == 1 {
+        completed.push('/');
+    }
+    Some(completed)
+}
+
+/// Recursive size of a directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectorySize {
+    pub bytes: u64,
+    /// Entries that could not be read, left out of `bytes`
+    pub skipped: u64,
+}
 
 /// Get directory size (recursive)
-pub fn get_directory_size(path: &Path) -> Result<u64> {
-    let mut size = 0u64;
+///
+/// Symlinks count as the link itself and are never followed, so a link back
+/// to an ancestor can't send the walk around in circles. Only `path` itself
+/// being unreadable is an error; unreadable entries below it are counted in
+/// `skipped` and the walk carries on.
+pub fn get_directory_size(path: &Path) -> Result<DirectorySize> {
+    let mut size = DirectorySize::default();
     
     if path.is_dir() {
         for entry in std::fs::read_dir(path)? {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
+            // symlink_metadata: describes the link, not its target
+            let Ok((entry, metadata)) = entry.and_then(|entry| {
+                std::fs::symlink_metadata(entry.path()).map(|metadata| (entry, metadata))
+            }) else {
+                size.skipped += 1;
+                continue;
+            };
             
             if metadata.is_dir() {
-                size += get_directory_size(&entry.path())?;
+                match get_directory_size(&entry.path()) {
+                    Ok(inner) => {
+                        size.bytes += inner.bytes;
+                        size.skipped += inner.skipped;
+                    }
+                    Err(_) => size.skipped += 1,
+                }
             } else {
-                size += metadata.len();
+                size.bytes += metadata.len();
             }
         }
     }
@@ -23,6 +107,225 @@ pub fn get_directory_size(path: &Path) -> Result<u64> {
     Ok(size)
 }
 
+/// Find directories under `root` that contain nothing but other empty directories
+///
+/// Results are ordered children-first so they can be removed in sequence.
+/// `root` itself is never included, and symlinks are not followed.
+pub fn find_empty_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    
+    if let Ok(read_dir) = std::fs::read_dir(root) {
+        for entry in read_dir.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                collect_empty_dirs(&entry.path(), &mut found);
+            }
+        }
+    }
+    
+    found
+}
+
+/// Bottom-up walk; returns whether `dir` is empty once its empty children are gone
+fn collect_empty_dirs(dir: &Path, found: &mut Vec<PathBuf>) -> bool {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    
+    let mut empty = true;
+    for entry in read_dir {
+        let is_empty_child = entry.is_ok_and(|entry| {
+            entry.file_type().is_ok_and(|t| t.is_dir()) && collect_empty_dirs(&entry.path(), found)
+        });
+        if !is_empty_child {
+            empty = false;
+        }
+    }
+    
+    if empty {
+        found.push(dir.to_path_buf());
+    }
+    empty
+}
+
+/// Remove directories found by `find_empty_dirs`, returning how many were removed
+///
+/// Uses `remove_dir`, so anything that gained contents in the meantime is kept.
+pub fn remove_empty_dirs(dirs: &[PathBuf]) -> usize {
+    dirs.iter()
+        .filter(|dir| std::fs::remove_dir(dir).is_ok())
+        .count()
+}
+
+/// A path like `target` nobody has taken yet: `target` itself, else
+/// `name (copy).ext`, then `name (copy 2).ext` and so on
+pub fn unique_copy_path(target: &Path) -> PathBuf {
+    if std::fs::symlink_metadata(target).is_err() {
+        return target.to_path_buf();
+    }
+    // Directories keep dots in their names whole
+    let (stem, extension) = match (target.file_stem(), target.extension()) {
+        (Some(stem), Some(ext)) if !target.is_dir() => {
+            (stem.to_string_lossy().to_string(), format!(".{}", ext.to_string_lossy()))
+        }
+        _ => (target.file_name().unwrap_or_default().to_string_lossy().to_string(), String::new()),
+    };
+
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { " (copy)".to_string() } else { format!(" (copy {})", n) };
+            target.with_file_name(format!("{}{}{}", stem, suffix, extension))
+        })
+        .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+        .expect("some copy name is free")
+}
+
+/// Copy `src` to `dst`, a whole directory tree included, returning the files copied
+///
+/// Directories and files keep their permissions. With `preserve_metadata`
+/// they also keep their access and modification times and, where the user
+/// may set it, their owner. Symlinks are copied as links, never followed.
+/// Pipes, sockets, and devices are skipped. `dst` must not exist yet; see
+/// `unique_copy_path`.
+///
+/// `on_file` is called with each file's size once it is copied. An error
+/// from it stops the walk there and is returned, so a cancelled job doesn't
+/// have to wait for the rest of the tree.
+pub fn copy_recursive_with(
+    src: &Path,
+    dst: &Path,
+    preserve_metadata: bool,
+    on_file: &mut dyn FnMut(u64) -> Result<()>,
+) -> Result<u64> {
+    let metadata = std::fs::symlink_metadata(src)
+        .with_context(|| format!("Cannot read {}", src.display()))?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        let target = std::fs::read_link(src)?;
+        copy_symlink(&target, dst).with_context(|| format!("Cannot create link {}", dst.display()))?;
+        if preserve_metadata {
+            let (accessed, modified) = file_times(&metadata);
+            filetime::set_symlink_file_times(dst, accessed, modified)
+                .with_context(|| format!("Cannot set the times of {}", dst.display()))?;
+            copy_owner(&metadata, dst);
+        }
+        on_file(0)?;
+        return Ok(1);
+    }
+    if file_type.is_file() {
+        if std::fs::symlink_metadata(dst).is_ok() {
+            bail!("{} already exists", dst.display());
+        }
+        // std::fs::copy carries the permissions over
+        let size = std::fs::copy(src, dst).with_context(|| format!("Cannot copy {}", src.display()))?;
+        if preserve_metadata {
+            copy_metadata(&metadata, dst)?;
+        }
+        on_file(size)?;
+        return Ok(1);
+    }
+    if !file_type.is_dir() {
+        return Ok(0);
+    }
+
+    std::fs::create_dir(dst).with_context(|| format!("Cannot create {}", dst.display()))?;
+    let mut copied = 0;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        copied += copy_recursive_with(&entry.path(), &dst.join(entry.file_name()), preserve_metadata, on_file)?;
+    }
+    // Last, so a read-only directory can still be filled, and filling it
+    // doesn't move its modification time again
+    if preserve_metadata {
+        copy_metadata(&metadata, dst)?;
+    } else {
+        std::fs::set_permissions(dst, metadata.permissions())?;
+    }
+    Ok(copied)
+}
+
+/// Give `dst` the times, owner, and permissions recorded in `metadata`
+///
+/// The times go first: on Windows a read-only file's can't be changed.
+fn copy_metadata(metadata: &std::fs::Metadata, dst: &Path) -> Result<()> {
+    let (accessed, modified) = file_times(metadata);
+    filetime::set_file_times(dst, accessed, modified)
+        .with_context(|| format!("Cannot set the times of {}", dst.display()))?;
+    copy_owner(metadata, dst);
+    std::fs::set_permissions(dst, metadata.permissions())
+        .with_context(|| format!("Cannot set the permissions of {}", dst.display()))?;
+    Ok(())
+}
+
+fn file_times(metadata: &std::fs::Metadata) -> (filetime::FileTime, filetime::FileTime) {
+    (
+        filetime::FileTime::from_last_access_time(metadata),
+        filetime::FileTime::from_last_modification_time(metadata),
+    )
+}
+
+/// Hand `dst` to the original's owner and group
+///
+/// Only root may give a file away, so for everyone else this usually fails;
+/// the copy still belongs to whoever made it, which is noted in the log
+/// rather than failing the copy.
+#[cfg(unix)]
+fn copy_owner(metadata: &std::fs::Metadata, dst: &Path) {
+    use std::os::unix::fs::MetadataExt;
+    if let Err(e) = std::os::unix::fs::lchown(dst, Some(metadata.uid()), Some(metadata.gid())) {
+        log::debug!("Kept the copier as owner of {}: {}", dst.display(), e);
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_metadata: &std::fs::Metadata, _dst: &Path) {}
+
+#[cfg(unix)]
+fn copy_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn copy_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    // Relative targets are relative to the link's directory
+    let resolved = link.parent().map(|dir| dir.join(target)).unwrap_or_else(|| target.to_path_buf());
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Filesystem types whose reads go over the network
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "glusterfs",
+    "fuse.sshfs", "fuse.rclone", "fuse.s3fs", "davfs", "fuse.davfs2",
+];
+
+/// Check whether a path lives on a network filesystem (NFS, CIFS, sshfs, ...)
+///
+/// Uses the longest matching mount point in `/proc/mounts`; always `false`
+/// where that isn't available.
+pub fn is_network_fs(path: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            // Spaces in mount points are escaped as \040
+            let mount_point = PathBuf::from(mount_point.replace("\\040", " "));
+            path.starts_with(&mount_point).then_some((mount_point, fs_type))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .is_some_and(|(_, fs_type)| NETWORK_FS_TYPES.contains(&fs_type))
+}
+
 /// Format file size for display
 pub fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -39,3 +342,326 @@ pub fn format_size(bytes: u64) -> String {
         format!("{} B", bytes)
     }
 }
+
+/// Format a modification time, or a dash when it's unknown
+///
+/// The last week reads as "3 days ago"; anything older, or in the future,
+/// as a local "2024-03-14 09:31".
+pub fn format_modified(modified: Option<SystemTime>) -> String {
+    match modified {
+        Some(time) => format_time_since(time, SystemTime::now()),
+        None => "-".to_string(),
+    }
+}
+
+fn format_time_since(time: SystemTime, now: SystemTime) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = MINUTE * 60;
+    const DAY: u64 = HOUR * 24;
+
+    let plural = |n: u64, unit: &str| format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" });
+    match now.duration_since(time).map(|age| age.as_secs()) {
+        Ok(secs) if secs < MINUTE => "just now".to_string(),
+        Ok(secs) if secs < HOUR => format!("{} min ago", secs / MINUTE),
+        Ok(secs) if secs < DAY => plural(secs / HOUR, "hour"),
+        Ok(secs) if secs < 7 * DAY => plural(secs / DAY, "day"),
+        _ => chrono::DateTime::<chrono::Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+    }
+}
+
+/// Maximum number of lines shown in a text preview
+pub const PREVIEW_MAX_LINES: usize = 40;
+
+/// Extensions previewed as text
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "rs", "py", "js", "ts", "tsx", "jsx", "json", "toml", "yaml", "yml",
+    "c", "h", "cpp", "hpp", "go", "java", "kt", "rb", "sh", "bash", "zsh", "fish",
+    "html", "css", "scss", "xml", "svg", "csv", "ini", "cfg", "conf", "log", "sql",
+    "lua", "vim", "lock", "gitignore", "env", "tex", "org", "rst",
+];
+
+/// Whether a file name looks like text: a known extension, or none at all
+/// (`Makefile`, `LICENSE`)
+pub fn is_text_name(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => TEXT_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(ext)),
+        None => true,
+    }
+}
+
+/// Read at most `max_bytes` from the start of a file
+pub fn read_file_prefix(path: &Path, max_bytes: usize) -> Result<Vec<u8>> {
+    if super::member_of_image(path).is_some() {
+        return super::iso::read_prefix(path, max_bytes);
+    }
+    let mut buf = Vec::with_capacity(max_bytes.min(8192));
+    std::fs::File::open(path)?
+        .take(max_bytes as u64)
+        .read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Check whether a byte prefix looks like UTF-8 text
+///
+/// A multi-byte character cut off at the end of the prefix is tolerated.
+pub fn is_text(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Bytes shown in a hex preview by default
+pub const DEFAULT_HEX_PREVIEW_BYTES: usize = 512;
+
+/// Check whether a byte prefix looks binary: a NUL, or mostly control characters
+///
+/// "Mostly" means over a tenth, so stray form feeds or escape codes in a
+/// log still count as text.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes.iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r')) || b == 0x7f)
+        .count();
+    control * 10 > bytes.len()
+}
+
+/// Format bytes as `hexdump -C` style rows: offset, hex columns, ASCII gutter
+///
+/// `per_row` bytes per row; past eight, the hex columns get a gap in the middle.
+/// A short last row is padded so its gutter lines up.
+pub fn hex_dump_lines(bytes: &[u8], per_row: usize) -> Vec<String> {
+    let per_row = per_row.max(1);
+    bytes
+        .chunks(per_row)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut line = format!("{:08x} ", row * per_row);
+            for i in 0..per_row {
+                if i == 8 {
+                    line.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(byte) => line.push_str(&format!(" {:02x}", byte)),
+                    None => line.push_str("   "),
+                }
+            }
+            line.push_str("  |");
+            line.extend(chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+            line.push('|');
+            line
+        })
+        .collect()
+}
+
+/// Count lines, words, and bytes like `wc`
+pub fn text_stats(bytes: &[u8]) -> (usize, usize, usize) {
+    let lines = bytes.iter().filter(|&&b| b == b'\n').count();
+    let words = bytes
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .count();
+    (lines, words, bytes.len())
+}
+
+/// Compare names the way people count: `img2` before `img10`, ignoring case
+///
+/// Digit runs compare by value. When nothing else differs, the name whose
+/// first differing number has more leading zeros goes last (`a1` < `a01`),
+/// then names differing only in case compare plainly, keeping the order total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    let mut zeros = Ordering::Equal;
+    loop {
+        let (Some(&x), Some(&y)) = (a_chars.peek(), b_chars.peek()) else {
+            // One ran out; the shorter name goes first
+            return a_chars.peek().is_some().cmp(&b_chars.peek().is_some())
+                .then(zeros)
+                .then_with(|| a.cmp(b));
+        };
+
+        let ordering = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let take_digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                let mut run = String::new();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    run.push(c);
+                }
+                run
+            };
+            let (x_run, y_run) = (take_digits(&mut a_chars), take_digits(&mut b_chars));
+            let (x_value, y_value) = (x_run.trim_start_matches('0'), y_run.trim_start_matches('0'));
+            zeros = zeros.then(x_run.len().cmp(&y_run.len()));
+            x_value.len().cmp(&y_value.len()).then_with(|| x_value.cmp(y_value))
+        } else {
+            a_chars.next();
+            b_chars.next();
+            x.to_lowercase().cmp(y.to_lowercase())
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["img10.png", "IMG2.png", "img1.png", "img01.png", "img", "img1a", "img1b2", "img1b10"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["img", "img1.png", "img01.png", "img1a", "img1b2", "img1b10", "IMG2.png", "img10.png"]);
+
+        assert_eq!(natural_cmp("a007", "a7"), Ordering::Greater);
+        assert_eq!(natural_cmp("File", "file"), "File".cmp("file"));
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_copy_recursive_nested() {
+        let dir = TempDir::new("copy");
+        let root = dir.path();
+        let src = root.join("src");
+        std::fs::create_dir_all(src.join("a/b/c")).unwrap();
+        std::fs::create_dir_all(src.join("empty")).unwrap();
+        std::fs::write(src.join("top.txt"), "top").unwrap();
+        std::fs::write(src.join("a/one.txt"), "one").unwrap();
+        std::fs::write(src.join("a/b/c/deep.txt"), "deep").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(src.join("a/one.txt"), std::fs::Permissions::from_mode(0o640)).unwrap();
+            std::fs::set_permissions(src.join("a/b"), std::fs::Permissions::from_mode(0o750)).unwrap();
+            std::os::unix::fs::symlink("one.txt", src.join("a/link")).unwrap();
+        }
+
+        let dst = unique_copy_path(&src);
+        assert_eq!(dst, root.join("src (copy)"));
+        let copied = copy_recursive_with(&src, &dst, true, &mut |_| Ok(())).unwrap();
+        assert_eq!(copied, if cfg!(unix) { 4 } else { 3 });
+        assert_eq!(std::fs::read_to_string(dst.join("a/b/c/deep.txt")).unwrap(), "deep");
+        assert!(dst.join("empty").is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |p: &str| std::fs::metadata(dst.join(p)).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode("a/one.txt"), 0o640);
+            assert_eq!(mode("a/b"), 0o750);
+            assert_eq!(std::fs::read_link(dst.join("a/link")).unwrap(), Path::new("one.txt"));
+        }
+
+        // A second copy takes the next free name; copying onto a taken one fails
+        assert_eq!(unique_copy_path(&src), root.join("src (copy 2)"));
+        assert_eq!(unique_copy_path(&src.join("top.txt")), src.join("top (copy).txt"));
+        assert!(copy_recursive_with(&src.join("top.txt"), &dst.join("top.txt"), true, &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_copy_keeps_modification_time() {
+        let dir = TempDir::new("copy-times");
+        let root = dir.path();
+        let src = root.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("old.txt"), "old").unwrap();
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(src.join("old.txt"), old).unwrap();
+        filetime::set_file_mtime(&src, old).unwrap();
+        let mtime = |path: &Path| filetime::FileTime::from_last_modification_time(&std::fs::metadata(path).unwrap());
+
+        copy_recursive_with(&src, &root.join("kept"), true, &mut |_| Ok(())).unwrap();
+        assert_eq!(mtime(&root.join("kept/old.txt")), old);
+        assert_eq!(mtime(&root.join("kept")), old);
+
+        copy_recursive_with(&src, &root.join("fresh"), false, &mut |_| Ok(())).unwrap();
+        assert_ne!(mtime(&root.join("fresh/old.txt")), old);
+    }
+
+    #[test]
+    fn test_find_empty_dirs_nested() {
+        let dir = TempDir::new("empty");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::create_dir_all(root.join("keep/empty")).unwrap();
+        std::fs::write(root.join("keep/file.txt"), b"x").unwrap();
+
+        let found = find_empty_dirs(root);
+        let position = |p: &str| found.iter().position(|f| *f == root.join(p));
+        assert_eq!(found.len(), 4);
+        assert!(position("a/b/c").is_some());
+        assert!(position("a/b/c") < position("a/b"));
+        assert!(position("a/b") < position("a"));
+        assert!(position("keep/empty").is_some());
+        assert!(position("keep").is_none());
+
+        assert_eq!(remove_empty_dirs(&found), 4);
+        assert!(root.join("keep/file.txt").exists());
+        assert!(!root.join("a").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_size_skips_symlink_cycles() {
+        let dir = TempDir::new("size");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/file"), [0u8; 100]).unwrap();
+        std::os::unix::fs::symlink(root, root.join("sub/loop")).unwrap();
+
+        let link_len = std::fs::symlink_metadata(root.join("sub/loop")).unwrap().len();
+        assert_eq!(get_directory_size(root).unwrap(), DirectorySize { bytes: 100 + link_len, skipped: 0 });
+    }
+
+    #[test]
+    fn test_hex_dump_partial_row() {
+        let lines = hex_dump_lines(b"\x7fELF\x00abcdefghijklmnop", 16);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "00000000  7f 45 4c 46 00 61 62 63  64 65 66 67 68 69 6a 6b  |.ELF.abcdefghijk|");
+        assert_eq!(lines[1].len(), lines[0].len() - 11);
+        assert!(lines[1].starts_with("00000010  6c 6d 6e 6f 70    "));
+        assert!(lines[1].ends_with("  |lmnop|"));
+        assert!(hex_dump_lines(b"", 16).is_empty());
+
+        assert!(is_binary(b"\x7fELF\x00"));
+        assert!(!is_binary(b"plain text\n"));
+    }
+
+    #[test]
+    fn test_format_time_since() {
+        use std::time::Duration;
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let ago = |secs| format_time_since(now - Duration::from_secs(secs), now);
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(5 * 60), "5 min ago");
+        assert_eq!(ago(3600), "1 hour ago");
+        assert_eq!(ago(3 * 86400), "3 days ago");
+        assert_eq!(ago(30 * 86400).len(), "2023-10-15 22:13".len());
+        assert_eq!(format_modified(None), "-");
+    }
+
+    #[test]
+    fn test_complete_dir_path() {
+        let dir = TempDir::new("complete");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("projects")).unwrap();
+        std::fs::create_dir_all(root.join("pictures/2024")).unwrap();
+        std::fs::write(root.join("print.txt"), b"x").unwrap();
+
+        assert_eq!(complete_dir_path("p", root).as_deref(), Some("p"));
+        assert_eq!(complete_dir_path("pr", root).as_deref(), Some("projects/"));
+        assert_eq!(complete_dir_path("pictures/2", root).as_deref(), Some("pictures/2024/"));
+        assert_eq!(complete_dir_path("x", root), None);
+
+        let absolute = format!("{}/pic", root.display());
+        assert_eq!(complete_dir_path(&absolute, Path::new("/")), Some(format!("{}/pictures/", root.display())));
+    }
+}
@@ -1,89 +1,890 @@
 //! Filesystem operations
 
+mod attributes;
+mod dir_size;
+mod git_log;
+mod iso;
+mod job;
 mod listing;
+mod operations;
+mod trash_bin;
+mod tree;
+mod watcher;
 
+pub use attributes::*;
+pub use dir_size::DirSizeCache;
+pub use git_log::{GitLogCache, GIT_COLUMN_WIDTH};
+pub use iso::{extract_to_temp, inside_image, is_disk_image, member_of_image};
+pub use job::{JobKind, JobOutcome, JobProgress, JobQueue};
 pub use listing::*;
+pub use operations::*;
+pub use trash_bin::TrashBin;
+pub use tree::TreeRow;
+pub use watcher::DirectoryWatcher;
 
 use anyhow::Result;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Kind of filesystem entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    /// Named pipe; opening it for reading can block
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl FileType {
+    fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        if metadata.is_dir() {
+            return Self::Directory;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            let file_type = metadata.file_type();
+            if file_type.is_fifo() {
+                return Self::Fifo;
+            } else if file_type.is_socket() {
+                return Self::Socket;
+            } else if file_type.is_block_device() {
+                return Self::BlockDevice;
+            } else if file_type.is_char_device() {
+                return Self::CharDevice;
+            }
+        }
+
+        Self::Regular
+    }
+
+    /// FIFOs, sockets, and devices must never be read for previews
+    pub fn is_special(self) -> bool {
+        !matches!(self, Self::Regular | Self::Directory)
+    }
+
+    /// Human-readable name of the type
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Regular => "File",
+            Self::Directory => "Directory",
+            Self::Fifo => "Named pipe",
+            Self::Socket => "Socket",
+            Self::BlockDevice => "Block device",
+            Self::CharDevice => "Character device",
+        }
+    }
+}
 
 /// Represents a file or directory entry
 #[derive(Debug, Clone)]
 pub struct FileEntry {
+    /// Display name; lossy for non-UTF-8 names, so never use it to build paths
     pub name: String,
+    /// Real path, used for all filesystem operations
     pub path: PathBuf,
     pub is_dir: bool,
+    pub file_type: FileType,
     pub size: u64,
     pub modified: Option<std::time::SystemTime>,
+    /// The entry is a symbolic link; the other fields describe its target,
+    /// or the link itself when the target is missing
+    pub is_symlink: bool,
+    /// Where the link points, as written in the link
+    pub link_target: Option<PathBuf>,
+    /// The entry is a symbolic link whose target is missing
+    pub is_broken_link: bool,
+    /// Unix `st_mode`, or the file attributes on Windows
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// A regular file that is an image by its extension, or by its first
+    /// bytes when it has none; sniffed once, when the directory is read
+    pub is_image: bool,
+    /// The owner write bit (not the readonly attribute on Windows)
+    pub writable: bool,
+}
+
+impl FileEntry {
+    /// The name before and after its last dot, as the Ext column shows it
+    ///
+    /// Directories, dotfiles like `.bashrc`, and names ending in a dot have
+    /// no extension.
+    pub fn split_extension(&self) -> (&str, Option<&str>) {
+        if self.is_dir {
+            return (&self.name, None);
+        }
+        match self.name.rsplit_once('.') {
+            Some((base, ext)) if !base.is_empty() && !ext.is_empty() => (base, Some(ext)),
+            _ => (&self.name, None),
+        }
+    }
+}
+
+/// Ordering applied to directory listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Size,
+    Modified,
+    Extension,
+}
+
+impl SortMode {
+    /// Next mode in the `s` key cycle
+    pub fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Size,
+            Self::Size => Self::Modified,
+            Self::Modified => Self::Extension,
+            Self::Extension => Self::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Size => "Size",
+            Self::Modified => "Modified",
+            Self::Extension => "Extension",
+        }
+    }
+}
+
+/// Which way a listing is sorted; directories still come first either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Ascending => Self::Descending,
+            Self::Descending => Self::Ascending,
+        }
+    }
+
+    /// Shown after the sort mode, e.g. "Name ↑"
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Self::Ascending => "↑",
+            Self::Descending => "↓",
+        }
+    }
+}
+
+/// A view of the listing driven by a set of marked paths, toggled off to show everything again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionFilter {
+    /// Only the marked entries are shown
+    Only(HashSet<PathBuf>),
+    /// The marked entries are hidden
+    Hide(HashSet<PathBuf>),
+}
+
+impl SelectionFilter {
+    fn shows(&self, path: &Path) -> bool {
+        match self {
+            Self::Only(paths) => paths.contains(path),
+            Self::Hide(paths) => !paths.contains(path),
+        }
+    }
+
+    /// Shown in the grid's title while the view is narrowed
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Only(_) => "marked only",
+            Self::Hide(_) => "marked hidden",
+        }
+    }
+}
+
+/// Beginning of a text file, for the preview pane
+#[derive(Debug, Clone)]
+pub struct TextPreview {
+    pub lines: Vec<String>,
+    /// The file is longer than the bytes that were read
+    pub truncated: bool,
+    pub line_count: usize,
+    pub word_count: usize,
+}
+
+/// Upper bound on single-child descents, guarding against symlink loops
+const MAX_DESCEND_DEPTH: usize = 64;
+
+/// How long a load runs before the file grid says so
+const SLOW_LOAD: Duration = Duration::from_millis(100);
+
+/// A directory read on a background thread, ready to replace the listing
+struct Listing {
+    path: PathBuf,
+    entries: Vec<FileEntry>,
+    is_network: bool,
+    collapsed_from: Option<PathBuf>,
+    /// Top-level tree nodes, when the load was started with the tree on
+    tree: Option<Vec<tree::TreeNode>>,
+}
+
+/// What a load needs to build the tree view off the UI thread
+struct TreePlan {
+    /// Directories expanded before the load, read again
+    expanded: HashSet<PathBuf>,
+    /// The directory the tree was built for; a new one is opened a few levels deep
+    root: PathBuf,
+    sort_mode: SortMode,
+    sort_direction: SortDirection,
+}
+
+/// What a finished background load did to the listing
+pub enum Loaded {
+    /// Another directory is shown
+    Entered,
+    /// The same directory was read again; holds the entries shown before
+    Reloaded(Vec<FileEntry>),
 }
 
+/// A finished load and the generation it was started for
+type LoadResult = (u64, Result<Listing>);
+
 /// Filesystem state and operations
 pub struct FileSystem {
     pub current_path: PathBuf,
+    /// Entries shown: `all_entries` narrowed by `filter` and `selection_filter`
     pub entries: Vec<FileEntry>,
+    /// Every entry of `current_path`, sorted
+    all_entries: Vec<FileEntry>,
+    /// Top-level nodes while the tree view is on
+    tree: Option<Vec<tree::TreeNode>>,
+    /// The directory `tree` was built for; a new one is opened a few levels deep
+    tree_root: PathBuf,
+    /// Depth and expansion of each of `entries` in the tree view; empty otherwise
+    pub tree_rows: Vec<TreeRow>,
+    /// Case-insensitive substring that names must contain (empty shows all)
+    pub filter: String,
+    /// Marked entries shown alone or hidden, until toggled off or the directory changes
+    pub selection_filter: Option<SelectionFilter>,
+    /// Automatically descend through single-child directory chains on enter
+    pub auto_descend_single: bool,
+    /// Top of the collapsed chain we descended through, if any
+    pub collapsed_from: Option<PathBuf>,
+    /// Whether `current_path` is on a network filesystem
+    pub is_network: bool,
+    /// Whether dotfiles are listed
+    pub show_hidden: bool,
+    /// Extensions of files shown as images (`FileEntry::is_image`)
+    pub image_extensions: Arc<[String]>,
+    /// Active sort ordering
+    pub sort_mode: SortMode,
+    /// Active sort direction
+    pub sort_direction: SortDirection,
+    /// Last commits for the git column; None while the column is off
+    pub git_log: Option<GitLogCache>,
+    /// Directories visited, oldest first
+    history: Vec<PathBuf>,
+    /// Position of `current_path` in `history`
+    history_index: usize,
+    /// History position the in-flight load travels to (back/forward)
+    history_target: Option<usize>,
+    /// Directory being read in the background, if any
+    pub loading: Option<PathBuf>,
+    /// When the current background load started
+    load_started: Instant,
+    /// Bumped on every load so superseded results are dropped
+    load_generation: u64,
+    /// Channel for finished loads
+    load_sender: UnboundedSender<LoadResult>,
+    load_receiver: UnboundedReceiver<LoadResult>,
 }
 
 impl FileSystem {
     pub fn new(path: PathBuf) -> Self {
+        let (load_sender, load_receiver) = mpsc::unbounded_channel();
         Self {
+            history: vec![path.clone()],
+            history_index: 0,
+            history_target: None,
             current_path: path,
             entries: Vec::new(),
+            all_entries: Vec::new(),
+            tree: None,
+            tree_root: PathBuf::new(),
+            tree_rows: Vec::new(),
+            filter: String::new(),
+            selection_filter: None,
+            auto_descend_single: false,
+            collapsed_from: None,
+            is_network: false,
+            show_hidden: false,
+            image_extensions: crate::graphics::thumbnails::IMAGE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            sort_mode: SortMode::Name,
+            sort_direction: SortDirection::Ascending,
+            git_log: None,
+            loading: None,
+            load_started: Instant::now(),
+            load_generation: 0,
+            load_sender,
+            load_receiver,
         }
     }
 
     /// Load directory contents
+    ///
+    /// Blocks until the directory is read; navigation goes through
+    /// `start_load` instead so slow mounts don't freeze the UI.
     pub fn load_directory(&mut self) -> Result<()> {
-        self.entries.clear();
+        let listing = Self::read_listing(
+            self.current_path.clone(),
+            self.show_hidden,
+            &self.image_extensions,
+            false,
+            self.collapsed_from.clone(),
+            self.tree_plan(),
+        )?;
+        self.install_listing(listing);
         
-        let read_dir = std::fs::read_dir(&self.current_path)?;
+        Ok(())
+    }
+
+    /// Read the entries of `path`, unsorted
+    ///
+    /// A disk image, or a directory in one, is listed from the image.
+    fn read_entries(path: &Path, show_hidden: bool, image_extensions: &[String]) -> Result<Vec<FileEntry>> {
+        if let Some((image, inner)) = iso::locate(path) {
+            return iso::read_entries(image, inner, show_hidden);
+        }
+        let mut entries = Vec::new();
+        let read_dir = std::fs::read_dir(path)?;
         
         for entry in read_dir.flatten() {
-            let metadata = entry.metadata()?;
+            // Entries can vanish or be unreadable (e.g. under /proc); skip them
+            let Ok(link_metadata) = entry.metadata() else {
+                continue;
+            };
+            // Describe a link by its target, so linked directories can be entered;
+            // a broken link falls back to the link itself
+            let is_symlink = link_metadata.file_type().is_symlink();
+            let target_metadata = is_symlink.then(|| std::fs::metadata(entry.path()));
+            let is_broken_link = target_metadata.as_ref().is_some_and(|target| target.is_err());
+            let metadata = match target_metadata {
+                Some(Ok(target)) => target,
+                _ => link_metadata,
+            };
             let name = entry.file_name().to_string_lossy().to_string();
             
-            // Skip hidden files for now (can be toggled later)
-            if name.starts_with('.') {
+            if !show_hidden && name.starts_with('.') {
                 continue;
             }
             
-            self.entries.push(FileEntry {
+            let (uid, gid) = metadata_owner(&metadata);
+            let path = entry.path();
+            let file_type = FileType::from_metadata(&metadata);
+            let link_target = is_symlink.then(|| std::fs::read_link(&path).ok()).flatten();
+            entries.push(FileEntry {
                 name,
-                path: entry.path(),
+                is_image: file_type == FileType::Regular && crate::graphics::is_image_file(&path, image_extensions),
+                writable: is_writable(&metadata),
+                path,
                 is_dir: metadata.is_dir(),
+                file_type,
                 size: metadata.len(),
                 modified: metadata.modified().ok(),
+                is_symlink,
+                link_target,
+                is_broken_link,
+                mode: metadata_mode(&metadata),
+                uid,
+                gid,
             });
         }
         
-        // Sort: directories first, then by name
-        self.entries.sort_by(|a, b| {
-            match (a.is_dir, b.is_dir) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        Ok(entries)
+    }
+
+    /// Read `path` on a blocking worker; the result arrives through `poll_loaded`
+    ///
+    /// With `descend`, single-child directory chains are followed as part of
+    /// the same job. `collapsed_from` is kept unless the descent moves on.
+    /// A load started while another is in flight supersedes it.
+    fn start_load(&mut self, path: PathBuf, descend: bool, collapsed_from: Option<PathBuf>) {
+        self.load_generation += 1;
+        self.history_target = None;
+        self.loading = Some(path.clone());
+        self.load_started = Instant::now();
+
+        let generation = self.load_generation;
+        let show_hidden = self.show_hidden;
+        let image_extensions = self.image_extensions.clone();
+        let tree = self.tree_plan();
+        let sender = self.load_sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let listing = std::panic::catch_unwind(|| {
+                Self::read_listing(path, show_hidden, &image_extensions, descend, collapsed_from, tree)
+            })
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("reading the directory failed unexpectedly")));
+            let _ = sender.send((generation, listing));
+        });
+    }
+
+    /// Body of a background load
+    ///
+    /// With a `tree` plan, the tree's expanded directories are read too.
+    fn read_listing(
+        path: PathBuf,
+        show_hidden: bool,
+        image_extensions: &[String],
+        descend: bool,
+        collapsed_from: Option<PathBuf>,
+        tree: Option<TreePlan>,
+    ) -> Result<Listing> {
+        let top = path.clone();
+        let mut path = path;
+        let mut entries = Self::read_entries(&path, show_hidden, image_extensions)?;
+
+        if descend {
+            for _ in 0..MAX_DESCEND_DEPTH {
+                let next = match entries.as_slice() {
+                    [only] if only.is_dir => only.path.clone(),
+                    _ => break,
+                };
+                // Stop at an unreadable link in the chain rather than failing it all
+                let Ok(next_entries) = Self::read_entries(&next, show_hidden, image_extensions) else {
+                    break;
+                };
+                path = next;
+                entries = next_entries;
             }
+        }
+
+        let is_network = is_network_fs(&path);
+        let tree = tree.map(|plan| {
+            // A directory shown as a tree for the first time is opened a few
+            // levels deep, except on network mounts where every read is slow
+            let auto_depth = if plan.root != path && !is_network { tree::AUTO_EXPAND_DEPTH } else { 0 };
+            let mut budget = tree::AUTO_EXPAND_MAX_DIRS;
+            let mut top_level = entries.clone();
+            Self::sort_list(&mut top_level, plan.sort_mode, plan.sort_direction);
+            Self::build_nodes(top_level, &plan, auto_depth, &mut budget, show_hidden, image_extensions)
         });
+
+        Ok(Listing {
+            is_network,
+            collapsed_from: if path != top { Some(top) } else { collapsed_from },
+            path,
+            entries,
+            tree,
+        })
+    }
+
+    /// What a load started now must do for the tree view, if it is on
+    fn tree_plan(&self) -> Option<TreePlan> {
+        let roots = self.tree.as_ref()?;
+        let mut expanded = HashSet::new();
+        tree::expanded_paths(roots, &mut expanded);
+        Some(TreePlan {
+            expanded,
+            root: self.tree_root.clone(),
+            sort_mode: self.sort_mode,
+            sort_direction: self.sort_direction,
+        })
+    }
+
+    /// Show a listing read for `current_path` or a new directory
+    fn install_listing(&mut self, listing: Listing) {
+        // Every load started with the tree on brings its nodes; one started
+        // before the tree was turned off brings nodes no longer wanted
+        if let Some(nodes) = listing.tree.filter(|_| self.tree.is_some()) {
+            self.tree = Some(nodes);
+            self.tree_root = listing.path.clone();
+        }
+        self.current_path = listing.path;
+        self.all_entries = listing.entries;
+        self.is_network = listing.is_network;
+        self.collapsed_from = listing.collapsed_from;
+        self.sort_entries();
+    }
+
+    /// Apply a finished background load, if one arrived
+    ///
+    /// Returns `None` while nothing new has finished, otherwise whether the
+    /// directory could be read and whether it is the one shown before. On
+    /// error the previous listing stays.
+    pub fn poll_loaded(&mut self) -> Option<Result<Loaded>> {
+        while let Ok((generation, listing)) = self.load_receiver.try_recv() {
+            if generation != self.load_generation {
+                continue;
+            }
+            self.loading = None;
+
+            return Some(listing.map(|listing| {
+                let same = listing.path == self.current_path;
+                // A filter belongs to the directory it was typed in
+                if !same {
+                    self.filter.clear();
+                    self.selection_filter = None;
+                }
+                match self.history_target.take() {
+                    Some(index) => self.history_index = index,
+                    None if !same => self.record_history(listing.path.clone()),
+                    None => {}
+                }
+                let previous = std::mem::take(&mut self.entries);
+                self.install_listing(listing);
+                if same { Loaded::Reloaded(previous) } else { Loaded::Entered }
+            }));
+        }
+        None
+    }
+
+    /// Whether a directory is being read in the background
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// Whether the background load has taken long enough to be worth showing
+    ///
+    /// Avoids flashing a loading state for directories that read instantly.
+    pub fn is_loading_slowly(&self) -> bool {
+        self.is_loading() && self.load_started.elapsed() >= SLOW_LOAD
+    }
+
+    /// Sort entries by the active mode, then re-apply the filter
+    ///
+    /// The tree is re-sorted level by level as it is; nothing is read again.
+    fn sort_entries(&mut self) {
+        let (mode, direction) = (self.sort_mode, self.sort_direction);
+        Self::sort_list(&mut self.all_entries, mode, direction);
+        if let Some(roots) = self.tree.as_mut() {
+            tree::sort(roots, &|a, b| Self::compare(a, b, mode, direction));
+        }
+        self.apply_filter();
+    }
+
+    /// Sort one directory's entries
+    fn sort_list(entries: &mut [FileEntry], mode: SortMode, direction: SortDirection) {
+        entries.sort_by(|a, b| Self::compare(a, b, mode, direction));
+    }
+
+    /// Order of two entries of one directory
+    ///
+    /// Directories group first, except when sorting purely by size.
+    fn compare(a: &FileEntry, b: &FileEntry, mode: SortMode, direction: SortDirection) -> std::cmp::Ordering {
+        if mode != SortMode::Size && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
         
-        Ok(())
+        let by_name = || natural_cmp(&a.name, &b.name);
+        let ordering = match mode {
+            SortMode::Name => by_name(),
+            SortMode::Size => a.size.cmp(&b.size).then_with(by_name),
+            SortMode::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+            // Extension, then the name without it, so the order reads down the Ext column
+            SortMode::Extension => {
+                let ((a_base, a_ext), (b_base, b_ext)) = (a.split_extension(), b.split_extension());
+                let ext = |ext: Option<&str>| ext.unwrap_or_default().to_lowercase();
+                ext(a_ext).cmp(&ext(b_ext))
+                    .then_with(|| natural_cmp(a_base, b_base))
+                    .then_with(by_name)
+            }
+        };
+        
+        match direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        }
+    }
+
+    /// Narrow the listing to names containing `query`, ignoring case
+    pub fn set_filter(&mut self, query: &str) {
+        self.filter = query.to_string();
+        self.apply_filter();
+    }
+
+    /// Show only the given paths, hide them, or (with `None`) the whole listing again
+    pub fn set_selection_filter(&mut self, filter: Option<SelectionFilter>) {
+        self.selection_filter = filter;
+        self.apply_filter();
+    }
+
+    /// Rebuild `entries` from `all_entries` (or the tree) and the filters
+    fn apply_filter(&mut self) {
+        let query = self.filter.to_lowercase();
+        let selection = self.selection_filter.as_ref();
+        let matches = |entry: &FileEntry| {
+            (query.is_empty() || entry.name.to_lowercase().contains(&query))
+                && selection.is_none_or(|filter| filter.shows(&entry.path))
+        };
+
+        match &self.tree {
+            Some(roots) => {
+                let mut rows = Vec::new();
+                tree::flatten(roots, 0, &mut rows);
+                (self.entries, self.tree_rows) = rows.into_iter().filter(|(entry, _)| matches(entry)).unzip();
+            }
+            None => {
+                self.entries = self.all_entries.iter().filter(|entry| matches(entry)).cloned().collect();
+                self.tree_rows.clear();
+            }
+        }
+    }
+
+    /// Whether the tree view is on
+    pub fn is_tree(&self) -> bool {
+        self.tree.is_some()
+    }
+
+    /// Switch between the flat listing and the tree view
+    ///
+    /// The tree shows flat until a background load brings its opened levels.
+    pub fn toggle_tree(&mut self) {
+        match self.tree {
+            Some(_) => {
+                self.tree = None;
+                self.apply_filter();
+            }
+            None => {
+                self.tree = Some(self.all_entries.iter().cloned().map(tree::TreeNode::leaf).collect());
+                self.tree_root = PathBuf::new();
+                self.apply_filter();
+                self.reload();
+            }
+        }
+    }
+
+    /// Expand the directory at `index` in the tree view, reading its entries
+    ///
+    /// Returns false if it is not a collapsed directory.
+    pub fn expand(&mut self, index: usize) -> Result<bool> {
+        let (mode, direction, show_hidden) = (self.sort_mode, self.sort_direction, self.show_hidden);
+        let Some(path) = self.entries.get(index).filter(|e| e.is_dir).map(|e| e.path.clone()) else {
+            return Ok(false);
+        };
+        let Some(node) = self.tree.as_mut().and_then(|roots| tree::node_mut(roots, &path)) else {
+            return Ok(false);
+        };
+        if node.expanded {
+            return Ok(false);
+        }
+
+        let mut children = Self::read_entries(&path, show_hidden, &self.image_extensions)?;
+        Self::sort_list(&mut children, mode, direction);
+        node.children = children.into_iter().map(tree::TreeNode::leaf).collect();
+        node.expanded = true;
+        self.apply_filter();
+        Ok(true)
+    }
+
+    /// Collapse the directory at `index`, or if it isn't expanded, find its parent
+    ///
+    /// Returns the row to select: the same directory, or the parent's row.
+    pub fn collapse(&mut self, index: usize) -> Option<usize> {
+        let path = self.entries.get(index)?.path.clone();
+        let node = self.tree.as_mut().and_then(|roots| tree::node_mut(roots, &path))?;
+
+        let target = if node.expanded {
+            // Drop the subtree; it is read again if expanded later
+            node.expanded = false;
+            node.children = Vec::new();
+            self.apply_filter();
+            path
+        } else {
+            path.parent()?.to_path_buf()
+        };
+        self.entries.iter().position(|entry| entry.path == target)
+    }
+
+    /// Nodes for `entries`, expanding those `plan` names and, while `budget`
+    /// lasts, any directory within `auto_depth` levels
+    fn build_nodes(
+        entries: Vec<FileEntry>,
+        plan: &TreePlan,
+        auto_depth: usize,
+        budget: &mut usize,
+        show_hidden: bool,
+        image_extensions: &[String],
+    ) -> Vec<tree::TreeNode> {
+        // This level is read before any deeper one, so the budget goes to the nearest directories
+        let read: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                let wanted = plan.expanded.contains(&entry.path);
+                let auto = auto_depth > 0 && *budget > 0 && !entry.is_symlink;
+                if !entry.is_dir || !(auto || wanted) {
+                    return (entry, None);
+                }
+                if !wanted {
+                    *budget -= 1;
+                }
+                // A directory that vanished or became unreadable just shows collapsed
+                let children = Self::read_entries(&entry.path, show_hidden, image_extensions).ok();
+                (entry, children)
+            })
+            .collect();
+
+        read.into_iter()
+            .map(|(entry, children)| {
+                let Some(mut children) = children else {
+                    return tree::TreeNode::leaf(entry);
+                };
+                Self::sort_list(&mut children, plan.sort_mode, plan.sort_direction);
+                let depth = auto_depth.saturating_sub(1);
+                tree::TreeNode {
+                    children: Self::build_nodes(children, plan, depth, budget, show_hidden, image_extensions),
+                    expanded: true,
+                    entry,
+                }
+            })
+            .collect()
+    }
+
+    /// Change the sort mode and re-sort in place
+    pub fn set_sort(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+        self.sort_entries();
+    }
+
+    /// Sort the other way round, in place
+    pub fn toggle_reverse(&mut self) {
+        self.set_sort_direction(self.sort_direction.flip());
+    }
+
+    /// Sort ascending or descending, in place
+    pub fn set_sort_direction(&mut self, direction: SortDirection) {
+        self.sort_direction = direction;
+        self.sort_entries();
+    }
+
+    /// Show or hide dotfiles and reload
+    pub fn toggle_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        self.reload();
+    }
+
+    /// Read the shown directory again in the background
+    ///
+    /// The listing stays until the new one arrives as `Loaded::Reloaded`.
+    pub fn reload(&mut self) {
+        self.start_load(self.current_path.clone(), false, self.collapsed_from.clone());
     }
 
     /// Navigate into a directory
-    pub fn enter_directory(&mut self, name: &str) -> Result<()> {
-        let new_path = self.current_path.join(name);
-        if new_path.is_dir() {
-            self.current_path = new_path;
-            self.load_directory()?;
+    pub fn enter_directory(&mut self, path: &Path) {
+        self.start_load(path.to_path_buf(), self.auto_descend_single, None);
+    }
+
+    /// Descend while the only entry is a subdirectory (e.g. `src/main/java/com`)
+    ///
+    /// Remembers where the chain started so `go_up` can unwind it in one step.
+    pub fn descend_single_children(&mut self) {
+        self.start_load(self.current_path.clone(), true, self.collapsed_from.clone());
+    }
+
+    /// Take in finished git logs and ask for the shown directory's
+    ///
+    /// Called every frame; the cache decides when git actually runs.
+    pub fn poll_git_log(&mut self) {
+        let Some(git_log) = self.git_log.as_mut() else {
+            return;
+        };
+        git_log.poll_completed();
+        if self.loading.is_none() && !inside_image(&self.current_path) {
+            git_log.request(&self.current_path);
         }
-        Ok(())
     }
 
     /// Navigate to parent directory
-    pub fn go_up(&mut self) -> Result<()> {
-        if let Some(parent) = self.current_path.parent() {
-            self.current_path = parent.to_path_buf();
-            self.load_directory()?;
+    ///
+    /// Returns `false` when already at the filesystem root.
+    pub fn go_up(&mut self) -> Result<bool> {
+        // Unwind a collapsed single-child chain back to where it started
+        if let Some(top) = self.collapsed_from.clone() {
+            self.start_load(top, false, None);
+            return Ok(true);
         }
-        Ok(())
+
+        // A relative path's parent chain ends in "" rather than the real root
+        let current = if self.current_path.is_absolute() {
+            self.current_path.clone()
+        } else {
+            self.current_path.canonicalize()?
+        };
+
+        match current.parent() {
+            Some(parent) => {
+                self.start_load(parent.to_path_buf(), false, None);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Go to the previous directory in history; `false` if there is none
+    pub fn back(&mut self) -> bool {
+        match self.history_index.checked_sub(1) {
+            Some(index) => {
+                self.travel(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go to the next directory in history; `false` if there is none
+    pub fn forward(&mut self) -> bool {
+        let index = self.history_index + 1;
+        if index >= self.history.len() {
+            return false;
+        }
+        self.travel(index);
+        true
+    }
+
+    /// Load a history entry without recording a new one
+    fn travel(&mut self, index: usize) {
+        self.start_load(self.history[index].clone(), false, None);
+        self.history_target = Some(index);
+    }
+
+    /// Record a newly visited directory, dropping the forward stack like a browser
+    fn record_history(&mut self, path: PathBuf) {
+        self.history.truncate(self.history_index + 1);
+        self.history.push(path);
+        self.history_index = self.history.len() - 1;
+    }
+
+    /// Read up to `max_bytes` of a file as text
+    ///
+    /// Returns `None` for binary (non-UTF-8 or NUL-containing) or unreadable files.
+    pub fn read_preview(path: &Path, max_bytes: usize) -> Option<TextPreview> {
+        let bytes = read_file_prefix(path, max_bytes).ok()?;
+        if !is_text(&bytes) {
+            return None;
+        }
+        
+        let file_len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let (line_count, word_count, _) = text_stats(&bytes);
+        
+        // A multi-byte character may be cut off at the cap
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => text,
+            Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+        };
+        
+        Some(TextPreview {
+            // Terminals render raw tabs inconsistently inside widgets
+            lines: text.lines().take(PREVIEW_MAX_LINES).map(|l| l.replace('\t', "    ")).collect(),
+            truncated: file_len > bytes.len() as u64,
+            line_count,
+            word_count,
+        })
     }
 
     /// Get entry at index
@@ -91,3 +892,103 @@ impl FileSystem {
         self.entries.get(index)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_entries_describes_symlinks() {
+        let dir = TempDir::new("links");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::os::unix::fs::symlink("dir", root.join("to-dir")).unwrap();
+        std::os::unix::fs::symlink("missing", root.join("broken")).unwrap();
+
+        let entries = FileSystem::read_entries(root, false, &[]).unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+        assert!(find("to-dir").is_symlink && find("to-dir").is_dir);
+        assert_eq!(find("to-dir").link_target, Some(PathBuf::from("dir")));
+        assert!(find("broken").is_symlink && !find("broken").is_dir);
+        assert!(find("broken").is_broken_link && !find("to-dir").is_broken_link);
+        assert!(!find("dir").is_symlink);
+    }
+
+    #[test]
+    fn test_selection_filter_narrows_and_restores() {
+        let dir = TempDir::new("selection-filter");
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let mut fs = FileSystem::new(dir.path().to_path_buf());
+        fs.load_directory().unwrap();
+        let names = |fs: &FileSystem| fs.entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        let marked: HashSet<PathBuf> = [dir.path().join("b")].into();
+
+        fs.set_selection_filter(Some(SelectionFilter::Only(marked.clone())));
+        assert_eq!(names(&fs), ["b"]);
+        fs.set_selection_filter(Some(SelectionFilter::Hide(marked)));
+        assert_eq!(names(&fs), ["a", "c"]);
+        fs.set_filter("c");
+        assert_eq!(names(&fs), ["c"]);
+        fs.set_filter("");
+        fs.set_selection_filter(None);
+        assert_eq!(names(&fs), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_extension_sort_follows_the_split_name() {
+        let dir = TempDir::new("extension-sort");
+        for name in ["report.txt", "report-2.md", "notes.TXT", ".bashrc", "archive.tar.gz"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("src.old")).unwrap();
+        let mut fs = FileSystem::new(dir.path().to_path_buf());
+        fs.show_hidden = true;
+        fs.load_directory().unwrap();
+        fs.set_sort(SortMode::Extension);
+
+        let split: Vec<_> = fs.entries.iter().map(FileEntry::split_extension).collect();
+        assert_eq!(split, [
+            ("src.old", None),
+            (".bashrc", None),
+            ("archive.tar", Some("gz")),
+            ("report-2", Some("md")),
+            ("notes", Some("TXT")),
+            ("report", Some("txt")),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_tree_opens_a_bounded_depth_in_the_background() {
+        let dir = TempDir::new("tree-depth");
+        std::fs::create_dir_all(dir.path().join("a/b/c/d")).unwrap();
+        let mut fs = FileSystem::new(dir.path().to_path_buf());
+        fs.load_directory().unwrap();
+        let depths = |fs: &FileSystem| fs.tree_rows.iter().map(|row| row.depth).collect::<Vec<_>>();
+
+        fs.toggle_tree();
+        assert_eq!(depths(&fs), [0]);
+        let loaded = loop {
+            if let Some(loaded) = fs.poll_loaded() {
+                break loaded.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+        assert!(matches!(loaded, Loaded::Reloaded(_)));
+        // a and b are opened, so c shows collapsed two levels down
+        assert_eq!(depths(&fs), [0, 1, 2]);
+        assert!(!fs.tree_rows[2].expanded);
+
+        // Re-sorting keeps the tree as read rather than reading it again
+        std::fs::create_dir(dir.path().join("a/b2")).unwrap();
+        fs.set_sort(SortMode::Modified);
+        assert_eq!(depths(&fs), [0, 1, 2]);
+
+        fs.collapse(0);
+        fs.set_sort(SortMode::Name);
+        assert_eq!(depths(&fs), [0]);
+    }
+}
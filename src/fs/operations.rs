@@ -0,0 +1,379 @@
+//! File operations (open, terminal, paste, trash, delete, rename, create)
+
+use anyhow::{bail, Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::{copy_recursive_with, inside_image, member_of_image, unique_copy_path};
+
+/// How a clipboard entry is pasted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    Copy,
+    Cut,
+}
+
+/// Paths recorded by Copy or Cut, waiting to be pasted
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    pub paths: Vec<PathBuf>,
+    pub mode: ClipboardMode,
+}
+
+/// Open a file, or a URL, with the platform's default application, detached
+pub fn open_external(target: impl AsRef<OsStr>) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+
+    command
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("No application to open the file with")?;
+    Ok(())
+}
+
+/// `editor` (`$EDITOR`, possibly with arguments) opening `path`, at `line` if known
+///
+/// `line_flag` is the config's `editor_line_flag`: arguments where `{line}`
+/// stands for the line and `{path}` for the file; without `{path}` the file
+/// goes last. Without a line the flag is left out and the editor opens at the top.
+pub fn editor_command(editor: &str, line_flag: &str, path: &Path, line: Option<usize>) -> Vec<OsString> {
+    let mut command: Vec<OsString> = editor.split_whitespace().map(OsString::from).collect();
+    let mut path_placed = false;
+    if let Some(line) = line {
+        for arg in line_flag.split_whitespace() {
+            let arg = arg.replace("{line}", &line.to_string());
+            match arg.split_once("{path}") {
+                Some((before, after)) => {
+                    let mut with_path = OsString::from(before);
+                    with_path.push(path);
+                    with_path.push(after);
+                    command.push(with_path);
+                    path_placed = true;
+                }
+                None => command.push(arg.into()),
+            }
+        }
+    }
+    if !path_placed {
+        command.push(path.into());
+    }
+    command
+}
+
+/// Start `command`, a terminal emulator and its arguments, in `dir`, detached
+pub fn open_terminal(command: &[String], dir: &Path) -> Result<()> {
+    let (program, args) = command.split_first().context("No terminal configured")?;
+    Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Cannot start {}", program))?;
+    Ok(())
+}
+
+/// Paste one clipboard path into `dest_dir`, returning the new path
+///
+/// A copy that would collide gets a "(copy)" name instead; a move refuses.
+/// `preserve_metadata` applies to copies; a move always keeps times and
+/// permissions, as a rename would. `on_file` is called with the size of each
+/// file copied; an error from it stops the paste, and removes what was copied
+/// of `source` so far.
+pub fn paste(
+    source: &Path,
+    mode: ClipboardMode,
+    dest_dir: &Path,
+    preserve_metadata: bool,
+    on_file: &mut dyn FnMut(u64) -> Result<()>,
+) -> Result<PathBuf> {
+    let name = source.file_name().context("Nothing to paste")?;
+    let target = dest_dir.join(name);
+    if source.is_dir() && dest_dir.starts_with(source) {
+        bail!("Cannot paste {} into itself", name.to_string_lossy());
+    }
+    if inside_image(dest_dir) || (mode == ClipboardMode::Cut && member_of_image(source).is_some()) {
+        bail!("Disk images are read-only");
+    }
+
+    match mode {
+        ClipboardMode::Copy => {
+            let target = unique_copy_path(&target);
+            copy_or_clean_up(source, &target, preserve_metadata, on_file)?;
+            Ok(target)
+        }
+        ClipboardMode::Cut => {
+            if target.exists() {
+                bail!("{} already exists", target.display());
+            }
+            move_entry(&RealFs, source, &target, on_file).into_result(source, &target)?;
+            Ok(target)
+        }
+    }
+}
+
+/// The filesystem calls a move makes, so tests can fail any one of them
+trait FsOps {
+    fn rename(&self, source: &Path, target: &Path) -> std::io::Result<()>;
+    /// Copy recursively, keeping metadata; `on_file` as for `paste`
+    fn copy(&self, source: &Path, target: &Path, on_file: &mut dyn FnMut(u64) -> Result<()>) -> Result<()>;
+    /// Delete a file or a whole tree
+    fn remove(&self, path: &Path) -> Result<()>;
+    /// Whether anything, even a broken link, is at `path`
+    fn exists(&self, path: &Path) -> bool;
+}
+
+struct RealFs;
+
+impl FsOps for RealFs {
+    fn rename(&self, source: &Path, target: &Path) -> std::io::Result<()> {
+        std::fs::rename(source, target)
+    }
+
+    fn copy(&self, source: &Path, target: &Path, on_file: &mut dyn FnMut(u64) -> Result<()>) -> Result<()> {
+        copy_recursive_with(source, target, true, on_file).map(drop)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        std::fs::symlink_metadata(path).is_ok()
+    }
+}
+
+/// Where a move left things
+#[derive(Debug)]
+enum MoveState {
+    Moved,
+    /// Nothing changed; the rename failed for a reason other than crossing filesystems
+    Failed(anyhow::Error),
+    /// The copy is complete but deleting the source failed, so both are kept
+    SourceRemains(anyhow::Error),
+    /// The copy failed and what it made of the target was removed
+    CleanedUp(anyhow::Error),
+    /// The copy failed and its partial target couldn't be removed either
+    PartialCopyLeft(anyhow::Error),
+}
+
+impl MoveState {
+    /// Ok for a finished move, otherwise an error saying what is left where
+    fn into_result(self, source: &Path, target: &Path) -> Result<()> {
+        match self {
+            Self::Moved => Ok(()),
+            Self::Failed(e) => Err(e),
+            Self::SourceRemains(e) => Err(e.context(format!("Copied, but {} remains", source.display()))),
+            Self::CleanedUp(e) => Err(e.context("Move failed, cleaned up")),
+            Self::PartialCopyLeft(e) => Err(e.context(format!("Move failed, partial copy left at {}", target.display()))),
+        }
+    }
+}
+
+/// Move `source` to `target`, copying and then deleting it when the two are
+/// on different filesystems, where a plain rename fails
+///
+/// The source is only deleted once the copy is complete, and a copy that
+/// fails midway is removed again, so a failure never loses the only copy.
+/// `on_file` is only called by the copy; a rename is over at once.
+fn move_entry(ops: &dyn FsOps, source: &Path, target: &Path, on_file: &mut dyn FnMut(u64) -> Result<()>) -> MoveState {
+    match ops.rename(source, target) {
+        Ok(()) => MoveState::Moved,
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            if let Err(e) = ops.copy(source, target, on_file) {
+                if ops.exists(target) && ops.remove(target).is_err() {
+                    return MoveState::PartialCopyLeft(e);
+                }
+                return MoveState::CleanedUp(e);
+            }
+            match ops.remove(source) {
+                Ok(()) => MoveState::Moved,
+                Err(e) => MoveState::SourceRemains(e),
+            }
+        }
+        Err(e) => MoveState::Failed(e.into()),
+    }
+}
+
+/// Copy `source` to `target`, which must not exist, removing the partial
+/// copy if the copy stops midway
+///
+/// A source inside a disk image is extracted from it.
+fn copy_or_clean_up(
+    source: &Path,
+    target: &Path,
+    preserve_metadata: bool,
+    on_file: &mut dyn FnMut(u64) -> Result<()>,
+) -> Result<()> {
+    let copied = if member_of_image(source).is_some() {
+        super::iso::extract(source, target, on_file)
+    } else {
+        copy_recursive_with(source, target, preserve_metadata, on_file).map(drop)
+    };
+    if let Err(e) = copied {
+        if std::fs::symlink_metadata(target).is_ok() {
+            let _ = delete(target);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Move a file or directory to the platform's trash
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    trash::delete(path).with_context(|| format!("Cannot move {} to Trash", path.display()))
+}
+
+/// Permanently delete a file, or a directory with everything in it
+pub fn delete(path: &Path) -> Result<()> {
+    // symlink_metadata so a link to a directory removes the link, not the tree
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Check a name typed for a new or renamed entry, returning it trimmed
+fn validate_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Name cannot be empty");
+    }
+    if name.contains(['/', std::path::MAIN_SEPARATOR]) {
+        bail!("Name cannot contain path separators");
+    }
+    if name == "." || name == ".." {
+        bail!("{} is not a valid name", name);
+    }
+    Ok(name)
+}
+
+/// Rename an entry within its directory, returning the new path
+pub fn rename(path: &Path, new_name: &str) -> Result<PathBuf> {
+    let new_name = validate_name(new_name)?;
+    let target = path.with_file_name(new_name);
+    if target.exists() {
+        bail!("{} already exists", new_name);
+    }
+    move_entry(&RealFs, path, &target, &mut |_| Ok(())).into_result(path, &target)?;
+    Ok(target)
+}
+
+/// Create an empty file in `dir`, returning its path
+pub fn create_file(dir: &Path, name: &str) -> Result<PathBuf> {
+    let target = dir.join(validate_name(name)?);
+    // create_new fails instead of truncating a file that appeared meanwhile
+    std::fs::File::create_new(&target).map_err(|e| creation_error(e, &target))?;
+    Ok(target)
+}
+
+/// Create a directory in `dir`, returning its path
+pub fn create_dir(dir: &Path, name: &str) -> Result<PathBuf> {
+    let target = dir.join(validate_name(name)?);
+    std::fs::create_dir(&target).map_err(|e| creation_error(e, &target))?;
+    Ok(target)
+}
+
+fn creation_error(e: std::io::Error, target: &Path) -> anyhow::Error {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    if e.kind() == std::io::ErrorKind::AlreadyExists {
+        anyhow::anyhow!("{} already exists", name)
+    } else {
+        anyhow::Error::new(e).context(format!("Cannot create {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command_places_the_line() {
+        let path = Path::new("/src/main.rs");
+        let args = |editor: &str, flag: &str, line| {
+            editor_command(editor, flag, path, line).into_iter().map(|arg| arg.into_string().unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(args("vim", "+{line}", Some(42)), ["vim", "+42", "/src/main.rs"]);
+        assert_eq!(args("code -w", "--goto {path}:{line}", Some(7)), ["code", "-w", "--goto", "/src/main.rs:7"]);
+        // No line tracked: the flag is left out and the file opens at the top
+        assert_eq!(args("vim", "+{line}", None), ["vim", "/src/main.rs"]);
+    }
+
+    /// A filesystem where every rename crosses devices and chosen calls fail
+    #[derive(Default)]
+    struct MockFs {
+        fail_copy: bool,
+        fail_remove: Vec<PathBuf>,
+        existing: std::cell::RefCell<Vec<PathBuf>>,
+    }
+
+    impl FsOps for MockFs {
+        fn rename(&self, _: &Path, _: &Path) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::CrossesDevices.into())
+        }
+
+        fn copy(&self, _: &Path, target: &Path, _: &mut dyn FnMut(u64) -> Result<()>) -> Result<()> {
+            // A failed copy still leaves part of the target behind
+            self.existing.borrow_mut().push(target.to_path_buf());
+            if self.fail_copy {
+                bail!("disk full");
+            }
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> Result<()> {
+            if self.fail_remove.iter().any(|failing| failing == path) {
+                bail!("permission denied");
+            }
+            self.existing.borrow_mut().retain(|existing| existing != path);
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.existing.borrow().iter().any(|existing| existing == path)
+        }
+    }
+
+    #[test]
+    fn test_move_across_filesystems_reports_what_is_left() {
+        let (source, target) = (Path::new("/a/report"), Path::new("/b/report"));
+        let status = |fs: &MockFs| {
+            fs.existing.borrow_mut().push(source.to_path_buf());
+            let result = move_entry(fs, source, target, &mut |_| Ok(())).into_result(source, target);
+            (result.map_err(|e| format!("{:#}", e)), fs.exists(source), fs.exists(target))
+        };
+
+        assert_eq!(status(&MockFs::default()), (Ok(()), false, true));
+
+        let source_stuck = MockFs { fail_remove: vec![source.to_path_buf()], ..MockFs::default() };
+        assert_eq!(
+            status(&source_stuck),
+            (Err("Copied, but /a/report remains: permission denied".to_string()), true, true)
+        );
+
+        let copy_fails = MockFs { fail_copy: true, ..MockFs::default() };
+        assert_eq!(status(&copy_fails), (Err("Move failed, cleaned up: disk full".to_string()), true, false));
+
+        let cleanup_fails = MockFs { fail_copy: true, fail_remove: vec![target.to_path_buf()], ..MockFs::default() };
+        assert_eq!(
+            status(&cleanup_fails),
+            (Err("Move failed, partial copy left at /b/report: disk full".to_string()), true, true)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! The platform trash: how much it holds, measured in the background, and emptying it
+//!
+//! Listing and purging the trash is only possible on Windows and with
+//! freedesktop trash folders (Linux and the BSDs); elsewhere
+//! `TrashBin::SUPPORTED` is false and no size is ever reported.
+
+use anyhow::Result;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Items in the trash and the bytes they take up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrashSummary {
+    pub items: usize,
+    pub bytes: u64,
+}
+
+enum TrashMessage {
+    Measured(Result<TrashSummary, String>),
+    /// What emptying freed
+    Emptied(Result<TrashSummary, String>),
+}
+
+/// The trash's size, measured on request, and a purge running on a blocking worker
+pub struct TrashBin {
+    /// The last measurement, until `invalidate`
+    pub summary: Option<TrashSummary>,
+    measuring: bool,
+    pub emptying: bool,
+    sender: UnboundedSender<TrashMessage>,
+    receiver: UnboundedReceiver<TrashMessage>,
+}
+
+impl TrashBin {
+    /// Whether the trash can be measured and emptied here
+    pub const SUPPORTED: bool = cfg!(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ));
+
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { summary: None, measuring: false, emptying: false, sender, receiver }
+    }
+
+    /// Start measuring, unless a measurement is cached or running
+    pub fn request(&mut self) {
+        if !Self::SUPPORTED || self.summary.is_some() || self.measuring || self.emptying {
+            return;
+        }
+        self.measuring = true;
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(TrashMessage::Measured(measure().map_err(|e| format!("{:#}", e))));
+        });
+    }
+
+    /// Forget the measurement, after something was moved to the trash
+    pub fn invalidate(&mut self) {
+        self.summary = None;
+    }
+
+    /// Delete everything in the trash for good; `poll` reports what was freed
+    pub fn empty(&mut self) {
+        if self.emptying {
+            return;
+        }
+        self.emptying = true;
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(TrashMessage::Emptied(purge().map_err(|e| format!("{:#}", e))));
+        });
+    }
+
+    /// Take in finished work, returning the outcome of emptying once it is done
+    ///
+    /// A failed measurement just leaves the size unknown; it is logged.
+    pub fn poll(&mut self) -> Option<Result<TrashSummary, String>> {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                TrashMessage::Measured(result) => {
+                    self.measuring = false;
+                    match result {
+                        Ok(summary) => self.summary = Some(summary),
+                        Err(e) => log::warn!("Cannot measure the trash: {}", e),
+                    }
+                }
+                TrashMessage::Emptied(result) => {
+                    self.emptying = false;
+                    self.summary = None;
+                    return Some(result);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn measure() -> Result<TrashSummary> {
+    let items = trash::os_limited::list()?;
+    Ok(TrashSummary { items: items.len(), bytes: items.iter().map(item_size).sum() })
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn purge() -> Result<TrashSummary> {
+    let items = trash::os_limited::list()?;
+    let freed = TrashSummary { items: items.len(), bytes: items.iter().map(item_size).sum() };
+    trash::os_limited::purge_all(items)?;
+    Ok(freed)
+}
+
+/// A trashed file's or directory's size, found next to its `.trashinfo`
+///
+/// The freedesktop layout keeps `info/<name>.trashinfo` beside `files/<name>`.
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+fn item_size(item: &trash::TrashItem) -> u64 {
+    let info = std::path::Path::new(&item.id);
+    let (Some(stem), Some(trash_dir)) = (info.file_stem(), info.parent().and_then(|dir| dir.parent())) else {
+        return 0;
+    };
+    let file = trash_dir.join("files").join(stem);
+    match std::fs::symlink_metadata(&file) {
+        Ok(metadata) if metadata.is_dir() => super::get_directory_size(&file).map(|size| size.bytes).unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+/// A trashed file's size; Windows only counts a directory's entries, so those add nothing
+#[cfg(target_os = "windows")]
+fn item_size(item: &trash::TrashItem) -> u64 {
+    trash::os_limited::metadata(item).ok().and_then(|metadata| metadata.size.size()).unwrap_or(0)
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn measure() -> Result<TrashSummary> {
+    anyhow::bail!("The trash can't be listed on this platform")
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn purge() -> Result<TrashSummary> {
+    anyhow::bail!("The trash can't be emptied on this platform")
+}
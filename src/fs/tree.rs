@@ -0,0 +1,123 @@
+//! Collapsible tree view of the current directory
+//!
+//! Subdirectories are read when first expanded and dropped again on
+//! collapse, so only the visible part of the tree is ever in memory.
+//! Showing a directory as a tree opens its first `AUTO_EXPAND_DEPTH`
+//! levels as part of the background load, reading at most
+//! `AUTO_EXPAND_MAX_DIRS` directories so a wide tree still arrives soon.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::FileEntry;
+
+/// Levels below the current directory opened when the tree is shown
+pub const AUTO_EXPAND_DEPTH: usize = 2;
+/// Directories read at most for that, nearest levels first
+pub const AUTO_EXPAND_MAX_DIRS: usize = 32;
+
+/// A listed entry and, while expanded, its children
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    pub entry: FileEntry,
+    pub expanded: bool,
+    /// Empty unless expanded
+    pub children: Vec<TreeNode>,
+}
+
+/// Layout of one visible tree row, parallel to `FileSystem::entries`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeRow {
+    /// Nesting below the current directory; top-level entries are 0
+    pub depth: usize,
+    pub expanded: bool,
+}
+
+impl TreeNode {
+    pub fn leaf(entry: FileEntry) -> Self {
+        Self { entry, expanded: false, children: Vec::new() }
+    }
+}
+
+/// Find the node for `path`, descending only through expanded directories
+pub fn node_mut<'a>(nodes: &'a mut [TreeNode], path: &Path) -> Option<&'a mut TreeNode> {
+    for node in nodes.iter_mut() {
+        if node.entry.path == path {
+            return Some(node);
+        }
+        if node.expanded && path.starts_with(&node.entry.path) {
+            return node_mut(&mut node.children, path);
+        }
+    }
+    None
+}
+
+/// Paths of every expanded directory, to carry expansion over a rebuild
+pub fn expanded_paths(nodes: &[TreeNode], paths: &mut HashSet<PathBuf>) {
+    for node in nodes.iter().filter(|node| node.expanded) {
+        paths.insert(node.entry.path.clone());
+        expanded_paths(&node.children, paths);
+    }
+}
+
+/// Sort every level in place, keeping what is expanded
+pub fn sort(nodes: &mut [TreeNode], compare: &impl Fn(&FileEntry, &FileEntry) -> Ordering) {
+    nodes.sort_by(|a, b| compare(&a.entry, &b.entry));
+    for node in nodes.iter_mut().filter(|node| node.expanded) {
+        sort(&mut node.children, compare);
+    }
+}
+
+/// Visible rows in display order
+pub fn flatten(nodes: &[TreeNode], depth: usize, rows: &mut Vec<(FileEntry, TreeRow)>) {
+    for node in nodes {
+        rows.push((node.entry.clone(), TreeRow { depth, expanded: node.expanded }));
+        if node.expanded {
+            flatten(&node.children, depth + 1, rows);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileType;
+
+    fn entry(path: &str, is_dir: bool) -> FileEntry {
+        FileEntry {
+            name: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+            path: PathBuf::from(path),
+            is_dir,
+            file_type: if is_dir { FileType::Directory } else { FileType::Regular },
+            size: 0,
+            modified: None,
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
+            mode: 0,
+            uid: 0,
+            gid: 0,
+            is_image: false,
+            writable: true,
+        }
+    }
+
+    #[test]
+    fn test_flatten_follows_expansion() {
+        let mut roots = vec![TreeNode::leaf(entry("/r/a", true)), TreeNode::leaf(entry("/r/b", false))];
+        let a = node_mut(&mut roots, Path::new("/r/a")).unwrap();
+        a.expanded = true;
+        a.children = vec![TreeNode::leaf(entry("/r/a/c", true))];
+        assert!(node_mut(&mut roots, Path::new("/r/a/c")).is_some());
+
+        let mut rows = Vec::new();
+        flatten(&roots, 0, &mut rows);
+        let depths: Vec<usize> = rows.iter().map(|(_, row)| row.depth).collect();
+        assert_eq!(depths, vec![0, 1, 0]);
+
+        let mut expanded = HashSet::new();
+        expanded_paths(&roots, &mut expanded);
+        assert_eq!(expanded, HashSet::from([PathBuf::from("/r/a")]));
+    }
+}
@@ -0,0 +1,110 @@
+//! Watch the open directories for changes made outside the app
+
+use anyhow::Result;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Quiet period after the last event before reporting a change
+///
+/// Long enough that a `git checkout` touching many files causes one reload.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Report anyway after this long, so a file written continuously still refreshes
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Watches a few directories (not recursively) and reports debounced changes
+pub struct DirectoryWatcher {
+    watcher: RecommendedWatcher,
+    /// Paths named by each event
+    receiver: Receiver<Vec<PathBuf>>,
+    /// Directories currently registered with the watcher
+    watched: Vec<PathBuf>,
+    /// Watched directories with events not yet reported
+    changed: HashSet<PathBuf>,
+    /// Times of the first and latest events not yet reported
+    pending: Option<(Instant, Instant)>,
+}
+
+impl DirectoryWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // Reading files for previews shows up as access events; ignore those
+            match event {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    let _ = sender.send(event.paths);
+                }
+                _ => {}
+            }
+        })?;
+
+        Ok(Self {
+            watcher,
+            receiver,
+            watched: Vec::new(),
+            changed: HashSet::new(),
+            pending: None,
+        })
+    }
+
+    /// Watch exactly `paths`, dropping directories no longer among them
+    ///
+    /// Directories already watched are left alone, so this is cheap to call every frame.
+    /// Returns the first error from a directory that couldn't be watched.
+    pub fn watch(&mut self, paths: &[&Path]) -> Result<()> {
+        let (kept, dropped): (Vec<PathBuf>, Vec<PathBuf>) = std::mem::take(&mut self.watched)
+            .into_iter()
+            .partition(|old| paths.contains(&old.as_path()));
+        for old in &dropped {
+            let _ = self.watcher.unwatch(old);
+            self.changed.remove(old);
+        }
+        self.watched = kept;
+
+        let mut result = Ok(());
+        for &path in paths {
+            if self.watched.iter().any(|watched| watched == path) {
+                continue;
+            }
+            // Recorded even on failure, so an unwatchable directory isn't retried every frame
+            self.watched.push(path.to_path_buf());
+            if let Err(e) = self.watcher.watch(path, RecursiveMode::NonRecursive) {
+                if result.is_ok() {
+                    result = Err(e.into());
+                }
+            }
+        }
+        result
+    }
+
+    /// Watched directories that changed and have been quiet for the debounce period
+    pub fn poll_changed(&mut self) -> Vec<PathBuf> {
+        while let Ok(paths) = self.receiver.try_recv() {
+            // An event names the entry that changed, or the directory itself
+            let touched: Vec<&PathBuf> = self.watched
+                .iter()
+                .filter(|dir| paths.iter().any(|p| p == *dir || p.parent() == Some(dir.as_path())))
+                .collect();
+            if touched.is_empty() {
+                // Left over from a directory no longer watched
+                continue;
+            }
+            self.changed.extend(touched.into_iter().cloned());
+
+            let now = Instant::now();
+            let first = self.pending.map_or(now, |(first, _)| first);
+            self.pending = Some((first, now));
+        }
+
+        match self.pending {
+            Some((first, last)) if last.elapsed() >= DEBOUNCE || first.elapsed() >= MAX_DELAY => {
+                self.pending = None;
+                self.changed.drain().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Persistent thumbnail cache on disk
+//!
+//! Resized thumbnails are stored as PNGs under the user cache directory so a
+//! fresh launch doesn't decode every image again. Files are named
+//! `<path hash>-<stamp hash>.png`, where the stamp covers the source's mtime
+//! and size plus the thumbnail settings; a changed source gets a new name and
+//! the old entry is removed when the new one is written.
+
+use anyhow::Result;
+use image::DynamicImage;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Thumbnails saved between runs
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Cache under `dirs::cache_dir()/graphterm/thumbnails`, if there is one
+    pub fn new() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Self::at(dir.join("graphterm").join("thumbnails")))
+    }
+
+    /// Cache in a specific directory
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Load the stored thumbnail for `path`, unless the source changed since
+    ///
+    /// `variant` identifies the thumbnail settings (size, quality).
+    pub fn load(&self, path: &Path, variant: &str) -> Option<DynamicImage> {
+        let entry = self.entry_path(path, variant)?;
+        image::open(entry).ok()
+    }
+
+    /// Store a thumbnail for `path`, replacing entries for older versions
+    pub fn store(&self, path: &Path, variant: &str, thumbnail: &DynamicImage) -> Result<()> {
+        let Some(entry) = self.entry_path(path, variant) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        self.remove_stale(path, &entry);
+
+        // Write under a temporary name so a crash never leaves half a PNG
+        let partial = entry.with_extension("part");
+        thumbnail.save_with_format(&partial, image::ImageFormat::Png)?;
+        std::fs::rename(&partial, &entry)?;
+        Ok(())
+    }
+
+    /// Remove every stored thumbnail
+    #[cfg(test)]
+    pub fn clear(&self) -> Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// File name for the current version of `path`, or None if it can't be stat'ed
+    fn entry_path(&self, path: &Path, variant: &str) -> Option<PathBuf> {
+        let path = std::fs::canonicalize(path).ok()?;
+        let metadata = std::fs::metadata(&path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+
+        let stamp = hash_of((modified.as_nanos(), metadata.len(), variant));
+        Some(self.dir.join(format!("{:016x}-{:016x}.png", hash_of(&path), stamp)))
+    }
+
+    /// Delete other entries for the same source path
+    fn remove_stale(&self, path: &Path, keep: &Path) {
+        let Some(prefix) = std::fs::canonicalize(path)
+            .ok()
+            .map(|path| format!("{:016x}-", hash_of(&path)))
+        else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let stale = entry.file_name().to_str().is_some_and(|name| name.starts_with(&prefix));
+            if stale && entry.path() != keep {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// `DefaultHasher::new` uses fixed keys, so names stay the same across runs
+/// (a toolchain update may change them, which only costs a regeneration)
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_changed_source_is_regenerated() {
+        let dir = TempDir::new("thumbs");
+        let root = dir.path();
+        let source = root.join("source.bin");
+        std::fs::write(&source, b"one").unwrap();
+
+        let cache = DiskCache::at(root.join("cache"));
+        let thumbnail = DynamicImage::new_rgba8(4, 4);
+        cache.store(&source, "200-fast", &thumbnail).unwrap();
+        assert!(cache.load(&source, "200-fast").is_some());
+        assert!(cache.load(&source, "200-high").is_none());
+
+        // A different size changes the stamp
+        std::fs::write(&source, b"longer").unwrap();
+        assert!(cache.load(&source, "200-fast").is_none());
+        cache.store(&source, "200-fast", &thumbnail).unwrap();
+        assert_eq!(std::fs::read_dir(root.join("cache")).unwrap().count(), 1);
+
+        cache.clear().unwrap();
+        assert!(!root.join("cache").exists());
+    }
+}
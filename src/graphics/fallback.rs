@@ -0,0 +1,91 @@
+//! Half-block fallback for terminals without a graphics protocol
+//!
+//! Each cell shows two vertical pixels: `▀` in the top pixel's color over a
+//! background in the bottom pixel's, using 24-bit SGR colors.
+
+use image::imageops::FilterType;
+use image::Rgba;
+use std::fmt::Write;
+
+/// Pixels with alpha below this are left at the terminal background
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Render `image_data` (PNG, JPEG, ...) as block art fitting `width` x `height` cells
+///
+/// Like Sixel, the art is drawn at the cursor: rows are joined by relative
+/// cursor moves, and the cursor is restored afterwards.
+pub fn render(width: u16, height: u16, image_data: &[u8]) -> String {
+    let Ok(img) = image::load_from_memory(image_data) else {
+        return String::new();
+    };
+    if width == 0 || height == 0 {
+        return String::new();
+    }
+
+    let rgba = img.resize(width as u32, height as u32 * 2, FilterType::Triangle).to_rgba8();
+    let (w, h) = (rgba.width(), rgba.height());
+    if w == 0 || h == 0 {
+        return String::new();
+    }
+
+    // Save the cursor so the UI isn't disturbed
+    let mut out = String::from("\x1b7");
+    for row in 0..h.div_ceil(2) {
+        if row > 0 {
+            let _ = write!(out, "\x1b[{}D\x1b[1B", w);
+        }
+        for x in 0..w {
+            let top = rgba.get_pixel(x, row * 2);
+            let bottom = (row * 2 + 1 < h).then(|| rgba.get_pixel(x, row * 2 + 1));
+            push_cell(&mut out, visible(top), bottom.and_then(visible));
+        }
+        out.push_str("\x1b[0m");
+    }
+    out.push_str("\x1b8");
+    out
+}
+
+fn visible(pixel: &Rgba<u8>) -> Option<[u8; 3]> {
+    let [r, g, b, a] = pixel.0;
+    (a >= ALPHA_THRESHOLD).then_some([r, g, b])
+}
+
+/// One cell: the upper half block colored by `top`, with `bottom` behind it
+fn push_cell(out: &mut String, top: Option<[u8; 3]>, bottom: Option<[u8; 3]>) {
+    match (top, bottom) {
+        (Some([r, g, b]), Some([br, bg, bb])) => {
+            let _ = write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m▀", r, g, b, br, bg, bb);
+        }
+        (Some([r, g, b]), None) => {
+            let _ = write!(out, "\x1b[38;2;{};{};{};49m▀", r, g, b);
+        }
+        (None, Some([r, g, b])) => {
+            let _ = write!(out, "\x1b[38;2;{};{};{};49m▄", r, g, b);
+        }
+        (None, None) => out.push_str("\x1b[49m "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_two_pixels_per_cell() {
+        let mut img = image::RgbaImage::new(2, 4);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if y % 2 == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) };
+            if x == 1 && y >= 2 {
+                *pixel = Rgba([0, 0, 0, 0]);
+            }
+        }
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let art = render(2, 2, &png);
+        assert!(art.starts_with("\x1b7") && art.ends_with("\x1b8"));
+        assert_eq!(art.matches('▀').count(), 3);
+        assert_eq!(art.matches("\x1b[49m ").count(), 1);
+        assert_eq!(art.matches("\x1b[2D\x1b[1B").count(), 1);
+    }
+}
@@ -1,18 +1,49 @@
 //! Icon management and rendering via terminal graphics protocols
 
-use image::{DynamicImage, RgbaImage, Rgba, imageops::FilterType};
-use std::collections::HashMap;
-use std::path::Path;
+use image::{RgbaImage, Rgba};
+use std::collections::{HashMap, HashSet};
 
 use crate::graphics::{GraphicsBackend, GraphicsProtocol};
 
-/// Default icon size in terminal cells
+/// Icon image size in pixels
 pub const ICON_SIZE: u32 = 16;
 
+/// Cells an icon occupies in the file grid (columns, rows); the terminal
+/// scales the `ICON_SIZE` image to fit
+pub const ICON_CELLS: (u16, u16) = (2, 1);
+
+/// Kitty image ids for icons start here, clear of the thumbnail counter
+const ICON_ID_BASE: u32 = 0x4000_0000;
+
+/// Where the file grid wants an icon drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconSlot {
+    /// Screen column and row (0-indexed)
+    pub x: u16,
+    pub y: u16,
+    /// Lowercase extension, ignored for directories and images
+    pub file_type: String,
+    pub is_dir: bool,
+    /// `FileEntry::is_image`
+    pub is_image: bool,
+    /// The row is selected or marked; its restyle by ratatui wipes iTerm2 images
+    pub highlighted: bool,
+}
+
+/// A generated icon and how it has been sent
+struct CachedIcon {
+    /// Kitty image id
+    id: u32,
+    /// Transmission (Kitty) or inline image (iTerm2)
+    sequence: String,
+    /// Whether the Kitty transmission has been written
+    transmitted: bool,
+}
+
 /// Icon manager - handles loading, caching, and rendering file type icons
 pub struct IconManager {
-    /// Cached rendered icon escape sequences
-    cache: HashMap<String, String>,
+    /// Cached icons by type key
+    cache: HashMap<String, CachedIcon>,
     /// Graphics backend
     backend: GraphicsBackend,
 }
@@ -25,36 +56,110 @@ impl IconManager {
         }
     }
 
-    /// Get the icon escape sequence for a file type
-    /// Returns empty string if graphics not supported (use emoji fallback)
-    pub fn get_icon_sequence(&mut self, file_type: &str, is_dir: bool) -> String {
-        if !self.backend.supports_images() {
+    /// Whether the backend can draw icons (Kitty and iTerm2)
+    pub fn supported(backend: &GraphicsBackend) -> bool {
+        matches!(backend.protocol, GraphicsProtocol::Kitty | GraphicsProtocol::ITerm2)
+    }
+
+    /// Escape sequences drawing an icon in every slot
+    ///
+    /// Meant to be written after ratatui draws the frame, only when the slots
+    /// change. With Kitty, icons placed earlier are removed first and each
+    /// icon's data is sent once, then placed by id. The cursor is saved and
+    /// restored around the output.
+    pub fn render_slots(&mut self, slots: &[IconSlot]) -> String {
+        if !Self::supported(&self.backend) {
             return String::new();
         }
 
-        let key = if is_dir { "folder" } else { file_type }.to_string();
-        
-        if let Some(cached) = self.cache.get(&key) {
-            return cached.clone();
+        let mut out = String::from("\x1b7");
+        let kitty = self.backend.protocol == GraphicsProtocol::Kitty;
+        if kitty {
+            // Lowercase d=i keeps the data for the next placement
+            for icon in self.cache.values() {
+                out.push_str(&format!("\x1b_Ga=d,d=i,i={},q=2\x1b\\", icon.id));
+            }
         }
 
-        // Generate icon image
-        let icon = self.generate_icon(file_type, is_dir);
-        
-        // Encode to terminal graphics protocol
-        let sequence = self.encode_icon(&icon);
-        
-        self.cache.insert(key, sequence.clone());
-        sequence
+        for (index, slot) in slots.iter().enumerate() {
+            out.push_str(&format!("\x1b[{};{}H", slot.y + 1, slot.x + 1));
+            let icon = self.icon(&slot.file_type, slot.is_dir, slot.is_image);
+            if !kitty {
+                out.push_str(&icon.sequence);
+                continue;
+            }
+
+            if !icon.transmitted {
+                out.push_str(&icon.sequence);
+                icon.transmitted = true;
+            }
+            // One placement per slot; C=1 leaves the cursor alone
+            out.push_str(&format!(
+                "\x1b_Ga=p,i={},p={},c={},r={},C=1,q=2\x1b\\",
+                icon.id, index + 1, ICON_CELLS.0, ICON_CELLS.1
+            ));
+        }
+
+        out.push_str("\x1b8");
+        self.backend.passthrough(out)
+    }
+
+    /// Delete the Kitty data of every icon not drawn in `keep`
+    ///
+    /// The terminal holds each transmitted image until told to free it; an
+    /// icon released here is sent again the next time a slot needs it.
+    pub fn release_unused(&mut self, keep: &[IconSlot]) -> String {
+        if self.backend.protocol != GraphicsProtocol::Kitty {
+            return String::new();
+        }
+
+        let kept: HashSet<String> = keep.iter()
+            .map(|slot| Self::key(&slot.file_type, slot.is_dir, slot.is_image))
+            .collect();
+        let mut out = String::new();
+        for (key, icon) in &mut self.cache {
+            if icon.transmitted && !kept.contains(key) {
+                // Uppercase d=I frees the data along with any placement
+                out.push_str(&format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", icon.id));
+                icon.transmitted = false;
+            }
+        }
+        if out.is_empty() {
+            return out;
+        }
+        self.backend.passthrough(out)
+    }
+
+    /// Cache key of an icon; extensions can't contain a dot, so the
+    /// folder and image keys never clash with one
+    fn key(file_type: &str, is_dir: bool, is_image: bool) -> String {
+        match (is_dir, is_image) {
+            (true, _) => ".folder",
+            (false, true) => ".image",
+            (false, false) => file_type,
+        }.to_string()
+    }
+
+    /// The cached icon for a type, generating it on first use
+    fn icon(&mut self, file_type: &str, is_dir: bool, is_image: bool) -> &mut CachedIcon {
+        let key = Self::key(file_type, is_dir, is_image);
+        if !self.cache.contains_key(&key) {
+            let id = ICON_ID_BASE + self.cache.len() as u32;
+            let sequence = self.encode_icon(&self.generate_icon(file_type, is_dir, is_image), id);
+            self.cache.insert(key.clone(), CachedIcon { id, sequence, transmitted: false });
+        }
+        self.cache.get_mut(&key).expect("icon was just inserted")
     }
 
     /// Generate a simple colored icon image
-    fn generate_icon(&self, file_type: &str, is_dir: bool) -> RgbaImage {
+    fn generate_icon(&self, file_type: &str, is_dir: bool, is_image: bool) -> RgbaImage {
         let mut img = RgbaImage::new(ICON_SIZE, ICON_SIZE);
         
         // Choose color based on file type
         let color = if is_dir {
             Rgba([100, 180, 255, 255]) // Blue for folders
+        } else if is_image {
+            Rgba([255, 100, 150, 255]) // Pink for images
         } else {
             match file_type {
                 "rs" => Rgba([255, 100, 50, 255]),   // Orange for Rust
@@ -62,7 +167,6 @@ impl IconManager {
                 "js" | "ts" => Rgba([255, 220, 50, 255]), // Yellow for JS
                 "md" => Rgba([100, 200, 100, 255]), // Green for markdown
                 "toml" | "json" | "yaml" => Rgba([200, 150, 255, 255]), // Purple for config
-                "png" | "jpg" | "gif" => Rgba([255, 100, 150, 255]), // Pink for images
                 _ => Rgba([180, 180, 180, 255]),    // Gray for unknown
             }
         };
@@ -85,69 +189,100 @@ impl IconManager {
     }
 
     /// Encode icon to terminal graphics escape sequence
-    fn encode_icon(&self, img: &RgbaImage) -> String {
+    fn encode_icon(&self, img: &RgbaImage, id: u32) -> String {
         match self.backend.protocol {
-            GraphicsProtocol::Kitty => self.encode_kitty(img),
-            GraphicsProtocol::ITerm2 => self.encode_iterm2(img),
+            GraphicsProtocol::Kitty => Self::encode_kitty(img, id),
+            GraphicsProtocol::ITerm2 => Self::encode_iterm2(img),
             _ => String::new(),
         }
     }
 
-    /// Encode image using Kitty graphics protocol
-    fn encode_kitty(&self, img: &RgbaImage) -> String {
-        use base64::{Engine, engine::general_purpose::STANDARD};
-        
-        // Encode PNG
-        let mut png_data = Vec::new();
-        {
-            use std::io::Cursor;
-            let mut cursor = Cursor::new(&mut png_data);
-            img.write_to(&mut cursor, image::ImageFormat::Png).ok();
-        }
-        
-        let encoded = STANDARD.encode(&png_data);
-        
-        // Kitty inline image: f=100 (PNG), a=T (transmit+display), c=2 (2 columns), r=1 (1 row)
-        // Use virtual placement for inline rendering
-        if encoded.len() <= 4096 {
-            format!("\x1b_Gf=100,a=T,t=d,c=2,r=1;{}\x1b\\", encoded)
-        } else {
-            // Chunked transmission for larger images
-            let mut result = String::new();
-            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
-            
-            for (i, chunk) in chunks.iter().enumerate() {
-                let is_last = i == chunks.len() - 1;
-                let m = if is_last { 0 } else { 1 };
-                let chunk_str = std::str::from_utf8(chunk).unwrap_or("");
-                
-                if i == 0 {
-                    result.push_str(&format!("\x1b_Gf=100,a=T,t=d,c=2,r=1,m={};{}\x1b\\", m, chunk_str));
-                } else {
-                    result.push_str(&format!("\x1b_Gm={};{}\x1b\\", m, chunk_str));
-                }
-            }
-            result
-        }
+    /// Transmit (without displaying) using Kitty graphics protocol
+    fn encode_kitty(img: &RgbaImage, id: u32) -> String {
+        // a=t (transmit only), f=100 (PNG), i=image id, q=2 (no replies);
+        // a 16x16 PNG always fits in one chunk
+        format!("\x1b_Ga=t,f=100,t=d,i={},q=2;{}\x1b\\", id, Self::encode_png(img))
     }
 
     /// Encode image using iTerm2 protocol
-    fn encode_iterm2(&self, img: &RgbaImage) -> String {
+    fn encode_iterm2(img: &RgbaImage) -> String {
+        format!(
+            "\x1b]1337;File=inline=1;width={};height={}:{}\x07",
+            ICON_CELLS.0, ICON_CELLS.1, Self::encode_png(img)
+        )
+    }
+
+    /// Base64-encoded PNG of the icon
+    fn encode_png(img: &RgbaImage) -> String {
         use base64::{Engine, engine::general_purpose::STANDARD};
-        
+
         let mut png_data = Vec::new();
-        {
-            use std::io::Cursor;
-            let mut cursor = Cursor::new(&mut png_data);
-            img.write_to(&mut cursor, image::ImageFormat::Png).ok();
-        }
-        
-        let encoded = STANDARD.encode(&png_data);
-        format!("\x1b]1337;File=inline=1;width=2;height=1:{}\x07", encoded)
+        img.write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png).ok();
+        STANDARD.encode(&png_data)
     }
 }
 
-/// Get file extension from filename
-pub fn get_extension(filename: &str) -> &str {
-    filename.rsplit('.').next().unwrap_or("")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(protocol: GraphicsProtocol) -> IconManager {
+        IconManager::new(GraphicsBackend {
+            protocol,
+            cell_size: (10, 20),
+            tmux_passthrough: false,
+            iterm2_multipart: false,
+        })
+    }
+
+    fn slot(x: u16, file_type: &str) -> IconSlot {
+        IconSlot { x, y: 3, file_type: file_type.to_string(), is_dir: false, is_image: false, highlighted: false }
+    }
+
+    #[test]
+    fn test_kitty_transmits_each_icon_once() {
+        let mut icons = manager(GraphicsProtocol::Kitty);
+        let first = icons.render_slots(&[slot(0, "rs"), slot(10, "rs")]);
+        let id = ICON_ID_BASE;
+        assert_eq!(first.matches(&format!("a=t,f=100,t=d,i={},", id)).count(), 1);
+        assert!(first.starts_with("\x1b7\x1b[4;1H\x1b_Ga=t,"));
+        assert!(first.contains(&format!("\x1b_Ga=p,i={},p=1,", id)));
+        assert!(first.contains(&format!("\x1b[4;11H\x1b_Ga=p,i={},p=2,", id)));
+        assert!(first.ends_with("\x1b8"));
+
+        // Later frames remove the old placements first, then place by id alone
+        let second = icons.render_slots(&[slot(5, "rs")]);
+        assert!(!second.contains("a=t"));
+        let delete = second.find(&format!("a=d,d=i,i={},", id)).unwrap();
+        let place = second.find(&format!("a=p,i={},p=1,", id)).unwrap();
+        assert!(delete < place);
+    }
+
+    #[test]
+    fn test_release_frees_icons_off_screen() {
+        let mut icons = manager(GraphicsProtocol::Kitty);
+        icons.render_slots(&[slot(0, "rs"), slot(10, "md")]);
+        let shown = [slot(0, "rs")];
+        icons.render_slots(&shown);
+
+        let released = icons.release_unused(&shown);
+        assert_eq!(released.matches("a=d,d=I,").count(), 1);
+        assert!(released.contains(&format!("i={},", ICON_ID_BASE + 1)));
+        assert!(icons.release_unused(&shown).is_empty());
+
+        // The released icon is transmitted again when next drawn
+        let again = icons.render_slots(&[slot(0, "md")]);
+        assert!(again.contains(&format!("a=t,f=100,t=d,i={},", ICON_ID_BASE + 1)));
+    }
+
+    #[test]
+    fn test_iterm2_draws_inline_in_every_slot() {
+        let mut icons = manager(GraphicsProtocol::ITerm2);
+        for _ in 0..2 {
+            let out = icons.render_slots(&[slot(0, "rs"), slot(10, "md")]);
+            assert_eq!(out.matches("\x1b]1337;File=inline=1;width=2;height=1:").count(), 2);
+            assert!(out.contains("\x1b[4;11H\x1b]1337;File="));
+            assert!(!out.contains("\x1b_G"));
+        }
+    }
 }
@@ -0,0 +1,91 @@
+//! iTerm2 inline images
+//!
+//! Reference: https://iterm2.com/documentation-images.html
+//!
+//! The classic form sends the whole file in one `OSC 1337 ; File=` sequence,
+//! which iTerm2 struggles with for large images and tmux cuts short. Since
+//! 3.5 the file can go in parts instead: `MultipartFile=` with the arguments,
+//! a `FilePart=` per chunk of base64, then `FileEnd`.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// Base64 bytes per `FilePart`, as with Kitty's chunks
+const CHUNK_SIZE: usize = 4096;
+
+/// First iTerm2 release that understands multipart transfers
+const MULTIPART_SINCE: (u32, u32) = (3, 5);
+
+/// Inline `image_data` with `args` (e.g. `preserveAspectRatio=1`)
+///
+/// With `multipart`, images too big for one chunk are sent in parts; small
+/// ones, and everything for older versions, go in a single sequence.
+pub fn render(args: &str, image_data: &[u8], multipart: bool) -> String {
+    let encoded = STANDARD.encode(image_data);
+    let args = match args {
+        "" => format!("inline=1;size={}", image_data.len()),
+        args => format!("inline=1;size={};{}", image_data.len(), args),
+    };
+    if !multipart || encoded.len() <= CHUNK_SIZE {
+        return format!("\x1b]1337;File={}:{}\x07", args, encoded);
+    }
+
+    let mut result = format!("\x1b]1337;MultipartFile={}\x07", args);
+    for chunk in encoded.as_bytes().chunks(CHUNK_SIZE) {
+        // Base64 is ASCII, so any byte boundary is a char boundary
+        let chunk = std::str::from_utf8(chunk).unwrap_or("");
+        result.push_str(&format!("\x1b]1337;FilePart={}\x07", chunk));
+    }
+    result.push_str("\x1b]1337;FileEnd\x07");
+    result
+}
+
+/// Whether iTerm2 `version` (e.g. `3.5.0beta2`) takes multipart transfers
+pub fn supports_multipart(version: &str) -> bool {
+    let mut parts = version.split('.').map(|part| {
+        let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u32>().ok()
+    });
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor) >= MULTIPART_SINCE,
+        (Some(major), None) => major > MULTIPART_SINCE.0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_images_go_in_parts() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let encoded_len = STANDARD.encode(&data).len();
+
+        let sequence = render("preserveAspectRatio=1", &data, true);
+        let units: Vec<&str> = sequence.split_terminator('\x07').collect();
+        assert_eq!(units[0], "\x1b]1337;MultipartFile=inline=1;size=20000;preserveAspectRatio=1");
+        assert_eq!(*units.last().unwrap(), "\x1b]1337;FileEnd");
+        let parts = &units[1..units.len() - 1];
+        assert_eq!(parts.len(), encoded_len.div_ceil(CHUNK_SIZE));
+        assert!(parts.iter().all(|part| part.starts_with("\x1b]1337;FilePart=")));
+        let payload: String = parts.iter().map(|part| &part["\x1b]1337;FilePart=".len()..]).collect();
+        assert_eq!(STANDARD.decode(payload).unwrap(), data);
+
+        // tmux gets each part wrapped on its own
+        let wrapped = crate::graphics::tmux::wrap(&sequence);
+        assert_eq!(wrapped.matches("\x1bPtmux;").count(), units.len());
+
+        // Small images, and older versions, keep the single sequence
+        assert!(render("", &data[..100], true).starts_with("\x1b]1337;File=inline=1;size=100:"));
+        assert_eq!(render("", &data, false).matches('\x07').count(), 1);
+    }
+
+    #[test]
+    fn test_supports_multipart() {
+        assert!(supports_multipart("3.5.0"));
+        assert!(supports_multipart("3.5.0beta2"));
+        assert!(supports_multipart("4"));
+        assert!(!supports_multipart("3.4.23"));
+        assert!(!supports_multipart(""));
+    }
+}
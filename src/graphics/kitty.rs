@@ -53,12 +53,18 @@ pub fn render(x: u16, y: u16, _width: u16, _height: u16, image_data: &[u8]) -> S
     }
 }
 
-/// Clear an image at the specified position
-pub fn clear(_x: u16, _y: u16) -> String {
-    // a=d means delete, d=a means delete all images
+/// Delete every image placed on screen
+pub fn clear() -> String {
+    // a=d means delete, d=a means all placements (lowercase keeps the data,
+    // so images sent with an id can be shown again with a=p)
     "\x1b_Ga=d,d=a\x1b\\".to_string()
 }
 
+/// Delete every image and free its data, for when the app exits
+pub fn delete_all() -> String {
+    "\x1b_Ga=d,d=A\x1b\\".to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
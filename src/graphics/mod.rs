@@ -4,7 +4,11 @@
 
 mod protocol;
 mod kitty;
+mod iterm2;
 mod sixel;
+mod fallback;
+mod tmux;
+mod disk_cache;
 pub mod icons;
 pub mod thumbnails;
 
@@ -24,48 +28,94 @@ pub enum GraphicsProtocol {
     Fallback,
 }
 
+/// Cell size assumed when the terminal won't report one
+const DEFAULT_CELL_SIZE: (u16, u16) = (12, 24);
+
 /// Graphics backend for rendering images in terminal
 #[derive(Clone)]
 pub struct GraphicsBackend {
     pub protocol: GraphicsProtocol,
+    /// Size of one terminal cell in pixels (width, height)
+    pub cell_size: (u16, u16),
+    /// Wrap graphics sequences for tmux; on when `TMUX` is set, unless the config turns it off
+    pub tmux_passthrough: bool,
+    /// iTerm2 is new enough to take large images in parts
+    pub iterm2_multipart: bool,
 }
 
 impl GraphicsBackend {
     /// Detect the best available graphics protocol
     pub fn detect() -> Self {
-        let protocol = Self::detect_protocol();
-        eprintln!("[GraphTerm] Detected graphics protocol: {:?}", protocol);
-        Self { protocol }
+        let (protocol, signal) = Self::detect_protocol();
+        let cell_size = query_cell_size().unwrap_or(DEFAULT_CELL_SIZE);
+        log::info!("Detected graphics protocol: {:?} ({})", protocol, signal);
+        let iterm2_multipart = protocol == GraphicsProtocol::ITerm2 && Self::detect_iterm2_multipart();
+        Self { protocol, cell_size, tmux_passthrough: tmux::detected(), iterm2_multipart }
+    }
+
+    /// Number of cells (columns, rows) needed to show `width` x `height` pixels
+    pub fn cells_for(&self, width: u32, height: u32) -> (u16, u16) {
+        let (cell_w, cell_h) = (self.cell_size.0.max(1) as u32, self.cell_size.1.max(1) as u32);
+        (width.div_ceil(cell_w) as u16, height.div_ceil(cell_h) as u16)
     }
 
-    fn detect_protocol() -> GraphicsProtocol {
-        // Check for Kitty
-        if std::env::var("KITTY_WINDOW_ID").is_ok() {
-            return GraphicsProtocol::Kitty;
+    /// Pick a protocol, along with the signal that selected it
+    ///
+    /// Kitty-capable signals are checked first, so they win when a terminal
+    /// sets several (e.g. WezTerm inside a Konsole session).
+    fn detect_protocol() -> (GraphicsProtocol, &'static str) {
+        let var = |name: &str| std::env::var(name).ok();
+        let term = var("TERM").unwrap_or_default();
+        let term_program = var("TERM_PROGRAM").unwrap_or_default();
+
+        // Kitty protocol: Kitty itself, Ghostty and WezTerm
+        if var("KITTY_WINDOW_ID").is_some() {
+            return (GraphicsProtocol::Kitty, "KITTY_WINDOW_ID");
+        }
+        if term.contains("kitty") {
+            return (GraphicsProtocol::Kitty, "TERM contains kitty");
+        }
+        if term_program.eq_ignore_ascii_case("ghostty") {
+            return (GraphicsProtocol::Kitty, "TERM_PROGRAM=ghostty");
+        }
+        if var("GHOSTTY_RESOURCES_DIR").is_some() {
+            return (GraphicsProtocol::Kitty, "GHOSTTY_RESOURCES_DIR");
+        }
+        if var("WEZTERM_PANE").is_some() {
+            return (GraphicsProtocol::Kitty, "WEZTERM_PANE");
         }
 
-        // Check TERM variable for kitty
-        if let Ok(term) = std::env::var("TERM") {
-            if term.contains("kitty") {
-                return GraphicsProtocol::Kitty;
-            }
+        // iTerm2 inline images; LC_TERMINAL is the one ssh passes along
+        if term_program == "iTerm.app" {
+            return (GraphicsProtocol::ITerm2, "TERM_PROGRAM=iTerm.app");
+        }
+        if var("LC_TERMINAL").as_deref() == Some("iTerm2") {
+            return (GraphicsProtocol::ITerm2, "LC_TERMINAL=iTerm2");
         }
 
-        // Check for iTerm2
-        if let Ok(term_program) = std::env::var("TERM_PROGRAM") {
-            if term_program == "iTerm.app" {
-                return GraphicsProtocol::ITerm2;
-            }
+        // Konsole supports Sixel
+        if var("KONSOLE_VERSION").is_some() {
+            return (GraphicsProtocol::Sixel, "KONSOLE_VERSION");
         }
 
-        // Check for WezTerm (supports Kitty protocol)
-        if std::env::var("WEZTERM_PANE").is_ok() {
-            return GraphicsProtocol::Kitty;
+        // Ask the terminal directly (xterm, mlterm, foot, ...)
+        if check_sixel_support() {
+            return (GraphicsProtocol::Sixel, "DA1 reply");
         }
 
-        // TODO: Query terminal for Sixel support via escape sequences
-        // For now, fallback
-        GraphicsProtocol::Fallback
+        (GraphicsProtocol::Fallback, "no graphics signal")
+    }
+
+    /// Whether the iTerm2 version, locally or as forwarded by ssh, takes multipart images
+    fn detect_iterm2_multipart() -> bool {
+        let version = if std::env::var("TERM_PROGRAM").is_ok_and(|program| program == "iTerm.app") {
+            std::env::var("TERM_PROGRAM_VERSION")
+        } else {
+            std::env::var("LC_TERMINAL_VERSION")
+        };
+        let multipart = version.as_deref().is_ok_and(iterm2::supports_multipart);
+        log::debug!("iTerm2 version {:?}, multipart: {}", version.ok(), multipart);
+        multipart
     }
 
     /// Render an image at the specified position
@@ -75,24 +125,63 @@ impl GraphicsBackend {
     /// * `y` - Row position (0-indexed)  
     /// * `width` - Width in terminal cells
     /// * `height` - Height in terminal cells
-    /// * `image_data` - Raw RGBA image data
+    /// * `image_data` - Encoded image data (PNG)
     pub fn render_image(&self, x: u16, y: u16, width: u16, height: u16, image_data: &[u8]) -> String {
         match self.protocol {
             GraphicsProtocol::Kitty => kitty::render(x, y, width, height, image_data),
-            GraphicsProtocol::Sixel => sixel::render(x, y, width, height, image_data),
+            GraphicsProtocol::Sixel => sixel::render(x, y, width, height, self.cell_size, image_data),
             GraphicsProtocol::ITerm2 => self.render_iterm2(x, y, width, height, image_data),
-            GraphicsProtocol::Fallback => String::new(), // Use Unicode in UI layer
+            GraphicsProtocol::Fallback => fallback::render(width, height, image_data),
         }
     }
 
     fn render_iterm2(&self, _x: u16, _y: u16, _width: u16, _height: u16, image_data: &[u8]) -> String {
-        // iTerm2 protocol: OSC 1337 ; File=inline=1;size=<bytes>:<base64 data> ST
-        let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, image_data);
-        format!("\x1b]1337;File=inline=1;size={}:{}\x07", image_data.len(), encoded)
+        iterm2::render("", image_data, self.iterm2_multipart)
+    }
+
+    /// Escape sequence removing previously drawn images, if the protocol needs one
+    ///
+    /// Kitty keeps images on their own layer until told otherwise; the other
+    /// protocols paint into cells that the next redraw overwrites.
+    pub fn clear_images(&self) -> String {
+        match self.protocol {
+            GraphicsProtocol::Kitty => self.passthrough(kitty::clear()),
+            _ => String::new(),
+        }
+    }
+
+    /// Sequence removing everything sent to the terminal, to run on exit
+    ///
+    /// Kitty keeps transmitted images after the alternate screen is left,
+    /// where they can resurface in the scrollback.
+    pub fn release_images(&self) -> String {
+        match self.protocol {
+            GraphicsProtocol::Kitty => self.passthrough(kitty::delete_all()),
+            _ => String::new(),
+        }
+    }
+
+    /// Prepare finished output for the terminal, wrapping it for tmux when enabled
+    ///
+    /// Apply once, to complete sequences; wrapping twice breaks them.
+    pub fn passthrough(&self, sequence: String) -> String {
+        if self.tmux_passthrough {
+            tmux::wrap(&sequence)
+        } else {
+            sequence
+        }
     }
 
     /// Check if real graphics are supported
     pub fn supports_images(&self) -> bool {
         self.protocol != GraphicsProtocol::Fallback
     }
+
+    /// Whether images become part of the cells they cover
+    ///
+    /// Only Kitty keeps images on a layer of their own that `clear_images` can
+    /// remove; the others stay until those cells are drawn over.
+    pub fn draws_into_cells(&self) -> bool {
+        self.protocol != GraphicsProtocol::Kitty
+    }
 }
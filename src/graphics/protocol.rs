@@ -1,24 +1,167 @@
 //! Protocol detection utilities
 
 use std::io::{self, Write};
+use std::time::Duration;
+
+/// How long to wait for the terminal to answer a query
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
 
 /// Query terminal for graphics capabilities
+///
+/// Sends Primary Device Attributes (DA1) and returns the raw reply, e.g.
+/// `\x1b[?62;4;6c`. Needs raw mode so the reply isn't line-buffered; returns
+/// `None` if stdin isn't a terminal or nothing arrives within the timeout.
 pub fn query_terminal_capabilities() -> Option<String> {
-    // Send Device Attributes query
+    if !stdin_is_tty() {
+        return None;
+    }
+
     let mut stdout = io::stdout();
-    
-    // Primary Device Attributes (DA1)
-    let _ = stdout.write_all(b"\x1b[c");
-    let _ = stdout.flush();
-    
-    // In a real implementation, we'd read the response
-    // This requires async/non-blocking I/O
-    None
+    stdout.write_all(b"\x1b[c").ok()?;
+    stdout.flush().ok()?;
+
+    read_reply(b'c', QUERY_TIMEOUT)
 }
 
 /// Check if terminal supports Sixel by querying DA1
 pub fn check_sixel_support() -> bool {
     // Sixel support is indicated by "4" in the DA1 response
-    // For now, we rely on environment variable detection
+    query_terminal_capabilities()
+        .is_some_and(|reply| parse_da1_attributes(&reply).contains(&"4"))
+}
+
+/// Query the size of one terminal cell in pixels
+///
+/// Tries the `TIOCGWINSZ` pixel fields first, then asks the terminal with
+/// `CSI 16 t` (reply `ESC [ 6 ; height ; width t`). Returns `(width, height)`.
+pub fn query_cell_size() -> Option<(u16, u16)> {
+    if let Some(size) = cell_size_from_winsize() {
+        return Some(size);
+    }
+    if !stdin_is_tty() {
+        return None;
+    }
+
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[16t").ok()?;
+    stdout.flush().ok()?;
+
+    let reply = read_reply(b't', QUERY_TIMEOUT)?;
+    parse_cell_size_reply(&reply)
+}
+
+/// Parse `ESC [ 6 ; height ; width t` into `(width, height)`
+fn parse_cell_size_reply(reply: &str) -> Option<(u16, u16)> {
+    let start = reply.find("\x1b[6;")?;
+    let body = reply[start + 4..].split('t').next()?;
+    let (height, width) = body.split_once(';')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Cell size from the window size ioctl, when the terminal fills in pixels
+#[cfg(unix)]
+fn cell_size_from_winsize() -> Option<(u16, u16)> {
+    let mut ws = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ writes a single winsize into `ws`
+    let ret = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    if ret < 0 || ws.ws_col == 0 || ws.ws_row == 0 || ws.ws_xpixel == 0 || ws.ws_ypixel == 0 {
+        return None;
+    }
+    let (width, height) = (ws.ws_xpixel / ws.ws_col, ws.ws_ypixel / ws.ws_row);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+#[cfg(not(unix))]
+fn cell_size_from_winsize() -> Option<(u16, u16)> {
+    None
+}
+
+/// Split a DA1 reply (`ESC [ ? 62 ; 4 ; 6 c`) into its attribute codes
+fn parse_da1_attributes(reply: &str) -> Vec<&str> {
+    let Some(start) = reply.find("\x1b[?") else {
+        return Vec::new();
+    };
+    let body = &reply[start + 3..];
+    let body = body.split('c').next().unwrap_or("");
+    body.split(';').filter(|attr| !attr.is_empty()).collect()
+}
+
+#[cfg(unix)]
+fn stdin_is_tty() -> bool {
+    // SAFETY: isatty only inspects the descriptor
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdin_is_tty() -> bool {
     false
 }
+
+/// Read stdin until `terminator` arrives or `timeout` elapses
+///
+/// Reads one byte at a time so nothing past the reply is consumed.
+#[cfg(unix)]
+fn read_reply(terminator: u8, timeout: Duration) -> Option<String> {
+    let deadline = std::time::Instant::now() + timeout;
+    let mut reply = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fds` is a single valid pollfd for the duration of the call
+        let ready = unsafe { libc::poll(&mut fds, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return None;
+        }
+
+        let mut byte = 0u8;
+        // SAFETY: reads at most one byte into `byte`
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+        if n != 1 {
+            return None;
+        }
+
+        reply.push(byte);
+        if byte == terminator && reply.contains(&0x1b) {
+            return String::from_utf8(reply).ok();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn read_reply(_terminator: u8, _timeout: Duration) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_da1_attributes() {
+        assert_eq!(parse_da1_attributes("\x1b[?62;4;6;22c"), vec!["62", "4", "6", "22"]);
+        assert!(!parse_da1_attributes("\x1b[?1;2c").contains(&"4"));
+        assert!(parse_da1_attributes("garbage").is_empty());
+    }
+
+    #[test]
+    fn test_parse_cell_size_reply() {
+        assert_eq!(parse_cell_size_reply("\x1b[6;24;12t"), Some((12, 24)));
+        assert_eq!(parse_cell_size_reply("\x1b[6;0;0t"), None);
+        assert_eq!(parse_cell_size_reply("\x1b[?62c"), None);
+    }
+}
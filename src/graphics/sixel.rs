@@ -1,23 +1,234 @@
 //! Sixel Graphics implementation
-//! 
+//!
 //! Sixel is a bitmap graphics format for terminals, originally from DEC VT terminals.
 //! Reference: https://www.vt100.net/docs/vt3xx-gp/chapter14.html
 
+use image::imageops::FilterType;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Maximum palette size supported by most Sixel terminals
+const MAX_COLORS: usize = 256;
+
+/// Pixels with alpha below this are left transparent
+const ALPHA_THRESHOLD: u8 = 128;
+
 /// Render image using Sixel protocol
-/// 
-/// For Phase 1, this is a stub. Phase 2 will implement full Sixel encoding.
-pub fn render(_x: u16, _y: u16, _width: u16, _height: u16, _image_data: &[u8]) -> String {
-    // Sixel format:
-    // DCS P1;P2;P3 q <sixel data> ST
-    // Where DCS = ESC P, ST = ESC \
-    
-    // TODO: Implement Sixel encoding in Phase 2
-    // This requires quantizing colors to 256 palette and encoding as sixel bands
-    String::new()
+///
+/// `image_data` is an encoded image (PNG, JPEG, ...), scaled to fit within
+/// `width` x `height` cells of `cell_size` pixels. Sixel draws at the cursor, so like the Kitty
+/// path the caller is responsible for positioning.
+pub fn render(_x: u16, _y: u16, width: u16, height: u16, cell_size: (u16, u16), image_data: &[u8]) -> String {
+    let Ok(img) = image::load_from_memory(image_data) else {
+        return String::new();
+    };
+
+    let max_w = (width as u32 * cell_size.0 as u32).max(1);
+    let max_h = (height as u32 * cell_size.1 as u32).max(1);
+    let rgba = if img.width() > max_w || img.height() > max_h {
+        img.resize(max_w, max_h, FilterType::Triangle).to_rgba8()
+    } else {
+        img.to_rgba8()
+    };
+
+    let (w, h) = (rgba.width() as usize, rgba.height() as usize);
+    if w == 0 || h == 0 {
+        return String::new();
+    }
+
+    let (indices, palette) = quantize_to_palette(rgba.as_raw());
+    encode(&indices, rgba.as_raw(), &palette, w, h)
+}
+
+/// Encode quantized pixels as a Sixel DCS sequence
+///
+/// DCS P1;P2;P3 q <raster attributes> <palette> <bands> ST, where P2=1 keeps
+/// unpainted (transparent) pixels at the terminal background.
+fn encode(indices: &[u8], rgba: &[u8], palette: &[[u8; 3]], w: usize, h: usize) -> String {
+    let mut out = String::new();
+    let _ = write!(out, "\x1bP0;1;0q\"1;1;{};{}", w, h);
+
+    // Color introducers use RGB percentages
+    for (i, [r, g, b]) in palette.iter().enumerate() {
+        let pct = |c: u8| (c as u32 * 100 + 127) / 255;
+        let _ = write!(out, "#{};2;{};{};{}", i, pct(*r), pct(*g), pct(*b));
+    }
+
+    let opaque = |px: usize| rgba[px * 4 + 3] >= ALPHA_THRESHOLD;
+
+    // Each band covers six pixel rows
+    for band_top in (0..h).step_by(6) {
+        let band_rows = (h - band_top).min(6);
+
+        // Sixel bit masks per color, one per column
+        let mut masks: Vec<(u8, Vec<u8>)> = Vec::new();
+        let mut slot_of: HashMap<u8, usize> = HashMap::new();
+        for row in 0..band_rows {
+            for col in 0..w {
+                let px = (band_top + row) * w + col;
+                if !opaque(px) {
+                    continue;
+                }
+                let color = indices[px];
+                let slot = *slot_of.entry(color).or_insert_with(|| {
+                    masks.push((color, vec![0; w]));
+                    masks.len() - 1
+                });
+                masks[slot].1[col] |= 1 << row;
+            }
+        }
+
+        for (i, (color, bits)) in masks.iter().enumerate() {
+            if i > 0 {
+                out.push('$'); // Carriage return: overdraw the same band
+            }
+            let _ = write!(out, "#{}", color);
+            push_run_length(&mut out, bits);
+        }
+        out.push('-'); // Next band
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Append sixel characters, collapsing runs with the `!<count><char>` repeat
+fn push_run_length(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let run = bits[i..].iter().take_while(|&&b| b == bits[i]).count();
+        let c = (63 + bits[i]) as char;
+        if run > 3 {
+            let _ = write!(out, "!{}{}", run, c);
+        } else {
+            for _ in 0..run {
+                out.push(c);
+            }
+        }
+        i += run;
+    }
+}
+
+/// Convert RGBA pixels to a palette of at most 256 colors via median cut
+///
+/// Returns one palette index per pixel and the palette itself. Alpha is
+/// ignored here; the encoder decides which pixels are transparent.
+fn quantize_to_palette(image_data: &[u8]) -> (Vec<u8>, Vec<[u8; 3]>) {
+    let pixels: Vec<[u8; 3]> = image_data
+        .chunks_exact(4)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    if pixels.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut unique = pixels.clone();
+    unique.sort_unstable();
+    unique.dedup();
+
+    let palette: Vec<[u8; 3]> = if unique.len() <= MAX_COLORS {
+        unique
+    } else {
+        median_cut(pixels.clone(), MAX_COLORS)
+    };
+
+    // Map each distinct color to its nearest palette entry once
+    let mut nearest: HashMap<[u8; 3], u8> = HashMap::new();
+    let indices = pixels
+        .iter()
+        .map(|px| {
+            *nearest.entry(*px).or_insert_with(|| {
+                palette
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, c)| {
+                        (0..3)
+                            .map(|i| (c[i] as i32 - px[i] as i32).pow(2))
+                            .sum::<i32>()
+                    })
+                    .map(|(i, _)| i as u8)
+                    .unwrap_or(0)
+            })
+        })
+        .collect();
+
+    (indices, palette)
 }
 
-/// Convert RGB image to Sixel palette
-fn _quantize_to_palette(_image_data: &[u8]) -> (Vec<u8>, Vec<[u8; 3]>) {
-    // TODO: Implement color quantization
-    (Vec::new(), Vec::new())
+/// Split the color space into `max_colors` boxes by repeatedly halving the
+/// box with the widest channel range at its median, averaging each box
+fn median_cut(pixels: Vec<[u8; 3]>, max_colors: usize) -> Vec<[u8; 3]> {
+    let channel_range = |colors: &[[u8; 3]], ch: usize| {
+        let (min, max) = colors
+            .iter()
+            .fold((u8::MAX, u8::MIN), |(lo, hi), c| (lo.min(c[ch]), hi.max(c[ch])));
+        max.saturating_sub(min)
+    };
+    let widest = |colors: &[[u8; 3]]| {
+        (0..3)
+            .map(|ch| (channel_range(colors, ch), ch))
+            .max()
+            .unwrap_or((0, 0))
+    };
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < max_colors {
+        // Pick the splittable box with the largest spread
+        let Some((index, (_, channel))) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (i, widest(b)))
+            .filter(|(_, (range, _))| *range > 0)
+            .max_by_key(|(_, (range, _))| *range)
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|c| c[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let n = colors.len() as u64;
+            let mut sum = [0u64; 3];
+            for c in colors {
+                for ch in 0..3 {
+                    sum[ch] += c[ch] as u64;
+                }
+            }
+            [(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solid_color_encoding() {
+        let img = image::RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255]));
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let result = render(0, 0, 1, 1, (12, 24), &png);
+        assert!(result.starts_with("\x1bP0;1;0q"));
+        assert!(result.contains("#0;2;100;0;0"));
+        assert!(result.ends_with("\x1b\\"));
+    }
+
+    #[test]
+    fn test_quantize_limits_palette() {
+        let img = image::RgbaImage::from_fn(64, 64, |x, y| image::Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255]));
+        let (indices, palette) = quantize_to_palette(img.as_raw());
+        assert_eq!(indices.len(), 64 * 64);
+        assert!(palette.len() <= MAX_COLORS);
+        assert!(indices.iter().all(|&i| (i as usize) < palette.len()));
+    }
 }
@@ -1,161 +1,863 @@
 //! Thumbnail generation and caching
 
-use image::{DynamicImage, imageops::FilterType};
-use std::collections::HashMap;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader, codecs::gif::GifDecoder, imageops::FilterType, metadata::Orientation};
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use base64::{Engine, engine::general_purpose::STANDARD};
 
 use crate::graphics::{GraphicsBackend, GraphicsProtocol};
+use crate::graphics::disk_cache::DiskCache;
+use crate::graphics::iterm2;
 
-/// Maximum thumbnail size (in pixels)
-pub const THUMBNAIL_SIZE: u32 = 200;
+/// Default maximum thumbnail size (in pixels)
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 200;
 
-/// Thumbnail cache
-pub struct ThumbnailCache {
-    /// Map from file path to base64-encoded thumbnail data
-    cache: HashMap<PathBuf, String>,
+/// Smallest and largest thumbnail sizes accepted from the config or the keys
+pub const MIN_THUMBNAIL_SIZE: u32 = 16;
+pub const MAX_THUMBNAIL_SIZE: u32 = 2048;
+
+/// Thumbnails kept in memory by default
+pub const DEFAULT_CACHE_ENTRIES: usize = 500;
+
+/// Memory budget for cached escape sequences by default, in bytes
+pub const DEFAULT_CACHE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Frames decoded from an animated GIF at most
+const MAX_ANIMATION_FRAMES: usize = 300;
+
+/// Extensions treated as images, for both previews and list icons, unless
+/// the config file lists others (`image_extensions`)
+///
+/// AVIF is listed even without the `avif` feature, so such files get a
+/// "cannot preview" note rather than being treated as unknown.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "avif", "bmp", "ico"];
+
+/// Trade-off between downscaling speed and output quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeQuality {
+    /// `DynamicImage::thumbnail`, good enough for small previews
+    Fast,
+    /// Triangle-filtered `resize`, for when the image is shown large
+    High,
+}
+
+/// Decodes, resizes, and encodes thumbnails; cloned into background jobs
+#[derive(Clone)]
+struct ThumbnailRenderer {
     /// Graphics backend for protocol-specific encoding
     backend: GraphicsBackend,
+    /// How thumbnails are downscaled
+    quality: ResizeQuality,
+    /// Longest side of a thumbnail, in pixels
+    size: u32,
+    /// Resized thumbnails kept between runs
+    disk_cache: Option<DiskCache>,
+}
+
+/// Escape sequences for one thumbnail
+#[derive(Debug, Clone)]
+struct Thumbnail {
+    /// Sends the image data (and displays it)
+    transmit: String,
+    /// Re-displays already transmitted data; Kitty only, where images have ids
+    display: Option<String>,
+    /// Whether `transmit` has been handed out yet
+    transmitted: bool,
+    /// Id the data is stored under; Kitty only
+    image_id: Option<u32>,
+    /// Animation frames and their delays, cycled by us for non-Kitty protocols
+    frames: Vec<(String, Duration)>,
+    /// When the animation was first shown
+    started: Option<Instant>,
+}
+
+impl Thumbnail {
+    /// A still image, or a Kitty animation that the terminal plays itself
+    fn still(transmit: String, display: Option<String>) -> Self {
+        Self { transmit, display, transmitted: false, image_id: None, frames: Vec::new(), started: None }
+    }
+
+    /// The frame due `elapsed` into a looping animation
+    fn frame_at(&self, elapsed: Duration) -> Option<&str> {
+        let total: Duration = self.frames.iter().map(|(_, delay)| *delay).sum();
+        if total.is_zero() {
+            return self.frames.first().map(|(frame, _)| frame.as_str());
+        }
+
+        let mut offset = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+        for (frame, delay) in &self.frames {
+            if offset < *delay {
+                return Some(frame);
+            }
+            offset -= *delay;
+        }
+        self.frames.last().map(|(frame, _)| frame.as_str())
+    }
+}
+
+impl Thumbnail {
+    /// Memory held by the escape sequences
+    fn byte_size(&self) -> usize {
+        self.transmit.len()
+            + self.display.as_ref().map_or(0, String::len)
+            + self.frames.iter().map(|(frame, _)| frame.len()).sum::<usize>()
+    }
+}
+
+/// Thumbnails by path, evicting the least recently used beyond an entry or byte limit
+struct ThumbnailLru {
+    /// Each thumbnail with the tick of its last use
+    entries: HashMap<PathBuf, (Thumbnail, u64)>,
+    /// Incremented on every use
+    tick: u64,
+    /// Total `byte_size` of `entries`
+    bytes: usize,
+    max_entries: usize,
+    max_bytes: usize,
+    /// Kitty ids of transmitted thumbnails dropped since `take_dropped`,
+    /// whose data the terminal still holds
+    dropped: Vec<u32>,
+}
+
+impl ThumbnailLru {
+    fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self { entries: HashMap::new(), tick: 0, bytes: 0, max_entries, max_bytes, dropped: Vec::new() }
+    }
+
+    /// Look up a thumbnail, marking it most recently used
+    fn get_mut(&mut self, path: &Path) -> Option<&mut Thumbnail> {
+        self.tick += 1;
+        let (thumbnail, used) = self.entries.get_mut(path)?;
+        *used = self.tick;
+        Some(thumbnail)
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some((thumbnail, _)) = self.entries.remove(path) {
+            self.discard(thumbnail);
+        }
+    }
+
+    fn insert(&mut self, path: PathBuf, thumbnail: Thumbnail) {
+        self.tick += 1;
+        self.bytes += thumbnail.byte_size();
+        if let Some((old, _)) = self.entries.insert(path, (thumbnail, self.tick)) {
+            self.discard(old);
+        }
+        self.evict();
+    }
+
+    /// Account for a thumbnail leaving the cache, noting its Kitty image if it was sent
+    fn discard(&mut self, thumbnail: Thumbnail) {
+        self.bytes -= thumbnail.byte_size();
+        if let Some(image_id) = thumbnail.image_id.filter(|_| thumbnail.transmitted) {
+            self.dropped.push(image_id);
+        }
+    }
+
+    /// Drop least recently used entries until within the limits
+    ///
+    /// The newest entry always stays, even if it alone is over the byte budget.
+    fn evict(&mut self) {
+        while self.entries.len() > 1
+            && (self.entries.len() > self.max_entries || self.bytes > self.max_bytes)
+        {
+            let Some(oldest) = self.entries.iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    fn set_limits(&mut self, max_entries: usize, max_bytes: usize) {
+        self.max_entries = max_entries.max(1);
+        self.max_bytes = max_bytes;
+        self.evict();
+    }
+
+    fn clear(&mut self) {
+        for (_, (thumbnail, _)) in std::mem::take(&mut self.entries) {
+            self.discard(thumbnail);
+        }
+    }
+}
+
+/// A finished background job: the path, the size it was made at, and the
+/// thumbnail, or None if decoding failed
+type JobResult = (PathBuf, u32, Option<Thumbnail>);
+
+/// Thumbnail cache
+///
+/// Decoding runs on `spawn_blocking` workers so large images never stall the
+/// render loop; finished sequences arrive through `poll_completed`. A miss in
+/// memory is looked up in the on-disk cache by the worker before decoding.
+pub struct ThumbnailCache {
+    /// Encoded thumbnails by file path, bounded by `set_cache_limits`
+    cache: ThumbnailLru,
+    /// Settings shared with background jobs
+    renderer: ThumbnailRenderer,
+    /// Paths with a job in flight
+    pending: HashSet<PathBuf>,
+    /// Paths that failed to decode, so they aren't retried every frame
+    failed: HashSet<PathBuf>,
+    /// One image shown at its own size, zoomed with the mouse wheel
+    zoom: Option<(PathBuf, u32)>,
+    /// Next Kitty image id to hand out
+    next_image_id: u32,
+    /// Channel for finished jobs
+    sender: UnboundedSender<JobResult>,
+    receiver: UnboundedReceiver<JobResult>,
 }
 
 impl ThumbnailCache {
     pub fn new(backend: GraphicsBackend) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
         Self {
-            cache: HashMap::new(),
-            backend,
+            cache: ThumbnailLru::new(DEFAULT_CACHE_ENTRIES, DEFAULT_CACHE_BYTES),
+            renderer: ThumbnailRenderer {
+                backend,
+                quality: ResizeQuality::Fast,
+                size: DEFAULT_THUMBNAIL_SIZE,
+                disk_cache: DiskCache::new(),
+            },
+            pending: HashSet::new(),
+            failed: HashSet::new(),
+            zoom: None,
+            next_image_id: 1,
+            sender,
+            receiver,
         }
     }
 
     /// Get thumbnail for an image file
-    /// Returns the escape sequence to render the image, or None if not an image
+    ///
+    /// Only pass images (`FileEntry::is_image`); anything else fails to decode.
+    ///
+    /// Returns the cached escape sequence. On a miss this starts a background
+    /// job and returns None; `is_pending` tells a loading image apart from one
+    /// that isn't an image or can't be decoded. With Kitty the image data is
+    /// only sent the first time; later calls return a short re-display by id.
     pub fn get_thumbnail(&mut self, path: &Path) -> Option<String> {
-        // Check if it's an image file
-        if !Self::is_image_file(path) {
-            return None;
+        // Check cache
+        if let Some(cached) = self.cache.get_mut(path) {
+            if !cached.frames.is_empty() {
+                let started = *cached.started.get_or_insert_with(Instant::now);
+                return cached.frame_at(started.elapsed()).map(str::to_string);
+            }
+            if let Some(display) = cached.display.as_ref().filter(|_| cached.transmitted) {
+                return Some(display.clone());
+            }
+            cached.transmitted = true;
+            return Some(cached.transmit.clone());
         }
 
-        // Check cache
-        if let Some(cached) = self.cache.get(path) {
-            return Some(cached.clone());
+        if !self.pending.contains(path) && !self.failed.contains(path) {
+            self.spawn_job(path);
+        }
+        None
+    }
+
+    /// Delete the Kitty data of every transmitted thumbnail but `keep`'s
+    ///
+    /// A released thumbnail stays cached and is transmitted again the next
+    /// time it is shown.
+    pub fn release_transmitted(&mut self, keep: Option<&Path>) -> String {
+        let mut out = String::new();
+        for (path, (thumbnail, _)) in &mut self.cache.entries {
+            let Some(image_id) = thumbnail.image_id.filter(|_| thumbnail.transmitted) else {
+                continue;
+            };
+            if Some(path.as_path()) != keep {
+                out.push_str(&delete_image(image_id));
+                thumbnail.transmitted = false;
+            }
+        }
+        if out.is_empty() {
+            return out;
         }
+        self.renderer.backend.passthrough(out)
+    }
 
-        // Load and resize image
-        let img = image::open(path).ok()?;
-        let thumbnail = self.create_thumbnail(&img);
-        
-        // Encode for terminal
-        let sequence = self.encode_thumbnail(&thumbnail);
+    /// Delete the Kitty data of thumbnails dropped from the cache since the last call
+    ///
+    /// Evicted, forgotten, or resized thumbnails would otherwise hold
+    /// terminal memory until exit.
+    pub fn take_dropped(&mut self) -> String {
+        let out: String = self.cache.dropped.drain(..).map(delete_image).collect();
+        if out.is_empty() {
+            return out;
+        }
+        self.renderer.backend.passthrough(out)
+    }
+
+    /// Whether a thumbnail for `path` is ready to show
+    pub fn is_cached(&self, path: &Path) -> bool {
+        self.cache.contains(path)
+    }
+
+    /// Whether `path` was tried and could not be decoded
+    pub fn has_failed(&self, path: &Path) -> bool {
+        self.failed.contains(path)
+    }
+
+    /// Whether a thumbnail for `path` is still being generated
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.pending.contains(path)
+    }
+
+    /// Move finished background jobs into the cache
+    pub fn poll_completed(&mut self) {
+        while let Ok((path, size, thumbnail)) = self.receiver.try_recv() {
+            self.pending.remove(&path);
+            // Made before a zoom changed; the next request makes it again
+            if size != self.size_for(&path) {
+                continue;
+            }
+            match thumbnail {
+                Some(thumbnail) => {
+                    self.cache.insert(path, thumbnail);
+                }
+                None => {
+                    log::debug!("No thumbnail for {}", path.display());
+                    self.failed.insert(path);
+                }
+            }
+        }
+    }
+
+    /// Decode and encode `path` on a blocking worker thread
+    fn spawn_job(&mut self, path: &Path) {
+        self.pending.insert(path.to_path_buf());
         
-        // Cache it
-        self.cache.insert(path.to_path_buf(), sequence.clone());
+        let image_id = self.allocate_image_id();
+        let mut renderer = self.renderer.clone();
+        let size = self.size_for(path);
+        if size != renderer.size {
+            // Zoomed thumbnails are passing views, not worth keeping on disk
+            renderer.size = size;
+            renderer.disk_cache = None;
+        }
+        let path = path.to_path_buf();
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            // A decoder panic on a malformed file just means no thumbnail
+            let thumbnail = std::panic::catch_unwind(AssertUnwindSafe(|| renderer.render(&path, image_id)))
+                .unwrap_or(None);
+            let _ = sender.send((path, size, thumbnail));
+        });
+    }
+
+    /// Hand out a Kitty image id; 0 means "no id" to the terminal, so skip it
+    fn allocate_image_id(&mut self) -> u32 {
+        let id = self.next_image_id;
+        self.next_image_id = self.next_image_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    /// Decode an image and apply its EXIF orientation
+    ///
+    /// `image::open` ignores the orientation tag, which leaves phone photos
+    /// sideways. Images without the tag are returned unchanged.
+    fn open_oriented(path: &Path) -> Option<DynamicImage> {
+        let mut decoder = ImageReader::open(path).ok()?
+            .with_guessed_format().ok()?
+            .into_decoder().ok()?;
+        let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
         
-        Some(sequence)
+        let mut img = DynamicImage::from_decoder(decoder).ok()?;
+        img.apply_orientation(orientation);
+        Some(img)
     }
 
-    /// Check if file is a supported image format
-    fn is_image_file(path: &Path) -> bool {
-        let ext = path.extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e.to_lowercase())
-            .unwrap_or_default();
+    /// Get image dimensions as string
+    ///
+    /// Only reads the header, but still opens the file; the UI keeps the result.
+    pub fn get_image_info(path: &Path) -> Option<String> {
+        let mut decoder = ImageReader::open(path).ok()?
+            .with_guessed_format().ok()?
+            .into_decoder().ok()?;
+        let (w, h) = decoder.dimensions();
         
-        matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "ico")
+        // Report the upright size, as displayed
+        let rotated = matches!(
+            decoder.orientation().unwrap_or(Orientation::NoTransforms),
+            Orientation::Rotate90 | Orientation::Rotate270
+                | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH
+        );
+        let (w, h) = if rotated { (h, w) } else { (w, h) };
+        Some(format!("{}×{} px", w, h))
+    }
+
+    /// Change how thumbnails are downscaled, dropping ones made the old way
+    pub fn set_quality(&mut self, quality: ResizeQuality) {
+        if self.renderer.quality != quality {
+            self.renderer.quality = quality;
+            self.clear();
+        }
+    }
+
+    /// Longest thumbnail side, in pixels
+    pub fn size(&self) -> u32 {
+        self.renderer.size
+    }
+
+    /// Longest side `path` is made at: its zoomed size, if it has one
+    fn size_for(&self, path: &Path) -> u32 {
+        match &self.zoom {
+            Some((zoomed, size)) if zoomed == path => *size,
+            _ => self.renderer.size,
+        }
+    }
+
+    /// Show one image at `size` instead of the usual size, or none with `None`
+    ///
+    /// The images affected are regenerated on their next request.
+    pub fn set_zoom(&mut self, zoom: Option<(&Path, u32)>) {
+        let zoom = zoom.map(|(path, size)| (path.to_path_buf(), size.max(1)));
+        if zoom == self.zoom {
+            return;
+        }
+        for (path, _) in self.zoom.iter().chain(zoom.iter()) {
+            self.cache.remove(path);
+            self.failed.remove(path);
+        }
+        self.zoom = zoom;
+    }
+
+    /// Change the longest thumbnail side, dropping thumbnails of the old size
+    pub fn set_size(&mut self, size: u32) {
+        let size = size.max(1);
+        if self.renderer.size != size {
+            self.renderer.size = size;
+            self.clear();
+        }
+    }
+
+    /// Bound the in-memory cache to `max_entries` thumbnails and `max_bytes` of sequences
+    pub fn set_cache_limits(&mut self, max_entries: usize, max_bytes: usize) {
+        self.cache.set_limits(max_entries, max_bytes);
+    }
+
+    /// Clear the cache
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.failed.clear();
+    }
+
+    /// Drop the thumbnail of a file that changed, so the next request regenerates it
+    pub fn forget(&mut self, path: &Path) {
+        self.cache.remove(path);
+        self.failed.remove(path);
+    }
+}
+
+impl ThumbnailRenderer {
+    /// Produce the escape sequences for `path`, or None if it can't be decoded
+    fn render(&self, path: &Path, image_id: u32) -> Option<Thumbnail> {
+        // Names can lie, so only decode what the content says is an image
+        let format = sniff_image_format(path)?;
+        let kitty = self.backend.protocol == GraphicsProtocol::Kitty;
+        let mut encoded = match self.render_animation(path, format, image_id) {
+            Some(animation) => animation,
+            None => {
+                let thumbnail = self.load_thumbnail(path)?;
+
+                // Encode for terminal
+                let display = kitty.then(|| self.kitty_display(&thumbnail, image_id));
+                Thumbnail::still(self.encode_thumbnail(&thumbnail, image_id), display)
+            }
+        };
+        encoded.image_id = kitty.then_some(image_id);
+        Some(encoded)
+    }
+
+    /// Encode an animated GIF, or None for anything with a single frame
+    fn render_animation(&self, path: &Path, format: ImageFormat, image_id: u32) -> Option<Thumbnail> {
+        if format != ImageFormat::Gif || !self.backend.supports_images() {
+            return None;
+        }
+
+        let frames = decode_gif_frames(path)?;
+        if frames.len() < 2 {
+            return None;
+        }
+        let frames: Vec<(DynamicImage, Duration)> = frames
+            .into_iter()
+            .map(|(frame, delay)| (self.create_thumbnail(&frame), delay))
+            .collect();
+
+        if self.backend.protocol == GraphicsProtocol::Kitty {
+            let display = self.kitty_display(&frames[0].0, image_id);
+            let animation = self.backend.passthrough(self.encode_kitty_animation(&frames, image_id));
+            return Some(Thumbnail::still(animation, Some(display)));
+        }
+
+        let encoded: Vec<(String, Duration)> = frames
+            .iter()
+            .map(|(frame, delay)| (self.encode_thumbnail(frame, image_id), *delay))
+            .collect();
+        Some(Thumbnail {
+            transmit: encoded[0].0.clone(),
+            display: None,
+            transmitted: false,
+            image_id: None,
+            frames: encoded,
+            started: None,
+        })
+    }
+
+    /// Fetch the resized image from the disk cache, or decode and resize it
+    fn load_thumbnail(&self, path: &Path) -> Option<DynamicImage> {
+        let variant = format!("{}-{:?}", self.size, self.quality);
+        if let Some(thumbnail) = self.disk_cache.as_ref().and_then(|c| c.load(path, &variant)) {
+            return Some(thumbnail);
+        }
+
+        // Load (upright) and resize image
+        let img = ThumbnailCache::open_oriented(path)?;
+        let thumbnail = self.create_thumbnail(&img);
+        if let Some(disk_cache) = &self.disk_cache {
+            // A read-only cache dir just means regenerating next time
+            let _ = disk_cache.store(path, &variant, &thumbnail);
+        }
+        Some(thumbnail)
     }
 
     /// Create a resized thumbnail
     fn create_thumbnail(&self, img: &DynamicImage) -> DynamicImage {
+        if self.quality == ResizeQuality::Fast {
+            // Aspect-preserving and much cheaper than a filtered resize
+            return img.thumbnail(self.size, self.size);
+        }
+
         // Calculate aspect-preserving dimensions
         let (w, h) = (img.width(), img.height());
+        let size = self.size;
         let (new_w, new_h) = if w > h {
-            (THUMBNAIL_SIZE, (THUMBNAIL_SIZE as f32 * h as f32 / w as f32) as u32)
+            (size, (size as f32 * h as f32 / w as f32) as u32)
         } else {
-            ((THUMBNAIL_SIZE as f32 * w as f32 / h as f32) as u32, THUMBNAIL_SIZE)
+            ((size as f32 * w as f32 / h as f32) as u32, size)
         };
         
         img.resize(new_w, new_h, FilterType::Triangle)
     }
 
     /// Encode thumbnail for terminal display
-    fn encode_thumbnail(&self, img: &DynamicImage) -> String {
-        match self.backend.protocol {
-            GraphicsProtocol::Kitty => self.encode_kitty(img),
+    fn encode_thumbnail(&self, img: &DynamicImage, image_id: u32) -> String {
+        let sequence = match self.backend.protocol {
+            GraphicsProtocol::Kitty => self.encode_kitty(img, image_id),
             GraphicsProtocol::ITerm2 => self.encode_iterm2(img),
-            _ => String::new(), // No graphics support
-        }
+            GraphicsProtocol::Sixel | GraphicsProtocol::Fallback => self.encode_cells(img),
+        };
+        self.backend.passthrough(sequence)
     }
 
-    /// Encode using Kitty graphics protocol
-    fn encode_kitty(&self, img: &DynamicImage) -> String {
-        let rgba = img.to_rgba8();
-        let (w, h) = (rgba.width(), rgba.height());
-        
-        // Encode PNG
+    /// Encode for protocols drawn into a block of cells: Sixel, or half-block art
+    fn encode_cells(&self, img: &DynamicImage) -> String {
         let mut png_data = Vec::new();
         {
             use std::io::Cursor;
             let mut cursor = Cursor::new(&mut png_data);
-            rgba.write_to(&mut cursor, image::ImageFormat::Png).ok();
+            img.to_rgba8().write_to(&mut cursor, image::ImageFormat::Png).ok();
         }
         
-        let encoded = STANDARD.encode(&png_data);
-        
-        // Calculate cell dimensions (assume ~12 pixels per cell)
-        let cols = (w as f32 / 12.0).ceil() as u16;
-        let rows = (h as f32 / 24.0).ceil() as u16;
+        let (cols, rows) = self.backend.cells_for(img.width(), img.height());
+        self.backend.render_image(0, 0, cols, rows, &png_data)
+    }
+
+    /// Encode using Kitty graphics protocol, storing the image under `image_id`
+    fn encode_kitty(&self, img: &DynamicImage, image_id: u32) -> String {
+        // Calculate cell dimensions from the terminal's cell size
+        let (cols, rows) = self.backend.cells_for(img.width(), img.height());
         
         // Kitty graphics escape sequence
         // f=100 (PNG), a=T (transmit+display), t=d (direct data)
+        // i=image id, p=placement id (re-placing replaces it), q=2 (no replies)
         // c=columns, r=rows
-        if encoded.len() <= 4096 {
-            format!("\x1b_Gf=100,a=T,t=d,c={},r={};{}\x1b\\", cols, rows, encoded)
-        } else {
-            // Chunked transmission
-            let mut result = String::new();
-            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
-            
-            for (i, chunk) in chunks.iter().enumerate() {
-                let is_last = i == chunks.len() - 1;
-                let m = if is_last { 0 } else { 1 };
-                let chunk_str = std::str::from_utf8(chunk).unwrap_or("");
-                
-                if i == 0 {
-                    result.push_str(&format!("\x1b_Gf=100,a=T,t=d,c={},r={},m={};{}\x1b\\", cols, rows, m, chunk_str));
-                } else {
-                    result.push_str(&format!("\x1b_Gm={};{}\x1b\\", m, chunk_str));
-                }
-            }
-            result
+        let control = format!("f=100,a=T,t=d,i={},p=1,q=2,c={},r={}", image_id, cols, rows);
+        kitty_chunks(&control, &encode_png(img))
+    }
+
+    /// Encode GIF frames as one Kitty animation under `image_id`
+    ///
+    /// The first frame is transmitted and placed as usual, the rest are added
+    /// with `a=f`, and `a=a` starts an endless loop. The terminal then plays it
+    /// without further output from us.
+    fn encode_kitty_animation(&self, frames: &[(DynamicImage, Duration)], image_id: u32) -> String {
+        let Some(((first, first_delay), rest)) = frames.split_first() else {
+            return String::new();
+        };
+
+        let mut result = self.encode_kitty(first, image_id);
+        // r=1 edits the first frame's gap, z=gap in milliseconds
+        result.push_str(&format!("\x1b_Ga=a,i={},r=1,z={},q=2\x1b\\", image_id, first_delay.as_millis()));
+        for (frame, delay) in rest {
+            let control = format!("a=f,f=100,t=d,i={},z={},q=2", image_id, delay.as_millis());
+            result.push_str(&kitty_chunks(&control, &encode_png(frame)));
         }
+        // s=3 runs the animation, v=1 loops forever
+        result.push_str(&format!("\x1b_Ga=a,i={},s=3,v=1,q=2\x1b\\", image_id));
+        result
     }
 
-    /// Encode using iTerm2 protocol
+    /// Re-display an image already transmitted under `image_id`
+    fn kitty_display(&self, img: &DynamicImage, image_id: u32) -> String {
+        let (cols, rows) = self.backend.cells_for(img.width(), img.height());
+        self.backend.passthrough(format!("\x1b_Ga=p,i={},p=1,q=2,c={},r={}\x1b\\", image_id, cols, rows))
+    }
+
+    /// Encode using iTerm2 protocol, in parts when the terminal takes them
     fn encode_iterm2(&self, img: &DynamicImage) -> String {
-        let rgba = img.to_rgba8();
+        iterm2::render("preserveAspectRatio=1", &png_bytes(img), self.backend.iterm2_multipart)
+    }
+}
+
+/// Decode every frame of a GIF with its delay, composited to full size
+///
+/// Stops after `MAX_ANIMATION_FRAMES` so huge animations can't exhaust memory.
+fn decode_gif_frames(path: &Path) -> Option<Vec<(DynamicImage, Duration)>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+    let decoder = GifDecoder::new(file).ok()?;
+
+    let mut frames = Vec::new();
+    for frame in decoder.into_frames().take(MAX_ANIMATION_FRAMES) {
+        let frame = frame.ok()?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let millis = numer.checked_div(denom).unwrap_or(0);
+        // Browsers treat near-zero delays as 100ms; so should we
+        let delay = if millis <= 10 { 100 } else { millis };
+        frames.push((DynamicImage::ImageRgba8(frame.into_buffer()), Duration::from_millis(delay as u64)));
+    }
+    Some(frames)
+}
+
+/// Encode an image as base64 PNG for the Kitty protocol
+fn encode_png(img: &DynamicImage) -> String {
+    STANDARD.encode(png_bytes(img))
+}
+
+/// `img` as a PNG file
+fn png_bytes(img: &DynamicImage) -> Vec<u8> {
+    let mut png_data = Vec::new();
+    img.to_rgba8()
+        .write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png)
+        .ok();
+    png_data
+}
+
+/// Wrap a base64 payload in Kitty escape sequences, chunked at 4096 bytes
+///
+/// `control` goes on the first chunk only; later chunks just carry `m=`.
+fn kitty_chunks(control: &str, encoded: &str) -> String {
+    if encoded.len() <= 4096 {
+        return format!("\x1b_G{};{}\x1b\\", control, encoded);
+    }
+
+    // Chunked transmission
+    let mut result = String::new();
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    
+    for (i, chunk) in chunks.iter().enumerate() {
+        let is_last = i == chunks.len() - 1;
+        let m = if is_last { 0 } else { 1 };
+        let chunk_str = std::str::from_utf8(chunk).unwrap_or("");
         
-        let mut png_data = Vec::new();
-        {
-            use std::io::Cursor;
-            let mut cursor = Cursor::new(&mut png_data);
-            rgba.write_to(&mut cursor, image::ImageFormat::Png).ok();
+        if i == 0 {
+            result.push_str(&format!("\x1b_G{},m={};{}\x1b\\", control, m, chunk_str));
+        } else {
+            result.push_str(&format!("\x1b_Gm={};{}\x1b\\", m, chunk_str));
         }
-        
-        let encoded = STANDARD.encode(&png_data);
-        format!("\x1b]1337;File=inline=1;preserveAspectRatio=1:{}\x07", encoded)
     }
+    result
+}
 
-    /// Get image dimensions as string
-    pub fn get_image_info(path: &Path) -> Option<String> {
-        let img = image::open(path).ok()?;
-        Some(format!("{}×{} px", img.width(), img.height()))
+/// Check if a file is an image, going by `extensions`
+///
+/// The extension costs nothing to check; only files without one are opened
+/// to look for an image signature.
+pub fn is_image_file(path: &Path, extensions: &[String]) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => is_image_extension(ext, extensions),
+        None => sniff_image_format(path).is_some(),
     }
+}
 
-    /// Clear the cache
-    pub fn clear(&mut self) {
-        self.cache.clear();
+/// Image format of `path` by its first bytes, or None if it isn't a known image
+pub fn sniff_image_format(path: &Path) -> Option<ImageFormat> {
+    let header = crate::fs::read_file_prefix(path, 12).ok()?;
+    image_format_from_magic(&header)
+}
+
+/// Match the magic numbers of the formats we preview
+fn image_format_from_magic(header: &[u8]) -> Option<ImageFormat> {
+    match header {
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some(ImageFormat::Png),
+        [0xff, 0xd8, 0xff, ..] => Some(ImageFormat::Jpeg),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(ImageFormat::Gif),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(ImageFormat::WebP),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f' | b's', ..] => Some(ImageFormat::Avif),
+        [b'B', b'M', ..] => Some(ImageFormat::Bmp),
+        [0, 0, 1, 0, ..] => Some(ImageFormat::Ico),
+        _ => None,
     }
 }
 
-/// Check if a file is an image
-pub fn is_image_file(path: &Path) -> bool {
-    ThumbnailCache::is_image_file(path)
+/// Why an image that failed to decode can't be previewed, when it's known
+pub fn unsupported_reason(path: &Path) -> Option<&'static str> {
+    let is_avif = sniff_image_format(path) == Some(ImageFormat::Avif);
+    (is_avif && !cfg!(feature = "avif")).then_some("built without AVIF support")
+}
+
+/// Kitty: free the data stored under `image_id`, along with its placements
+fn delete_image(image_id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", image_id)
+}
+
+/// Check if an extension (without the dot, any case) is among `extensions`
+fn is_image_extension(ext: &str, extensions: &[String]) -> bool {
+    extensions.iter().any(|known| known.eq_ignore_ascii_case(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_image_format_from_magic() {
+        assert_eq!(image_format_from_magic(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(ImageFormat::Png));
+        assert_eq!(image_format_from_magic(b"\xff\xd8\xff\xe0"), Some(ImageFormat::Jpeg));
+        assert_eq!(image_format_from_magic(b"GIF89a\x01\0"), Some(ImageFormat::Gif));
+        assert_eq!(image_format_from_magic(b"RIFF\x24\0\0\0WEBPVP8 "), Some(ImageFormat::WebP));
+        assert_eq!(image_format_from_magic(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(image_format_from_magic(b"# notes.png\n"), None);
+        assert_eq!(image_format_from_magic(b""), None);
+    }
+
+    fn kitty_renderer() -> ThumbnailRenderer {
+        ThumbnailRenderer {
+            backend: GraphicsBackend {
+                protocol: GraphicsProtocol::Kitty,
+                cell_size: (10, 20),
+                tmux_passthrough: false,
+                iterm2_multipart: false,
+            },
+            quality: ResizeQuality::Fast,
+            size: 32,
+            disk_cache: None,
+        }
+    }
+
+    /// Write a small sample image in `format` and render it for Kitty
+    fn render_sample(format: ImageFormat, name: &str) -> Option<Thumbnail> {
+        let dir = TempDir::new(&format!("formats-{}", name));
+        let path = dir.path().join(name);
+        image::RgbImage::from_pixel(16, 8, image::Rgb([200, 40, 40])).save_with_format(&path, format).unwrap();
+        kitty_renderer().render(&path, 1)
+    }
+
+    #[test]
+    fn test_render_webp_for_kitty() {
+        let thumbnail = render_sample(ImageFormat::WebP, "sample.webp").unwrap();
+        assert!(thumbnail.transmit.starts_with("\x1b_G"));
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn test_render_avif_for_kitty() {
+        let thumbnail = render_sample(ImageFormat::Avif, "sample.avif").unwrap();
+        assert!(thumbnail.transmit.starts_with("\x1b_G"));
+    }
+
+    #[cfg(not(feature = "avif"))]
+    #[test]
+    fn test_avif_without_feature_explains_why() {
+        assert!(render_sample(ImageFormat::Avif, "sample.avif").is_none());
+        let dir = TempDir::new("formats-explain");
+        let path = dir.path().join("explain.avif");
+        image::RgbImage::new(4, 4).save_with_format(&path, ImageFormat::Avif).unwrap();
+        assert_eq!(unsupported_reason(&path), Some("built without AVIF support"));
+    }
+
+    #[test]
+    fn test_zoom_drops_thumbnails_of_the_old_size() {
+        let mut cache = ThumbnailCache::new(kitty_renderer().backend);
+        let (a, b) = (Path::new("a.png"), Path::new("b.png"));
+        cache.cache.insert(a.to_path_buf(), Thumbnail::still("old".to_string(), None));
+
+        cache.set_zoom(Some((a, 64)));
+        assert!(!cache.is_cached(a));
+        assert_eq!((cache.size_for(a), cache.size_for(b)), (64, DEFAULT_THUMBNAIL_SIZE));
+
+        // A job started before the zoom finishes late and is thrown away
+        let thumbnail = || Some(Thumbnail::still("new".to_string(), None));
+        cache.sender.send((a.to_path_buf(), DEFAULT_THUMBNAIL_SIZE, thumbnail())).unwrap();
+        cache.poll_completed();
+        assert!(!cache.is_cached(a) && !cache.has_failed(a));
+        cache.sender.send((a.to_path_buf(), 64, thumbnail())).unwrap();
+        cache.poll_completed();
+        assert!(cache.is_cached(a));
+
+        cache.set_zoom(None);
+        assert!(!cache.is_cached(a));
+        assert_eq!(cache.size_for(a), DEFAULT_THUMBNAIL_SIZE);
+    }
+
+    #[test]
+    fn test_frame_at_loops() {
+        let mut thumbnail = Thumbnail::still(String::new(), None);
+        thumbnail.frames = vec![
+            ("a".to_string(), Duration::from_millis(100)),
+            ("b".to_string(), Duration::from_millis(50)),
+        ];
+        assert_eq!(thumbnail.frame_at(Duration::from_millis(0)), Some("a"));
+        assert_eq!(thumbnail.frame_at(Duration::from_millis(120)), Some("b"));
+        assert_eq!(thumbnail.frame_at(Duration::from_millis(160)), Some("a"));
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let thumbnail = |bytes| Thumbnail::still("x".repeat(bytes), None);
+        let mut lru = ThumbnailLru::new(2, 100);
+        lru.insert(PathBuf::from("a"), thumbnail(10));
+        lru.insert(PathBuf::from("b"), thumbnail(10));
+        assert!(lru.get_mut(Path::new("a")).is_some());
+        lru.insert(PathBuf::from("c"), thumbnail(10));
+        assert!(lru.get_mut(Path::new("b")).is_none());
+        assert!(lru.get_mut(Path::new("a")).is_some());
+
+        // Over the byte budget only the newest survives
+        lru.insert(PathBuf::from("d"), thumbnail(95));
+        assert_eq!(lru.entries.len(), 1);
+        assert_eq!(lru.bytes, 95);
+        lru.clear();
+        assert_eq!(lru.bytes, 0);
+    }
+
+    #[test]
+    fn test_dropped_thumbnails_are_deleted_from_the_terminal() {
+        let mut cache = ThumbnailCache::new(kitty_renderer().backend);
+        cache.set_cache_limits(1, DEFAULT_CACHE_BYTES);
+        let kitty = |id| Thumbnail { image_id: Some(id), ..Thumbnail::still("data".to_string(), Some("show".to_string())) };
+        cache.cache.insert(PathBuf::from("a.png"), kitty(7));
+        cache.cache.insert(PathBuf::from("b.png"), kitty(8));
+        // a was never sent, so evicting it frees nothing on the terminal
+        assert_eq!(cache.take_dropped(), "");
+
+        assert_eq!(cache.get_thumbnail(Path::new("b.png")).as_deref(), Some("data"));
+        cache.cache.insert(PathBuf::from("c.png"), kitty(9));
+        assert_eq!(cache.take_dropped(), "\x1b_Ga=d,d=I,i=8,q=2\x1b\\");
+        assert_eq!(cache.take_dropped(), "");
+    }
 }
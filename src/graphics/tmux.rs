@@ -0,0 +1,74 @@
+//! tmux passthrough for graphics sequences
+//!
+//! tmux swallows escape sequences it doesn't understand, Kitty graphics
+//! included. Wrapped as `ESC P tmux; … ESC \` with every inner ESC doubled,
+//! they reach the outer terminal untouched, provided tmux has
+//! `allow-passthrough` on.
+
+/// Whether we are running inside tmux
+pub fn detected() -> bool {
+    std::env::var_os("TMUX").is_some_and(|value| !value.is_empty())
+}
+
+/// Wrap each APC, DCS, and OSC sequence in `sequence` for passthrough
+///
+/// Everything else (cursor moves, colors, plain text) is meant for tmux itself
+/// and is copied as is.
+pub fn wrap(sequence: &str) -> String {
+    let mut out = String::with_capacity(sequence.len() + sequence.len() / 8);
+    let mut rest = sequence;
+
+    while let Some(start) = find_graphics_start(rest) {
+        out.push_str(&rest[..start]);
+        let unit = &rest[start..];
+        let end = sequence_end(unit);
+
+        out.push_str("\x1bPtmux;");
+        out.push_str(&unit[..end].replace('\x1b', "\x1b\x1b"));
+        out.push_str("\x1b\\");
+        rest = &unit[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Offset of the next `ESC _`, `ESC P`, or `ESC ]`
+fn find_graphics_start(text: &str) -> Option<usize> {
+    text.match_indices('\x1b')
+        .map(|(i, _)| i)
+        .find(|&i| matches!(text.as_bytes().get(i + 1), Some(b'_' | b'P' | b']')))
+}
+
+/// Length of the sequence at the start of `unit`, through its terminator
+///
+/// Sequences end at ST (`ESC \`); OSC may also end at BEL. An unterminated
+/// sequence runs to the end.
+fn sequence_end(unit: &str) -> usize {
+    let st = unit[2..].find("\x1b\\").map(|i| i + 2 + 2);
+    let bel = (unit.as_bytes()[1] == b']')
+        .then(|| unit.find('\x07').map(|i| i + 1))
+        .flatten();
+    match (st, bel) {
+        (Some(st), Some(bel)) => st.min(bel),
+        (Some(end), None) | (None, Some(end)) => end,
+        (None, None) => unit.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_graphics_sequences_only() {
+        let wrapped = wrap("\x1b7\x1b[3;4H\x1b_Ga=p,i=1\x1b\\\x1b]1337;File=x\x07\x1b8");
+        assert_eq!(
+            wrapped,
+            "\x1b7\x1b[3;4H\
+             \x1bPtmux;\x1b\x1b_Ga=p,i=1\x1b\x1b\\\x1b\\\
+             \x1bPtmux;\x1b\x1b]1337;File=x\x07\x1b\\\
+             \x1b8"
+        );
+        assert_eq!(wrap("plain"), "plain");
+    }
+}
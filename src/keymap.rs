@@ -0,0 +1,650 @@
+//! Key chords and the actions they trigger in normal mode
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something the user can do from the file list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    /// Clear the filter, then the marks, then quit
+    Cancel,
+    Up,
+    Down,
+    Home,
+    End,
+    Open,
+    GoUp,
+    Back,
+    Forward,
+    GoTo,
+    Filter,
+    Mark,
+    ShowMarkedOnly,
+    HideMarked,
+    ToggleSidebar,
+    TogglePreview,
+    PreviewUp,
+    PreviewDown,
+    ToggleMinimal,
+    CycleSort,
+    ReverseSort,
+    SortAscending,
+    SortDescending,
+    ToggleHidden,
+    ToggleContentPreviews,
+    RemoveEmptyDirs,
+    ToggleWritable,
+    ToggleImmutable,
+    DescendSingle,
+    NewFile,
+    NewDirectory,
+    ToggleDualPane,
+    SwitchPane,
+    CopyToOtherPane,
+    MoveToOtherPane,
+    ToggleTree,
+    /// Expand the selected directory in the tree view
+    Expand,
+    /// Collapse the selected directory, or select its parent
+    Collapse,
+    /// Switch the file list between rows and a grid of icons
+    ToggleIconView,
+    /// Make image thumbnails bigger, regenerating the one shown
+    ThumbnailLarger,
+    ThumbnailSmaller,
+    /// Show the selected image full screen; Enter on an image does too
+    ViewImage,
+    /// Total the size of the selected directories in the background
+    DirectorySize,
+    /// Put the marked entries, or the selection, on the clipboard
+    Copy,
+    Cut,
+    /// Paste the clipboard into the current directory
+    Paste,
+    /// Ask to delete the marked entries, or the selection
+    Delete,
+    Rename,
+    UndoRename,
+    /// Re-read the current directory
+    Refresh,
+    /// Copy the selected entry's absolute path to the system clipboard
+    CopyPath,
+    /// Copy just the selected entry's name
+    CopyName,
+    /// Search and run any action by name
+    CommandPalette,
+    ShowJobs,
+    /// List what Paste would copy or move, and drop items from it
+    ShowClipboard,
+    /// Add the current directory to the sidebar
+    AddBookmark,
+    /// Remove the highlighted sidebar entry
+    RemoveBookmark,
+    /// Open a shell, or the configured terminal, in the current directory
+    OpenTerminal,
+    OpenWebUrl,
+    Edit,
+    EmptyTrash,
+}
+
+impl Action {
+    /// Every action, in the order they are documented
+    pub const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::Cancel,
+        Action::Up,
+        Action::Down,
+        Action::Home,
+        Action::End,
+        Action::Open,
+        Action::GoUp,
+        Action::Back,
+        Action::Forward,
+        Action::GoTo,
+        Action::Filter,
+        Action::Mark,
+        Action::ShowMarkedOnly,
+        Action::HideMarked,
+        Action::ToggleSidebar,
+        Action::TogglePreview,
+        Action::PreviewUp,
+        Action::PreviewDown,
+        Action::ToggleMinimal,
+        Action::CycleSort,
+        Action::ReverseSort,
+        Action::SortAscending,
+        Action::SortDescending,
+        Action::ToggleHidden,
+        Action::ToggleContentPreviews,
+        Action::RemoveEmptyDirs,
+        Action::ToggleWritable,
+        Action::ToggleImmutable,
+        Action::DescendSingle,
+        Action::NewFile,
+        Action::NewDirectory,
+        Action::ToggleDualPane,
+        Action::SwitchPane,
+        Action::CopyToOtherPane,
+        Action::MoveToOtherPane,
+        Action::ToggleTree,
+        Action::Expand,
+        Action::Collapse,
+        Action::ToggleIconView,
+        Action::ThumbnailLarger,
+        Action::ThumbnailSmaller,
+        Action::ViewImage,
+        Action::DirectorySize,
+        Action::Copy,
+        Action::Cut,
+        Action::Paste,
+        Action::Delete,
+        Action::Rename,
+        Action::UndoRename,
+        Action::Refresh,
+        Action::CopyPath,
+        Action::CopyName,
+        Action::CommandPalette,
+        Action::ShowJobs,
+        Action::ShowClipboard,
+        Action::AddBookmark,
+        Action::RemoveBookmark,
+        Action::OpenTerminal,
+        Action::OpenWebUrl,
+        Action::Edit,
+        Action::EmptyTrash,
+    ];
+
+    /// Name used in the `[keys]` config table
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Cancel => "cancel",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Home => "home",
+            Self::End => "end",
+            Self::Open => "open",
+            Self::GoUp => "go_up",
+            Self::Back => "back",
+            Self::Forward => "forward",
+            Self::GoTo => "go_to",
+            Self::Filter => "filter",
+            Self::Mark => "mark",
+            Self::ShowMarkedOnly => "show_marked_only",
+            Self::HideMarked => "hide_marked",
+            Self::ToggleSidebar => "sidebar",
+            Self::TogglePreview => "preview",
+            Self::PreviewUp => "preview_up",
+            Self::PreviewDown => "preview_down",
+            Self::ToggleMinimal => "minimal",
+            Self::CycleSort => "sort",
+            Self::ReverseSort => "reverse_sort",
+            Self::SortAscending => "sort_ascending",
+            Self::SortDescending => "sort_descending",
+            Self::ToggleHidden => "hidden",
+            Self::ToggleContentPreviews => "content_previews",
+            Self::RemoveEmptyDirs => "remove_empty_dirs",
+            Self::ToggleWritable => "writable",
+            Self::ToggleImmutable => "immutable",
+            Self::DescendSingle => "descend",
+            Self::NewFile => "new_file",
+            Self::NewDirectory => "new_directory",
+            Self::ToggleDualPane => "dual_pane",
+            Self::SwitchPane => "switch_pane",
+            Self::CopyToOtherPane => "copy_to_other_pane",
+            Self::MoveToOtherPane => "move_to_other_pane",
+            Self::ToggleTree => "tree",
+            Self::Expand => "expand",
+            Self::Collapse => "collapse",
+            Self::ToggleIconView => "icon_view",
+            Self::ThumbnailLarger => "thumbnail_larger",
+            Self::ThumbnailSmaller => "thumbnail_smaller",
+            Self::ViewImage => "view_image",
+            Self::DirectorySize => "directory_size",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::Delete => "delete",
+            Self::Rename => "rename",
+            Self::UndoRename => "undo_rename",
+            Self::Refresh => "refresh",
+            Self::CopyPath => "copy_path",
+            Self::CopyName => "copy_name",
+            Self::CommandPalette => "command_palette",
+            Self::ShowJobs => "jobs",
+            Self::ShowClipboard => "clipboard",
+            Self::AddBookmark => "add_bookmark",
+            Self::RemoveBookmark => "remove_bookmark",
+            Self::OpenTerminal => "terminal",
+            Self::OpenWebUrl => "open_web_url",
+            Self::Edit => "edit",
+            Self::EmptyTrash => "empty_trash",
+        }
+    }
+
+    /// What the action does, as listed in the command palette
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Cancel => "Clear filter or marks, or quit",
+            Self::Up => "Move selection up",
+            Self::Down => "Move selection down",
+            Self::Home => "Select first entry",
+            Self::End => "Select last entry",
+            Self::Open => "Open selected entry",
+            Self::GoUp => "Go to parent directory",
+            Self::Back => "Go back",
+            Self::Forward => "Go forward",
+            Self::GoTo => "Go to path",
+            Self::Filter => "Filter entries",
+            Self::Mark => "Mark entry",
+            Self::ShowMarkedOnly => "Show marked only",
+            Self::HideMarked => "Hide marked",
+            Self::ToggleSidebar => "Toggle sidebar",
+            Self::TogglePreview => "Toggle preview pane",
+            Self::PreviewUp => "Scroll preview up",
+            Self::PreviewDown => "Scroll preview down",
+            Self::ToggleMinimal => "Toggle minimal mode",
+            Self::CycleSort => "Cycle sort mode",
+            Self::ReverseSort => "Reverse sort order",
+            Self::SortAscending => "Sort ascending",
+            Self::SortDescending => "Sort descending",
+            Self::ToggleHidden => "Toggle hidden files",
+            Self::ToggleContentPreviews => "Toggle content previews",
+            Self::RemoveEmptyDirs => "Remove empty directories",
+            Self::ToggleWritable => "Toggle writable",
+            Self::ToggleImmutable => "Toggle immutable flag",
+            Self::DescendSingle => "Descend through single subdirectories",
+            Self::NewFile => "New file",
+            Self::NewDirectory => "New directory",
+            Self::ToggleDualPane => "Toggle dual-pane mode",
+            Self::SwitchPane => "Switch pane",
+            Self::CopyToOtherPane => "Copy to other pane",
+            Self::MoveToOtherPane => "Move to other pane",
+            Self::ToggleTree => "Toggle tree view",
+            Self::Expand => "Expand directory",
+            Self::Collapse => "Collapse directory",
+            Self::ToggleIconView => "Toggle icon view",
+            Self::ThumbnailLarger => "Larger thumbnails",
+            Self::ThumbnailSmaller => "Smaller thumbnails",
+            Self::ViewImage => "View image full screen",
+            Self::DirectorySize => "Calculate directory size",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::Delete => "Delete",
+            Self::Rename => "Rename",
+            Self::UndoRename => "Undo last rename",
+            Self::Refresh => "Refresh",
+            Self::CopyPath => "Copy path to clipboard",
+            Self::CopyName => "Copy name to clipboard",
+            Self::CommandPalette => "Command palette",
+            Self::ShowJobs => "Show operation queue",
+            Self::ShowClipboard => "Show clipboard",
+            Self::AddBookmark => "Bookmark current directory",
+            Self::RemoveBookmark => "Remove highlighted bookmark",
+            Self::OpenTerminal => "Open terminal here",
+            Self::OpenWebUrl => "Open web URL of selection",
+            Self::Edit => "Edit in $EDITOR at the previewed line",
+            Self::EmptyTrash => "Empty the trash",
+        }
+    }
+
+    /// Look an action up by its config name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    /// Whether the action only changes the layout or quits, so it is safe
+    /// while a directory is still loading
+    pub fn allowed_while_loading(self) -> bool {
+        matches!(
+            self,
+            Self::Quit
+                | Self::Cancel
+                | Self::ToggleSidebar
+                | Self::TogglePreview
+                | Self::ToggleMinimal
+                | Self::ToggleIconView
+                | Self::ToggleDualPane
+                | Self::SwitchPane
+                | Self::CommandPalette
+                | Self::ShowJobs
+                | Self::ShowClipboard
+        )
+    }
+
+    /// Whether the action writes to the filesystem, so it has to wait for a
+    /// running copy, move, or delete
+    pub fn changes_files(self) -> bool {
+        matches!(
+            self,
+            Self::RemoveEmptyDirs
+                | Self::ToggleWritable
+                | Self::ToggleImmutable
+                | Self::NewFile
+                | Self::NewDirectory
+                | Self::CopyToOtherPane
+                | Self::MoveToOtherPane
+                | Self::Paste
+                | Self::Delete
+                | Self::Rename
+                | Self::UndoRename
+                | Self::EmptyTrash
+        )
+    }
+
+    /// Whether the action runs as a background job, which queues behind a
+    /// running one instead of waiting
+    pub fn starts_job(self) -> bool {
+        matches!(self, Self::CopyToOtherPane | Self::MoveToOtherPane | Self::Paste | Self::Delete)
+    }
+}
+
+/// A key plus modifiers, e.g. `ctrl+f` or `F1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Build a chord from a key event, normalized so lookups match parsed chords
+    ///
+    /// Shift is dropped for characters because the case already carries it
+    /// (`S` arrives as `Shift+S`).
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT) }
+    }
+
+    /// A chord without modifiers
+    pub const fn plain(code: KeyCode) -> Self {
+        Self { code, modifiers: KeyModifiers::NONE }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    /// Parse `[ctrl+][alt+][shift+]<key>`, case-insensitive except single characters
+    ///
+    /// Shift with a letter means the uppercase letter, which is how the key
+    /// arrives; other characters have to be written as their shifted symbol.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut modifiers = KeyModifiers::NONE;
+        // The key itself may be "+", as in "+" or "ctrl++"
+        let (prefix, key) = match s.strip_suffix('+') {
+            Some(prefix) => (prefix.strip_suffix('+').unwrap_or(prefix), "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+
+        for part in prefix.split('+').filter(|part| !part.is_empty()) {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier `{}` in `{}`", other, s)),
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        if !c.is_alphabetic() {
+                            return Err(format!("`{}` has no shifted form; bind the character Shift types instead", s));
+                        }
+                        KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+                    }
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n @ 1..=24) => KeyCode::F(n),
+                        _ => return Err(format!("unknown key `{}` in `{}`", key, s)),
+                    },
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Backspace => write!(f, "Bksp"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Key chords mapped to actions
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl Default for Keymap {
+    /// The built-in bindings
+    fn default() -> Self {
+        use KeyCode::*;
+        let alt = |code| KeyChord { code, modifiers: KeyModifiers::ALT };
+        let ctrl = |code| KeyChord { code, modifiers: KeyModifiers::CONTROL };
+        let plain = KeyChord::plain;
+
+        let bindings = [
+            (plain(Char('q')), Action::Quit),
+            (plain(Esc), Action::Cancel),
+            (plain(Up), Action::Up),
+            (plain(Char('k')), Action::Up),
+            (plain(Down), Action::Down),
+            (plain(Char('j')), Action::Down),
+            (plain(Home), Action::Home),
+            (plain(End), Action::End),
+            (plain(Enter), Action::Open),
+            (plain(Backspace), Action::GoUp),
+            (alt(Left), Action::Back),
+            (alt(Right), Action::Forward),
+            (plain(Char(':')), Action::GoTo),
+            (plain(Char('g')), Action::GoTo),
+            (plain(Char('/')), Action::Filter),
+            (plain(Char(' ')), Action::Mark),
+            (plain(Char('F')), Action::ShowMarkedOnly),
+            (plain(Char('H')), Action::HideMarked),
+            (plain(F(1)), Action::ToggleSidebar),
+            (plain(F(2)), Action::TogglePreview),
+            (alt(Up), Action::PreviewUp),
+            (alt(Down), Action::PreviewDown),
+            (plain(Char('m')), Action::ToggleMinimal),
+            (plain(Char('s')), Action::CycleSort),
+            (plain(Char('S')), Action::ReverseSort),
+            (plain(Char('[')), Action::SortAscending),
+            (plain(Char(']')), Action::SortDescending),
+            (plain(Char('.')), Action::ToggleHidden),
+            (plain(Char('P')), Action::ToggleContentPreviews),
+            (plain(Char('E')), Action::RemoveEmptyDirs),
+            (plain(Char('w')), Action::ToggleWritable),
+            (plain(Char('I')), Action::ToggleImmutable),
+            (plain(Char('>')), Action::DescendSingle),
+            (plain(Char('n')), Action::NewFile),
+            (plain(Char('N')), Action::NewDirectory),
+            (plain(F(3)), Action::ToggleDualPane),
+            (plain(Tab), Action::SwitchPane),
+            (plain(F(5)), Action::CopyToOtherPane),
+            (plain(F(6)), Action::MoveToOtherPane),
+            (plain(Char('T')), Action::ToggleTree),
+            (plain(Right), Action::Expand),
+            (plain(Left), Action::Collapse),
+            (plain(Char('v')), Action::ToggleIconView),
+            (plain(Char('+')), Action::ThumbnailLarger),
+            (plain(Char('=')), Action::ThumbnailLarger),
+            (plain(Char('-')), Action::ThumbnailSmaller),
+            (plain(F(4)), Action::ViewImage),
+            (plain(Char('u')), Action::DirectorySize),
+            (plain(Delete), Action::Delete),
+            (plain(Char('r')), Action::Rename),
+            (ctrl(Char('z')), Action::UndoRename),
+            (plain(Char('y')), Action::CopyPath),
+            (plain(Char('Y')), Action::CopyName),
+            // r and F5, the usual refresh keys, are taken by Rename and CopyToOtherPane
+            (ctrl(Char('r')), Action::Refresh),
+            (plain(Char('R')), Action::Refresh),
+            (ctrl(Char('p')), Action::CommandPalette),
+            (plain(Char('J')), Action::ShowJobs),
+            (plain(Char('C')), Action::ShowClipboard),
+            (plain(Char('b')), Action::AddBookmark),
+            (plain(Char('B')), Action::RemoveBookmark),
+            (plain(Char('!')), Action::OpenTerminal),
+            (plain(Char('W')), Action::OpenWebUrl),
+            (plain(Char('e')), Action::Edit),
+        ];
+
+        Self { bindings: bindings.into_iter().collect() }
+    }
+}
+
+impl Keymap {
+    /// Defaults with the given actions rebound
+    ///
+    /// Each entry replaces all default chords of its action. Unknown actions
+    /// and unparsable chords are skipped and returned as warnings, as is a
+    /// chord taken from an action that keeps its other defaults.
+    pub fn with_overrides(overrides: &[(String, Vec<String>)]) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut warnings = Vec::new();
+        let mut taken = Vec::new();
+
+        for (name, chords) in overrides {
+            let Some(action) = Action::from_name(name) else {
+                warnings.push(format!("unknown action `{}` in [keys]", name));
+                continue;
+            };
+
+            keymap.bindings.retain(|_, bound| *bound != action);
+            for chord in chords {
+                match chord.parse::<KeyChord>() {
+                    Ok(chord) => {
+                        if let Some(previous) = keymap.bindings.insert(chord, action).filter(|&previous| previous != action) {
+                            taken.push((chord, action, previous));
+                        }
+                    }
+                    Err(e) => warnings.push(format!("{} for `{}`", e, name)),
+                }
+            }
+        }
+
+        // Rebinding both actions of a swap is deliberate; only a default going missing is worth a word
+        for (chord, action, previous) in taken {
+            if !overrides.iter().any(|(name, _)| Action::from_name(name) == Some(previous)) {
+                warnings.push(format!("`{}` for `{}` no longer runs `{}`", chord, action.name(), previous.name()));
+            }
+        }
+
+        (keymap, warnings)
+    }
+
+    /// Action bound to a key event, if any
+    pub fn action_for(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        self.bindings.get(&KeyChord::new(code, modifiers)).copied()
+    }
+
+    /// Chords bound to `action`, sorted for display
+    pub fn chords_for(&self, action: Action) -> Vec<KeyChord> {
+        let mut chords: Vec<KeyChord> = self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(chord, _)| *chord)
+            .collect();
+        chords.sort_by_key(|chord| chord.to_string());
+        chords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chords() {
+        assert_eq!("q".parse::<KeyChord>(), Ok(KeyChord::plain(KeyCode::Char('q'))));
+        assert_eq!("F1".parse::<KeyChord>(), Ok(KeyChord::plain(KeyCode::F(1))));
+        assert_eq!(
+            "ctrl+f".parse::<KeyChord>(),
+            Ok(KeyChord { code: KeyCode::Char('f'), modifiers: KeyModifiers::CONTROL })
+        );
+        assert_eq!(
+            "Alt+Left".parse::<KeyChord>(),
+            Ok(KeyChord { code: KeyCode::Left, modifiers: KeyModifiers::ALT })
+        );
+        assert_eq!("+".parse::<KeyChord>(), Ok(KeyChord::plain(KeyCode::Char('+'))));
+        // Shift is carried by the case, as in key events
+        assert_eq!("shift+x".parse::<KeyChord>(), Ok(KeyChord::plain(KeyCode::Char('X'))));
+        assert_eq!(
+            "ctrl+shift+x".parse::<KeyChord>(),
+            Ok(KeyChord { code: KeyCode::Char('X'), modifiers: KeyModifiers::CONTROL })
+        );
+        assert!("shift+1".parse::<KeyChord>().is_err());
+        assert!("hyper+x".parse::<KeyChord>().is_err());
+        assert!("F99".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_overrides_replace_defaults() {
+        let overrides = vec![
+            ("quit".to_string(), vec!["ctrl+q".to_string()]),
+            ("bogus".to_string(), vec!["x".to_string()]),
+            ("refresh".to_string(), vec!["j".to_string()]),
+            // Taken from actions rebound here too, so nothing is lost unannounced
+            ("hidden".to_string(), vec!["ctrl+r".to_string()]),
+            ("reverse_sort".to_string(), vec!["S".to_string(), ".".to_string()]),
+        ];
+        let (keymap, warnings) = Keymap::with_overrides(&overrides);
+        assert_eq!(keymap.action_for(KeyCode::Char('q'), KeyModifiers::NONE), None);
+        assert_eq!(keymap.action_for(KeyCode::Char('q'), KeyModifiers::CONTROL), Some(Action::Quit));
+        // Uppercase letters arrive with Shift set
+        assert_eq!(keymap.action_for(KeyCode::Char('S'), KeyModifiers::SHIFT), Some(Action::ReverseSort));
+        assert_eq!(keymap.action_for(KeyCode::Char('j'), KeyModifiers::NONE), Some(Action::Refresh));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1], "`j` for `refresh` no longer runs `down`");
+    }
+}
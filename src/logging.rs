@@ -0,0 +1,66 @@
+//! Diagnostics written to a log file
+//!
+//! The UI owns the terminal, so nothing may go to stdout or stderr once it
+//! is up. Messages go to `dirs::cache_dir()/graphterm/log` instead, which is
+//! started afresh on each run.
+
+use anyhow::{Context, Result};
+use log::{Level, Log, Metadata, Record};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Appends each record to the log file as one line
+struct FileLogger {
+    file: Mutex<File>,
+    level: Level,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Location of the log file, if the platform has a cache directory
+pub fn path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("graphterm").join("log"))
+}
+
+/// Send `log` records to the log file: info and above, or debug too with `verbose`
+pub fn init(verbose: bool) -> Result<()> {
+    let path = path().context("no cache directory on this platform")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("cannot create log directory")?;
+    }
+    let file = File::create(&path).with_context(|| format!("cannot create {}", path.display()))?;
+
+    let level = if verbose { Level::Debug } else { Level::Info };
+    log::set_boxed_logger(Box::new(FileLogger { file: Mutex::new(file), level }))
+        .context("a logger is already set")?;
+    log::set_max_level(level.to_level_filter());
+    Ok(())
+}
@@ -4,20 +4,102 @@
 //! using Kitty/Sixel protocols, while maintaining full SSH compatibility.
 
 mod app;
+mod bookmarks;
+mod config;
+mod keymap;
+mod logging;
 mod ui;
+mod url_map;
 mod graphics;
 mod fs;
+#[cfg(test)]
+mod test_support;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use app::App;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize the application
-    let mut app = App::new()?;
-    
-    // Run the main event loop
-    app.run().await?;
-    
-    Ok(())
+/// How long quitting waits for background work, e.g. a cancelled copy
+/// finishing its current file; directory totals, git logs and trash
+/// purges still running after that are abandoned
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+const USAGE: &str = "usage: graphterm [--minimal] [--verbose] [DIRECTORY]";
+
+/// Command line: `graphterm [--minimal] [--verbose] [DIRECTORY]`
+struct Args {
+    /// Start with the sidebar and preview hidden
+    minimal: bool,
+    /// Log debug messages too
+    verbose: bool,
+    /// Directory to open, overriding `start_dir` from the config
+    start_dir: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Self { minimal: false, verbose: false, start_dir: None };
+        for arg in std::env::args_os().skip(1) {
+            if arg == "--minimal" {
+                args.minimal = true;
+            } else if arg == "--verbose" || arg == "-v" {
+                args.verbose = true;
+            } else if arg.to_string_lossy().starts_with('-') {
+                bail!("unknown option `{}`\n{}", arg.to_string_lossy(), USAGE);
+            } else if args.start_dir.is_some() {
+                bail!("only one directory can be given\n{}", USAGE);
+            } else {
+                let path = crate::fs::expand_tilde(&arg.to_string_lossy());
+                let dir = path.canonicalize()
+                    .with_context(|| format!("cannot open {}", path.display()))?;
+                if !dir.is_dir() {
+                    bail!("{} is not a directory", path.display());
+                }
+                args.start_dir = Some(dir);
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn main() -> Result<()> {
+    // Parse command line flags; a bad directory exits before the UI starts
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("graphterm: {:#}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // Diagnostics go to a file from here on; the terminal belongs to the UI
+    if let Err(e) = logging::init(args.verbose) {
+        eprintln!("[GraphTerm] Logging disabled: {:#}", e);
+    }
+
+    // Put the terminal back before a panic message is printed, or it lands
+    // in the alternate screen and the shell is left in raw mode. Background
+    // workers catch their own panics and report a failure, so the UI keeps
+    // running and their message goes to the log instead of over the screen.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            App::restore_terminal();
+            default_hook(info);
+        } else {
+            log::error!("Background worker {}", info);
+        }
+    }));
+
+    // Built by hand rather than with #[tokio::main], whose runtime waits on
+    // every blocking task when dropped, so quitting could hang on a walk
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(async {
+        // The app, and with it the terminal, is restored before shutting down
+        let mut app = App::new(args.minimal, args.start_dir)?;
+        app.run().await
+    });
+    runtime.shutdown_timeout(SHUTDOWN_GRACE);
+    result
 }
@@ -0,0 +1,29 @@
+//! Helpers shared by the unit tests
+
+use std::path::{Path, PathBuf};
+
+/// An empty scratch directory under the system temp dir, removed on drop
+///
+/// Dropping runs during unwinding too, so a failed assertion doesn't leave
+/// the fixture behind for the next run.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `graphterm-<name>-<pid>`; the name must differ between tests of a run
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("graphterm-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
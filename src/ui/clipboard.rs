@@ -0,0 +1,34 @@
+//! Clipboard overlay: what the next Paste copies or moves, with their sizes
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// One copied or cut path
+#[derive(Debug)]
+pub struct ClipboardItem {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// A file's length; directories are totalled by the `DirSizeCache`
+    pub size: Option<u64>,
+    /// For looking up a directory's cached total
+    pub modified: Option<SystemTime>,
+    /// Gone since it was copied, so Paste will fail on it
+    pub missing: bool,
+}
+
+/// A snapshot of the clipboard, refreshed by the app while the overlay is open
+#[derive(Debug, Default)]
+pub struct ClipboardList {
+    /// Whether Paste moves the items rather than copying them
+    pub cut: bool,
+    pub items: Vec<ClipboardItem>,
+    /// Index into `items` that Delete removes
+    pub selected: usize,
+}
+
+impl ClipboardList {
+    pub fn move_selection(&mut self, delta: i32) {
+        let last = self.items.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta as isize).min(last);
+    }
+}
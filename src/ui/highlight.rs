@@ -0,0 +1,106 @@
+//! Syntax highlighting for the text preview
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::cell::{OnceCell, RefCell};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme as SyntaxTheme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+/// Colors for highlighted text, from syntect's bundled themes
+const THEME_NAME: &str = "base16-ocean.dark";
+
+/// A highlighted file: its path and mtime, and the lines
+type Highlighted = (PathBuf, Option<SystemTime>, Vec<Line<'static>>);
+
+/// Highlights preview text by file extension
+///
+/// The syntax and theme sets take a noticeable moment to load, so they are
+/// only loaded for the first file that needs them. The last result is kept,
+/// since the preview is redrawn every frame.
+#[derive(Default)]
+pub struct Highlighter {
+    syntaxes: OnceCell<SyntaxSet>,
+    theme: OnceCell<SyntaxTheme>,
+    /// The last file highlighted
+    last: RefCell<Option<Highlighted>>,
+}
+
+impl Highlighter {
+    /// Highlighted `lines` of the file at `path`, or `None` for unknown file types
+    pub fn highlight(&self, path: &Path, modified: Option<SystemTime>, lines: &[String]) -> Option<Vec<Line<'static>>> {
+        if let Some((last_path, last_modified, highlighted)) = self.last.borrow().as_ref() {
+            if last_path == path && *last_modified == modified {
+                return Some(highlighted.clone());
+            }
+        }
+
+        let syntaxes = self.syntaxes.get_or_init(SyntaxSet::load_defaults_newlines);
+        let syntax = path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| syntaxes.find_syntax_by_extension(ext))
+            // Extensionless names like `Makefile` are listed as extensions too
+            .or_else(|| path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| syntaxes.find_syntax_by_extension(name)))?;
+        let theme = self.theme.get_or_init(|| {
+            ThemeSet::load_defaults().themes.remove(THEME_NAME).unwrap_or_default()
+        });
+
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let highlighted: Vec<Line<'static>> = lines
+            .iter()
+            .map(|line| {
+                // The newline-aware syntaxes expect each line to end in one
+                let line_with_newline = format!("{}\n", line);
+                let ranges = highlighter.highlight_line(&line_with_newline, syntaxes).ok();
+                match ranges {
+                    Some(ranges) => Line::from(ranges
+                        .into_iter()
+                        .map(|(style, text)| Span::styled(text.trim_end_matches('\n').to_string(), convert_style(style)))
+                        .collect::<Vec<_>>()),
+                    None => Line::from(line.clone()),
+                }
+            })
+            .collect();
+
+        *self.last.borrow_mut() = Some((path.to_path_buf(), modified, highlighted.clone()));
+        Some(highlighted)
+    }
+}
+
+/// Foreground and font style only; the pane keeps its own background
+fn convert_style(style: syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut converted = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        converted = converted.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        converted = converted.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        converted = converted.add_modifier(Modifier::UNDERLINED);
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_known_extensions_only() {
+        let highlighter = Highlighter::default();
+        let lines = vec!["fn main() {}".to_string()];
+
+        let highlighted = highlighter.highlight(Path::new("main.rs"), None, &lines).unwrap();
+        assert!(highlighted[0].spans.len() > 1);
+        let text: String = highlighted[0].spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, "fn main() {}");
+
+        assert!(highlighter.highlight(Path::new("notes.unknown-ext"), None, &lines).is_none());
+    }
+}
@@ -0,0 +1,21 @@
+//! Operation queue overlay: the running copy, move, or delete, those waiting, and those done
+
+/// A snapshot of the queue, refreshed by the app while the overlay is open
+#[derive(Debug, Default)]
+pub struct JobList {
+    /// The running job, e.g. "Copying 3 items to /tmp"
+    pub active: Option<String>,
+    /// Jobs waiting their turn, first to run first
+    pub pending: Vec<String>,
+    /// Finished jobs, most recent last, with whether they succeeded
+    pub completed: Vec<(String, bool)>,
+    /// Index into `pending` that Delete cancels
+    pub selected: usize,
+}
+
+impl JobList {
+    pub fn move_selection(&mut self, delta: i32) {
+        let last = self.pending.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta as isize).min(last);
+    }
+}
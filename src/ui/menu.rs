@@ -0,0 +1,94 @@
+//! Right-click context menu entries
+
+use crate::fs::FileEntry;
+use crate::keymap::Action;
+
+/// An entry in the context menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    /// Open a file with the default application
+    Open,
+    /// Enter a directory
+    Enter,
+    /// Open an image in the default viewer
+    OpenExternally,
+    /// Show the image in the preview pane, turning previews on if needed
+    Preview,
+    Copy,
+    Cut,
+    Paste,
+    Delete,
+    Rename,
+    /// Total a directory's size in the background
+    CalculateSize,
+    NewFolder,
+    Refresh,
+}
+
+/// Items for a menu opened on `entry`, or on empty space when None
+///
+/// The renderer and the key handler both call this, so indexes agree.
+pub fn context_menu_items(entry: Option<&FileEntry>) -> &'static [MenuItem] {
+    use MenuItem::*;
+
+    let Some(entry) = entry else {
+        return &[Paste, NewFolder, Refresh];
+    };
+    if entry.is_dir {
+        &[Enter, CalculateSize, Copy, Cut, Paste, Delete, Rename]
+    } else if entry.is_image {
+        &[OpenExternally, Preview, Copy, Cut, Paste, Delete, Rename]
+    } else {
+        &[Open, Copy, Cut, Paste, Delete, Rename]
+    }
+}
+
+impl MenuItem {
+    /// The keymap action this item runs on the selection, for items that have one
+    pub fn action(self) -> Option<Action> {
+        match self {
+            Self::Copy => Some(Action::Copy),
+            Self::Cut => Some(Action::Cut),
+            Self::Paste => Some(Action::Paste),
+            Self::Delete => Some(Action::Delete),
+            Self::Rename => Some(Action::Rename),
+            Self::CalculateSize => Some(Action::DirectorySize),
+            Self::NewFolder => Some(Action::NewDirectory),
+            Self::Refresh => Some(Action::Refresh),
+            Self::Open | Self::Enter | Self::OpenExternally | Self::Preview => None,
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Open | Self::Enter => "📂",
+            Self::OpenExternally => "🔗",
+            Self::Preview => "🖼️",
+            Self::Copy => "📋",
+            Self::Cut => "✂️",
+            Self::Paste => "📄",
+            Self::Delete => "🗑️",
+            Self::Rename => "✏️",
+            Self::CalculateSize => "📏",
+            Self::NewFolder => "📁",
+            Self::Refresh => "🔄",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Open => "Open",
+            Self::Enter => "Enter",
+            Self::OpenExternally => "Open externally",
+            Self::Preview => "Preview",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::Delete => "Delete",
+            Self::Rename => "Rename",
+            Self::CalculateSize => "Calculate size",
+            Self::NewFolder => "New folder",
+            Self::Refresh => "Refresh",
+        }
+    }
+}
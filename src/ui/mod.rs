@@ -1,19 +1,279 @@
 //! UI rendering and layout
 
+mod clipboard;
+mod highlight;
+mod jobs;
 mod layout;
+mod menu;
+mod palette;
+mod theme;
 
+pub use clipboard::{ClipboardItem, ClipboardList};
+pub use jobs::JobList;
 pub use layout::*;
+pub use menu::{context_menu_items, MenuItem};
+pub use palette::CommandPalette;
+pub use theme::Theme;
 
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
     Frame,
 };
 
-use crate::fs::FileSystem;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::bookmarks::Bookmark;
+use crate::fs::{DirSizeCache, FileEntry, FileSystem, FileType, JobKind, JobProgress, GIT_COLUMN_WIDTH};
 use crate::graphics::GraphicsBackend;
+use crate::graphics::icons::{IconManager, IconSlot};
+
+/// How long an error stays in the status bar
+const ERROR_DURATION: Duration = Duration::from_secs(4);
+
+/// Narrowest file grid, inside the border, that shows the modified column
+const MODIFIED_COLUMN_MIN_WIDTH: usize = 48;
+
+/// Widest the Ext column gets; longer extensions are cut short with "…"
+const EXT_COLUMN_MAX_WIDTH: usize = 8;
+
+/// An icon view cell: the icon, the name below it, and a blank row
+const ICON_CELL_WIDTH: u16 = 16;
+const ICON_CELL_HEIGHT: u16 = 3;
+
+/// Fewest icon columns worth leaving the list for
+const ICON_VIEW_MIN_COLUMNS: u16 = 2;
+
+/// Message shown in the status bar in place of the key hints
+pub struct StatusMessage {
+    pub text: String,
+    /// Errors are shown in red and outlast the next key press
+    pub is_error: bool,
+    pub shown_at: Instant,
+}
+
+/// What an input prompt's text is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    /// New name for the selected entry
+    Rename,
+    /// Directory to jump to, with tab completion
+    GoTo,
+    /// Name for a new empty file
+    NewFile,
+    /// Name for a new directory
+    NewDirectory,
+    /// "empty", typed out to empty the trash
+    EmptyTrash,
+}
+
+/// What keys go to: the file list, or whatever is open over it
+///
+/// The only record of what is open: rendering and input both go by it. The
+/// overlays' contents live alongside in `Ui`, and the app's `set_mode` drops
+/// those of the one being left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The file list with its sidebar, preview, or second pane, driven by the keymap
+    Normal,
+    /// The right-click menu
+    ContextMenu,
+    /// A text prompt or a yes/no question
+    Input(InputKind),
+    /// Typing into the `/` filter
+    Search,
+    /// The selected image alone, as large as the terminal allows
+    Viewer,
+    /// The command palette, listing every action with its key
+    Help,
+    /// The operation queue, in `Ui::job_list`
+    Jobs,
+    /// What Paste would copy or move, in `Ui::clipboard_list`
+    Clipboard,
+}
+
+/// The dialog an `Input` mode is showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// A line of text, in `Ui::input_prompt`
+    Prompt,
+    /// Delete the entries in `Ui::pending_delete`?
+    ConfirmDelete,
+    /// Remove the directories in `Ui::cleanup_preview`?
+    ConfirmCleanup,
+    /// Quit while a job is running?
+    ConfirmQuit,
+}
+
+/// One side of the dual-pane layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Left,
+    Right,
+}
+
+impl Pane {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Left => "Left",
+            Self::Right => "Right",
+        }
+    }
+}
+
+/// Selection of the pane without focus, swapped with the `Ui` fields on Tab
+#[derive(Debug, Default)]
+pub struct PaneState {
+    pub selected_index: usize,
+    pub scroll_offset: usize,
+    pub marked: HashSet<PathBuf>,
+}
+
+/// The second directory shown in dual-pane mode
+#[derive(Clone, Copy)]
+pub struct OtherPane<'a> {
+    pub fs: &'a FileSystem,
+    /// Side the focused pane (the `fs` passed to `render`) is on
+    pub focused: Pane,
+}
+
+/// What a file grid draws: selection, marks, and whether it has focus
+struct GridView<'a> {
+    selected: usize,
+    scroll: usize,
+    marked: &'a HashSet<PathBuf>,
+    focused: bool,
+    dir_sizes: &'a DirSizeCache,
+}
+
+/// Cells of the icon view from the last render, one per visible entry
+#[derive(Debug, Clone)]
+pub struct IconGrid {
+    /// In reading order, starting at the scroll offset
+    pub cells: Vec<Rect>,
+    pub columns: usize,
+}
+
+impl IconGrid {
+    /// Lay out `area`, inside its border, or `None` when it is too small for the grid
+    fn fit(area: Rect) -> Option<Self> {
+        let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+        if inner.width < ICON_CELL_WIDTH * ICON_VIEW_MIN_COLUMNS || inner.height < ICON_CELL_HEIGHT {
+            return None;
+        }
+        Some(Self {
+            cells: calculate_grid_layout(inner, ICON_CELL_WIDTH, ICON_CELL_HEIGHT),
+            columns: (inner.width / ICON_CELL_WIDTH) as usize,
+        })
+    }
+}
+
+/// Entries waiting for the user to confirm their deletion
+pub struct DeleteConfirm {
+    pub paths: Vec<PathBuf>,
+    /// Whether any of them is a directory, whose whole tree would go
+    pub has_dirs: bool,
+    /// Yes is highlighted; starts on No so a stray Enter deletes nothing
+    pub yes_selected: bool,
+    /// Delete for good instead of moving to the trash
+    pub permanent: bool,
+    /// Moving to the trash failed, so this asks about permanent deletion instead
+    pub trash_failed: bool,
+}
+
+impl DeleteConfirm {
+    pub fn new(paths: Vec<PathBuf>, permanent: bool) -> Self {
+        // symlink_metadata, like `fs::delete`: a link to a directory is just a link
+        let has_dirs = paths.iter()
+            .any(|path| std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()));
+        Self { paths, has_dirs, yes_selected: false, permanent, trash_failed: false }
+    }
+}
+
+/// Single-line text prompt shown over the UI
+pub struct InputPrompt {
+    pub kind: PromptKind,
+    /// Prompt title, e.g. "Rename"
+    pub title: String,
+    /// Text entered so far
+    pub value: String,
+    /// Cursor position, in characters
+    pub cursor: usize,
+    /// Entry the prompt acts on, fixed when it opens so clicks or reloads
+    /// meanwhile can't redirect it
+    pub target: Option<PathBuf>,
+}
+
+impl InputPrompt {
+    /// A prompt with the cursor after `value`
+    pub fn new(kind: PromptKind, title: impl Into<String>, value: String) -> Self {
+        let cursor = value.chars().count();
+        Self { kind, title: title.into(), value, cursor, target: None }
+    }
+
+    /// Replace the text, moving the cursor to the end
+    pub fn set_value(&mut self, value: String) {
+        self.cursor = value.chars().count();
+        self.value = value;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let at = self.byte_offset(self.cursor);
+        self.value.insert(at, c);
+        self.cursor += 1;
+    }
+
+    /// Remove the character before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.value.remove(self.byte_offset(self.cursor));
+        }
+    }
+
+    /// Remove the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.value.chars().count() {
+            self.value.remove(self.byte_offset(self.cursor));
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.value.chars().count());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.value.chars().count();
+    }
+
+    /// Text before and after the cursor
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.value.split_at(self.byte_offset(self.cursor))
+    }
+
+    fn byte_offset(&self, chars: usize) -> usize {
+        self.value.char_indices().nth(chars).map_or(self.value.len(), |(i, _)| i)
+    }
+}
 
 /// UI state
 pub struct Ui {
@@ -21,16 +281,105 @@ pub struct Ui {
     pub selected_index: usize,
     /// Scroll offset for file list
     pub scroll_offset: usize,
-    /// Whether context menu is visible
-    pub show_context_menu: bool,
+    /// What is open over the file list, and so what gets drawn
+    pub mode: Mode,
     /// Context menu position (x, y)
     pub context_menu_pos: (u16, u16),
     /// Selected context menu item
     pub context_menu_selected: usize,
+    /// Entry the context menu was opened on; None for empty space
+    pub context_menu_target: Option<PathBuf>,
+    /// Row of each context menu item in the last render
+    pub context_menu_rows: Vec<Rect>,
     /// Whether sidebar is visible
     pub show_sidebar: bool,
     /// Whether preview pane is visible
     pub show_preview: bool,
+    /// Minimal mode: file list only, with a compact header
+    pub minimal: bool,
+    /// Key that leaves minimal mode, as the compact header names it; None if unbound
+    pub full_view_key: Option<String>,
+    /// Transient message shown in the status bar
+    pub status_message: Option<StatusMessage>,
+    /// Copy, move, or delete running in the background; shown in place of the status bar
+    pub job_progress: Option<(JobKind, JobProgress)>,
+    /// Jobs waiting behind the running one
+    pub queued_jobs: usize,
+    /// What the trash holds, e.g. "34 items, 1.2 GB", once measured
+    pub trash_size: Option<String>,
+    /// The operation queue overlay's contents while it is open
+    pub job_list: Option<JobList>,
+    /// The clipboard overlay's contents while it is open
+    pub clipboard_list: Option<ClipboardList>,
+    /// Forces content previews on or off; `None` disables them on network mounts
+    pub preview_override: Option<bool>,
+    /// Empty directories awaiting confirmation before removal
+    pub cleanup_preview: Option<Vec<PathBuf>>,
+    /// Entries awaiting delete confirmation
+    pub pending_delete: Option<DeleteConfirm>,
+    /// Entries marked with Space or shift-click, acted on together
+    pub marked: HashSet<PathBuf>,
+    /// Active text prompt, if any
+    pub input_prompt: Option<InputPrompt>,
+    /// Open command palette, if any
+    pub command_palette: Option<CommandPalette>,
+    /// Where the palette was drawn in the last render
+    pub palette_area: Option<Rect>,
+    /// Bytes read for the hex preview of binary files
+    pub hex_preview_bytes: usize,
+    /// Show extensions in their own column, after the name without them
+    pub split_extension: bool,
+    /// Syntax highlighting for text previews, loaded on first use
+    highlighter: highlight::Highlighter,
+    /// File grid area from the last render, used for mouse hit-testing
+    pub file_grid_area: Rect,
+    /// Show entries as a grid of icons rather than rows
+    pub icon_view: bool,
+    /// Icon cells from the last render; `None` while the list is shown
+    pub icon_grid: Option<IconGrid>,
+    /// Graphic icons the last render left room for, drawn by the app afterwards
+    pub icon_slots: Vec<IconSlot>,
+    /// The selected image's thumbnail is still being generated
+    pub thumbnail_loading: bool,
+    /// The selected image could not be decoded
+    pub thumbnail_failed: bool,
+    /// Dimensions of the last image asked about, keyed by path and modification time
+    image_info: Option<(PathBuf, Option<SystemTime>, Option<String>)>,
+    /// The immutable flag of the last entry previewed, keyed the same way
+    immutable_flag: Option<(PathBuf, Option<SystemTime>, Option<bool>)>,
+    /// Why the last image that failed to decode can't be previewed, by path
+    unsupported_reason: Option<(PathBuf, Option<&'static str>)>,
+    /// Text of the last file previewed, keyed by path and modification time
+    text_preview: Option<(PathBuf, Option<SystemTime>, Option<crate::fs::TextPreview>)>,
+    /// "owner:group" by uid and gid, so the passwd and group databases are
+    /// read once per owner rather than every frame
+    owner_names: HashMap<(u32, u32), String>,
+    /// Hex dump bytes of the last binary file previewed, keyed by path and modification time
+    hex_preview: Option<(PathBuf, Option<SystemTime>, Option<Vec<u8>>)>,
+    /// Where the last render left room for the thumbnail, inside the preview pane
+    pub thumbnail_area: Option<Rect>,
+    /// Preview pane from the last render; `None` while it is hidden
+    pub preview_area: Option<Rect>,
+    /// First line of a text preview shown, for the selected file; also where `edit` opens it
+    pub preview_scroll: usize,
+    /// Colors, from the config file
+    pub theme: Theme,
+    /// Two file grids side by side; sidebar and preview are hidden meanwhile
+    pub dual_pane: bool,
+    /// Selection of the pane without focus in dual-pane mode
+    pub other_pane: PaneState,
+    /// Grid area of the pane without focus, for mouse hit-testing
+    pub other_grid_area: Rect,
+    /// Icon cells of the pane without focus
+    pub other_icon_grid: Option<IconGrid>,
+    /// Scrollbar track of the focused file grid; `None` when everything fits
+    pub scrollbar_area: Option<Rect>,
+    /// Sidebar entries, in order
+    pub bookmarks: Vec<Bookmark>,
+    /// Row of each bookmark in the last render; empty while the sidebar is hidden
+    pub sidebar_items: Vec<Rect>,
+    /// Bookmark under the mouse pointer
+    pub sidebar_hover: Option<usize>,
 }
 
 impl Ui {
@@ -38,23 +387,85 @@ impl Ui {
         Self {
             selected_index: 0,
             scroll_offset: 0,
-            show_context_menu: false,
+            mode: Mode::Normal,
             context_menu_pos: (0, 0),
             context_menu_selected: 0,
+            context_menu_target: None,
+            context_menu_rows: Vec::new(),
             show_sidebar: true,
             show_preview: true,
+            minimal: false,
+            full_view_key: Some("m".to_string()),
+            status_message: None,
+            job_progress: None,
+            queued_jobs: 0,
+            trash_size: None,
+            job_list: None,
+            clipboard_list: None,
+            preview_override: None,
+            cleanup_preview: None,
+            pending_delete: None,
+            marked: HashSet::new(),
+            input_prompt: None,
+            command_palette: None,
+            palette_area: None,
+            hex_preview_bytes: crate::fs::DEFAULT_HEX_PREVIEW_BYTES,
+            split_extension: false,
+            highlighter: highlight::Highlighter::default(),
+            file_grid_area: Rect::default(),
+            icon_view: false,
+            icon_grid: None,
+            icon_slots: Vec::new(),
+            thumbnail_loading: false,
+            thumbnail_failed: false,
+            image_info: None,
+            immutable_flag: None,
+            unsupported_reason: None,
+            text_preview: None,
+            hex_preview: None,
+            owner_names: HashMap::new(),
+            thumbnail_area: None,
+            preview_area: None,
+            preview_scroll: 0,
+            theme: Theme::default(),
+            dual_pane: false,
+            other_pane: PaneState::default(),
+            other_grid_area: Rect::default(),
+            other_icon_grid: None,
+            scrollbar_area: None,
+            bookmarks: Bookmark::defaults(),
+            sidebar_items: Vec::new(),
+            sidebar_hover: None,
         }
     }
 
     /// Render the entire UI
-    pub fn render(&self, frame: &mut Frame, fs: &FileSystem, graphics: &GraphicsBackend, thumbnail: Option<&str>) {
+    ///
+    /// `other` is the second pane in dual-pane mode; `fs` is always the focused one.
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        fs: &FileSystem,
+        other: Option<OtherPane>,
+        graphics: &GraphicsBackend,
+        thumbnail: Option<&str>,
+        dir_sizes: &DirSizeCache,
+    ) {
+        self.expire_status();
+        self.icon_slots.clear();
+        self.thumbnail_area = None;
+        self.preview_area = None;
+        self.sidebar_items.clear();
+        self.context_menu_rows.clear();
+        self.palette_area = None;
         let size = frame.area();
 
         // Create main layout: Header | Main Content | Status Bar
+        let header_height = if self.minimal { 1 } else { 3 };
         let main_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3),  // Header/toolbar
+                Constraint::Length(header_height),  // Header/toolbar
                 Constraint::Min(10),    // Main content
                 Constraint::Length(1),  // Status bar
             ])
@@ -62,8 +473,20 @@ impl Ui {
 
         self.render_header(frame, main_chunks[0], fs);
         
-        // Three-pane layout for main content
-        if self.show_sidebar || self.show_preview {
+        if let Some(other) = other.filter(|_| self.dual_pane) {
+            // Two grids side by side, the focused one on its own side
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(main_chunks[1]);
+            let (focused_area, other_area) = match other.focused {
+                Pane::Left => (halves[0], halves[1]),
+                Pane::Right => (halves[1], halves[0]),
+            };
+            self.render_file_grid(frame, focused_area, fs, graphics, dir_sizes);
+            self.render_other_grid(frame, other_area, other.fs, graphics, dir_sizes);
+        } else if self.show_sidebar || self.show_preview {
+            // Three-pane layout for main content
             let mut constraints = Vec::new();
             
             if self.show_sidebar {
@@ -86,35 +509,162 @@ impl Ui {
                 idx += 1;
             }
             
-            self.render_file_grid(frame, content_chunks[idx], fs, graphics);
+            self.render_file_grid(frame, content_chunks[idx], fs, graphics, dir_sizes);
             idx += 1;
             
             if self.show_preview {
-                self.render_preview(frame, content_chunks[idx], fs, thumbnail);
+                self.render_preview(frame, content_chunks[idx], fs, thumbnail, dir_sizes);
             }
         } else {
-            self.render_file_grid(frame, main_chunks[1], fs, graphics);
+            self.render_file_grid(frame, main_chunks[1], fs, graphics, dir_sizes);
         }
         
-        self.render_status_bar(frame, main_chunks[2], fs);
+        self.render_status_bar(frame, main_chunks[2], fs, other.map(|other| other.focused));
 
-        // Render context menu if visible
-        if self.show_context_menu {
-            self.render_context_menu(frame);
+        match self.mode {
+            Mode::ContextMenu => {
+                self.context_menu_rows = self.render_context_menu(frame, self.context_menu_entry(fs));
+            }
+            Mode::Input(InputKind::ConfirmCleanup) => {
+                if let Some(dirs) = &self.cleanup_preview {
+                    self.render_cleanup_preview(frame, fs, dirs);
+                }
+            }
+            Mode::Input(InputKind::ConfirmDelete) => {
+                if let Some(confirm) = &self.pending_delete {
+                    self.render_delete_confirm(frame, confirm);
+                }
+            }
+            Mode::Input(InputKind::ConfirmQuit) => self.render_quit_confirm(frame),
+            Mode::Input(InputKind::Prompt) => {
+                if let Some(prompt) = &self.input_prompt {
+                    self.render_input_prompt(frame, prompt);
+                }
+            }
+            Mode::Help => {
+                if let Some(palette) = &self.command_palette {
+                    self.palette_area = Some(self.render_command_palette(frame, palette));
+                }
+            }
+            Mode::Jobs => {
+                if let Some(jobs) = &self.job_list {
+                    self.render_job_list(frame, jobs);
+                }
+            }
+            Mode::Clipboard => {
+                if let Some(list) = &self.clipboard_list {
+                    self.render_clipboard_list(frame, list, dir_sizes);
+                }
+            }
+            Mode::Normal | Mode::Search | Mode::Viewer => {}
+        }
+
+        // Graphics would draw over overlays, so hide icons while one is open
+        if matches!(self.mode, Mode::ContextMenu | Mode::Input(_) | Mode::Help | Mode::Jobs | Mode::Clipboard) {
+            self.icon_slots.clear();
         }
     }
 
+    /// Render the full-screen image viewer: the selected image inside a frame
+    ///
+    /// Like the preview, the image itself is drawn by the app afterwards, in
+    /// `thumbnail_area`.
+    pub fn render_viewer(&mut self, frame: &mut Frame, fs: &FileSystem, thumbnail: Option<&str>) {
+        self.expire_status();
+        self.icon_slots.clear();
+        self.thumbnail_area = None;
+        self.preview_area = None;
+        self.sidebar_items.clear();
+        self.scrollbar_area = None;
+
+        let entry = fs.get_selected(self.selected_index);
+        let title = match entry {
+            Some(entry) => match self.image_info(entry) {
+                Some(info) => format!(" {} ({}) ", entry.name, info),
+                None => format!(" {} ", entry.name),
+            },
+            None => String::new(),
+        };
+        let footer = match &self.status_message {
+            Some(message) => Span::styled(
+                format!(" {} ", message.text),
+                Style::default().fg(if message.is_error { Color::Red } else { Color::Yellow }),
+            ),
+            None => Span::styled(" ←/→:Previous/next  Esc:Back ", Style::default().fg(Color::DarkGray)),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_style(Style::default().fg(Color::Green))
+            .title_bottom(Line::from(footer));
+        let inner = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let message = if thumbnail.is_some() {
+            self.thumbnail_area = Some(inner).filter(|rect| !rect.is_empty());
+            return;
+        } else if self.thumbnail_failed {
+            let reason = entry.and_then(|entry| self.unsupported_reason(&entry.path));
+            Span::styled(
+                reason.map_or_else(|| "Cannot preview this image".to_string(), |reason| format!("Cannot preview: {}", reason)),
+                Style::default().fg(Color::Red),
+            )
+        } else {
+            Span::styled("Loading…", Style::default().fg(Color::DarkGray))
+        };
+        let area = Rect::new(inner.x, inner.y + inner.height / 2, inner.width, 1.min(inner.height));
+        frame.render_widget(Paragraph::new(Line::from(message)).alignment(Alignment::Center), area);
+    }
+
     /// Render the header with path and navigation
     fn render_header(&self, frame: &mut Frame, area: Rect, fs: &FileSystem) {
         let path_display = fs.current_path.display().to_string();
         
+        if self.minimal {
+            // Compact single-line header without borders
+            let header = Paragraph::new(Line::from(vec![
+                Span::styled(" 📁 ", Style::default().fg(Color::Yellow)),
+                Span::styled(path_display, Style::default().fg(self.theme.path).add_modifier(Modifier::BOLD)),
+                Span::raw("  "),
+                match &self.full_view_key {
+                    Some(key) => Span::styled(format!("[{}:Full]", key), Style::default().fg(Color::DarkGray)),
+                    None => Span::raw(""),
+                },
+                if fs.show_hidden {
+                    Span::styled(" [Hidden shown]", Style::default().fg(Color::Yellow))
+                } else {
+                    Span::raw("")
+                },
+            ]));
+            frame.render_widget(header, area);
+            return;
+        }
+
+        // Mark the part of the path that was collapsed through
+        let collapsed = fs.collapsed_from.as_ref()
+            .and_then(|top| fs.current_path.strip_prefix(top).ok())
+            .map(|rest| format!(" (⤵ {})", rest.display()))
+            .unwrap_or_default();
+        
         let header = Paragraph::new(Line::from(vec![
             Span::styled(" 📁 ", Style::default().fg(Color::Yellow)),
-            Span::styled(&path_display, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(&path_display, Style::default().fg(self.theme.path).add_modifier(Modifier::BOLD)),
+            Span::styled(collapsed, Style::default().fg(Color::DarkGray)),
             Span::raw("  "),
+            if fs.is_network && !self.content_previews(fs) {
+                Span::styled("[NET: previews off] ", Style::default().fg(Color::Yellow))
+            } else {
+                Span::raw("")
+            },
             Span::styled("[F1:Sidebar]", Style::default().fg(Color::DarkGray)),
             Span::raw(" "),
             Span::styled("[F2:Preview]", Style::default().fg(Color::DarkGray)),
+            Span::raw(" "),
+            if fs.show_hidden {
+                Span::styled("[.:Hidden shown]", Style::default().fg(Color::Yellow))
+            } else {
+                Span::styled("[.:Hidden]", Style::default().fg(Color::DarkGray))
+            },
         ]))
         .block(Block::default()
             .borders(Borders::ALL)
@@ -125,54 +675,132 @@ impl Ui {
     }
 
     /// Render the sidebar with bookmarks
-    fn render_sidebar(&self, frame: &mut Frame, area: Rect, fs: &FileSystem) {
-        let home = dirs::home_dir()
-            .map(|p| p.display().to_string())
-            .unwrap_or_else(|| "~".to_string());
-        
-        let bookmarks = vec![
-            ("🏠", "Home", home.clone()),
-            ("📄", "Documents", format!("{}/Documents", home)),
-            ("⬇️", "Downloads", format!("{}/Downloads", home)),
-            ("🖼️", "Pictures", format!("{}/Pictures", home)),
-            ("🎵", "Music", format!("{}/Music", home)),
-            ("💻", "Desktop", format!("{}/Desktop", home)),
-        ];
-
-        let items: Vec<ListItem> = bookmarks
+    fn render_sidebar(&mut self, frame: &mut Frame, area: Rect, fs: &FileSystem) {
+        let current = self.current_bookmark(fs);
+        let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+        self.sidebar_items = (0..self.bookmarks.len().min(inner.height as usize))
+            .map(|row| Rect::new(inner.x, inner.y + row as u16, inner.width, 1))
+            .collect();
+
+        let items: Vec<ListItem> = self.bookmarks
             .iter()
-            .map(|(icon, name, path)| {
-                let is_current = fs.current_path.to_string_lossy().starts_with(path);
-                let style = if is_current {
+            .enumerate()
+            .map(|(index, bookmark)| {
+                let style = if current == Some(index) {
                     Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
                 } else {
                     Style::default().fg(Color::White)
                 };
+                let row_style = if self.sidebar_hover == Some(index) {
+                    Style::default().bg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
                 
                 ListItem::new(Line::from(vec![
-                    Span::raw(format!(" {} ", icon)),
-                    Span::styled(*name, style),
-                ]))
+                    Span::raw(format!(" {} ", bookmark.icon())),
+                    Span::styled(bookmark.name.clone(), style),
+                ])).style(row_style)
             })
             .collect();
 
-        let sidebar = List::new(items)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title(" Bookmarks ")
-                .title_style(Style::default().fg(Color::Yellow)));
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Bookmarks ")
+            .title_style(Style::default().fg(Color::Yellow));
+        if let Some(size) = &self.trash_size {
+            block = block.title_bottom(Line::from(Span::styled(format!(" 🗑 {} ", size), Style::default().fg(Color::DarkGray))));
+        }
+        let sidebar = List::new(items).block(block);
 
         frame.render_widget(sidebar, area);
     }
 
+    /// A directory's calculated size, or a spinner while it is being totalled
+    ///
+    /// "≥" marks a total that left out unreadable entries.
+    fn dir_size_label(dir_sizes: &DirSizeCache, entry: &FileEntry) -> Option<String> {
+        const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+        let path = entry.path.as_path();
+        if let Some(size) = dir_sizes.get(path, entry.modified) {
+            let partial = if size.skipped > 0 { "≥" } else { "" };
+            Some(format!("{}{}", partial, crate::fs::format_size(size.bytes)))
+        } else if dir_sizes.is_pending(path) {
+            let millis = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            Some(format!("{} calculating", SPINNER[(millis / 100) as usize % SPINNER.len()]))
+        } else {
+            None
+        }
+    }
+
+    /// Permission string and owner:group for the preview pane
+    #[cfg(unix)]
+    fn permission_lines(&mut self, entry: &FileEntry) -> Vec<Line<'static>> {
+        let owner = self.owner_names.entry((entry.uid, entry.gid)).or_insert_with(|| {
+            let user = crate::fs::user_name(entry.uid).unwrap_or_else(|| entry.uid.to_string());
+            let group = crate::fs::group_name(entry.gid).unwrap_or_else(|| entry.gid.to_string());
+            format!("{}:{}", user, group)
+        });
+        vec![
+            Line::from(vec![
+                Span::styled("Permissions: ", Style::default().fg(Color::DarkGray)),
+                Span::raw(format!("{} ({:04o})", crate::fs::format_permissions(entry.mode), entry.mode & 0o7777)),
+            ]),
+            Line::from(vec![
+                Span::styled("Owner: ", Style::default().fg(Color::DarkGray)),
+                Span::raw(owner.clone()),
+            ]),
+        ]
+    }
+
+    /// Readonly/hidden/system attributes for the preview pane
+    #[cfg(windows)]
+    fn permission_lines(&mut self, entry: &FileEntry) -> Vec<Line<'static>> {
+        let labels = crate::fs::attribute_labels(entry.mode);
+        let attributes = if labels.is_empty() { "none".to_string() } else { labels.join(", ") };
+        vec![Line::from(vec![
+            Span::styled("Attributes: ", Style::default().fg(Color::DarkGray)),
+            Span::raw(attributes),
+        ])]
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn permission_lines(&mut self, _entry: &FileEntry) -> Vec<Line<'static>> {
+        Vec::new()
+    }
+
     /// Render the preview pane
-    fn render_preview(&self, frame: &mut Frame, area: Rect, fs: &FileSystem, thumbnail: Option<&str>) {
-        let content = if let Some(entry) = fs.get_selected(self.selected_index) {
+    fn render_preview(&mut self, frame: &mut Frame, area: Rect, fs: &FileSystem, thumbnail: Option<&str>, dir_sizes: &DirSizeCache) {
+        self.preview_area = Some(area);
+        let mut content = if let Some(entry) = fs.get_selected(self.selected_index) {
             let name = entry.name.clone();
             let path = entry.path.display().to_string();
-            let is_image = crate::graphics::is_image_file(&entry.path);
+            let is_image = entry.is_image;
             
-            if entry.is_dir {
+            if entry.file_type.is_special() {
+                // Show type only; reading a FIFO or device could block or hang
+                vec![
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{} {}", Self::entry_icon(entry), entry.file_type.label()),
+                            Style::default().fg(self.entry_color(entry)).add_modifier(Modifier::BOLD),
+                        ),
+                    ]),
+                    Line::from(""),
+                    Line::from(vec![
+                        Span::styled("Name: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(name),
+                    ]),
+                    Line::from(vec![
+                        Span::styled("Path: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(path),
+                    ]),
+                ]
+            } else if entry.is_dir {
                 // Show directory info
                 vec![
                     Line::from(vec![
@@ -187,12 +815,27 @@ impl Ui {
                         Span::styled("Path: ", Style::default().fg(Color::DarkGray)),
                         Span::raw(path),
                     ]),
+                    Line::from(vec![
+                        Span::styled("Size: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(Self::dir_size_label(dir_sizes, entry)
+                            .unwrap_or_else(|| "not calculated".to_string())),
+                    ]),
+                    Line::from(vec![
+                        Span::styled("Modified: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(crate::fs::format_modified(entry.modified)),
+                    ]),
                 ]
+                .into_iter()
+                .chain(self.permission_lines(entry))
+                .collect()
             } else if is_image {
                 // Show image info with thumbnail placeholder
                 let size = crate::fs::format_size(entry.size);
-                let dimensions = crate::graphics::thumbnails::ThumbnailCache::get_image_info(&entry.path)
-                    .unwrap_or_else(|| "Unknown".to_string());
+                let dimensions = if self.content_previews(fs) {
+                    self.image_info(entry).unwrap_or_else(|| "Unknown".to_string())
+                } else {
+                    "Not loaded".to_string()
+                };
                 
                 let mut lines = vec![
                     Line::from(vec![
@@ -207,6 +850,10 @@ impl Ui {
                         Span::styled("Size: ", Style::default().fg(Color::DarkGray)),
                         Span::raw(size),
                     ]),
+                    Line::from(vec![
+                        Span::styled("Modified: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(crate::fs::format_modified(entry.modified)),
+                    ]),
                     Line::from(vec![
                         Span::styled("Dimensions: ", Style::default().fg(Color::DarkGray)),
                         Span::raw(dimensions),
@@ -214,20 +861,111 @@ impl Ui {
                 ];
                 
                 // If we have a thumbnail, add placeholder for where it will render
-                if thumbnail.is_some() {
+                if self.thumbnail_loading {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![
+                        Span::styled("Loading…", Style::default().fg(Color::DarkGray)),
+                    ]));
+                } else if thumbnail.is_some() {
                     lines.push(Line::from(""));
                     lines.push(Line::from(vec![
                         Span::styled("[Thumbnail Below]", Style::default().fg(Color::Green)),
                     ]));
+                } else if self.thumbnail_failed {
+                    let message = match self.unsupported_reason(&entry.path) {
+                        Some(reason) => format!("Cannot preview: {}", reason),
+                        None => "Cannot preview this image".to_string(),
+                    };
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![
+                        Span::styled(message, Style::default().fg(Color::Red)),
+                    ]));
                 }
                 
+                lines
+            } else if let Some(text) = self.text_preview(fs, entry) {
+                // Show the beginning of the file with a wc-style summary
+                let ext = entry.name.rsplit('.').next().unwrap_or("").to_uppercase();
+                let partial = if text.truncated { "≥" } else { "" };
+                
+                let mut lines = vec![
+                    Line::from(vec![
+                        Span::styled(ext, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                        Span::raw(" File"),
+                    ]),
+                    Line::from(vec![
+                        Span::styled("Lines: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(format!("{}{}", partial, text.line_count)),
+                        Span::styled("  Words: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(format!("{}{}", partial, text.word_count)),
+                        Span::styled("  Bytes: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(entry.size.to_string()),
+                    ]),
+                ];
+                // Scrolled down, the header says where, since that's where `edit` opens the file
+                self.preview_scroll = self.preview_scroll.min(text.lines.len().saturating_sub(1));
+                if self.preview_scroll > 0 {
+                    lines.push(Line::from(Span::styled(
+                        format!("From line {}", self.preview_scroll + 1),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                } else {
+                    lines.push(Line::from(""));
+                }
+                match self.highlighter.highlight(&entry.path, entry.modified, &text.lines) {
+                    Some(highlighted) => lines.extend(highlighted.into_iter().skip(self.preview_scroll)),
+                    None => lines.extend(text.lines.into_iter().skip(self.preview_scroll).map(Line::from)),
+                }
+                
+                if text.truncated {
+                    lines.push(Line::from(Span::styled(
+                        format!("… truncated, showing the first {}", crate::fs::format_size(crate::fs::PREVIEW_MAX_BYTES as u64)),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                
+                lines
+            } else if let Some(bytes) = self.hex_preview(fs, entry) {
+                // Binary contents as a hex dump, as many columns as fit
+                let ext = entry.name.rsplit('.').next().unwrap_or("").to_uppercase();
+                let inner_width = area.width.saturating_sub(2) as usize;
+                let per_row = [16, 8, 4].into_iter()
+                    .find(|&n| crate::fs::hex_dump_lines(&[0; 16][..n], n)[0].len() <= inner_width)
+                    .unwrap_or(4);
+
+                let mut lines = vec![
+                    Line::from(vec![
+                        Span::styled(ext, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                        Span::raw(" Binary"),
+                    ]),
+                    Line::from(vec![
+                        Span::styled("Size: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(crate::fs::format_size(entry.size)),
+                    ]),
+                    Line::from(""),
+                ];
+                lines.extend(crate::fs::hex_dump_lines(&bytes, per_row).into_iter().map(|line| {
+                    let (offset, rest) = line.split_at(8);
+                    Line::from(vec![
+                        Span::styled(offset.to_string(), Style::default().fg(Color::DarkGray)),
+                        Span::raw(rest.to_string()),
+                    ])
+                }));
+
+                if entry.size > bytes.len() as u64 {
+                    lines.push(Line::from(Span::styled(
+                        format!("… showing the first {}", crate::fs::format_size(bytes.len() as u64)),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+
                 lines
             } else {
                 // Show file info
                 let size = crate::fs::format_size(entry.size);
                 let ext = entry.name.rsplit('.').next().unwrap_or("").to_uppercase();
                 
-                vec![
+                let mut lines = vec![
                     Line::from(vec![
                         Span::styled(ext, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
                         Span::raw(" File"),
@@ -241,88 +979,495 @@ impl Ui {
                         Span::styled("Size: ", Style::default().fg(Color::DarkGray)),
                         Span::raw(size),
                     ]),
+                    Line::from(vec![
+                        Span::styled("Modified: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(crate::fs::format_modified(entry.modified)),
+                    ]),
                     Line::from(vec![
                         Span::styled("Path: ", Style::default().fg(Color::DarkGray)),
                         Span::raw(path),
                     ]),
-                ]
+                    Line::from(vec![
+                        Span::styled("Writable: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(if entry.writable { "yes" } else { "no" }),
+                    ]),
+                ];
+
+                if let Some(immutable) = self.immutable_flag(entry) {
+                    lines.push(Line::from(vec![
+                        Span::styled("Immutable: ", Style::default().fg(Color::DarkGray)),
+                        Span::raw(if immutable { "yes" } else { "no" }),
+                    ]));
+                }
+                lines.extend(self.permission_lines(entry));
+
+                lines
             }
         } else {
             vec![Line::from(Span::styled("No file selected", Style::default().fg(Color::DarkGray)))]
         };
 
+        // "name -> target" under the heading of a symlink
+        if let Some(entry) = fs.get_selected(self.selected_index).filter(|entry| entry.is_symlink) {
+            let target = entry.link_target.as_ref()
+                .map_or_else(|| "?".to_string(), |target| target.display().to_string());
+            let mut spans = vec![
+                Span::styled("Link: ", Style::default().fg(Color::DarkGray)),
+                Span::raw(format!("{} -> {}", entry.name, target)),
+            ];
+            if entry.is_broken_link {
+                spans.push(Span::styled(" (broken)", Style::default().fg(Color::Red)));
+            }
+            content.insert(content.len().min(2), Line::from(spans));
+        }
+
+        // The image goes below the text, where the placeholder points
+        if thumbnail.is_some() {
+            let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+            // Rows the text takes once wrapped
+            let text_rows: u16 = content.iter()
+                .map(|line| (line.width().max(1) as u16).div_ceil(inner.width.max(1)))
+                .sum();
+            let top = inner.y + text_rows.min(inner.height);
+            self.thumbnail_area = Some(Rect::new(inner.x, top, inner.width, inner.bottom() - top))
+                .filter(|rect| !rect.is_empty());
+        }
+
         let preview = Paragraph::new(content)
-            .wrap(Wrap { trim: true })
+            .wrap(Wrap { trim: false })
             .block(Block::default()
                 .borders(Borders::ALL)
                 .title(" Preview ")
                 .title_style(Style::default().fg(Color::Green)));
 
         frame.render_widget(preview, area);
-        
-        // Render thumbnail after the widget if we have one
-        // The thumbnail escape sequence is written directly to stdout after ratatui draws
-        if let Some(thumb_seq) = thumbnail {
-            // Store the sequence to be written after frame render
-            // Note: This is a simplified approach - in production you'd use cursor positioning
-            if let Some(entry) = fs.get_selected(self.selected_index) {
-                if crate::graphics::is_image_file(&entry.path) {
-                    // We'll print the thumbnail sequence after the frame
-                    // The position would be calculated based on preview pane location
-                    let _ = thumb_seq; // Sequence will be rendered by app after frame
-                }
+    }
+
+    /// Render the file grid
+    fn render_file_grid(&mut self, frame: &mut Frame, area: Rect, fs: &FileSystem, graphics: &GraphicsBackend, dir_sizes: &DirSizeCache) {
+        self.file_grid_area = area;
+        self.icon_grid = self.icon_grid_for(area, fs);
+        if self.icon_grid.is_some() {
+            // The grid scrolls by whole rows, and the window may have been resized
+            self.scroll_offset -= self.scroll_offset % self.columns();
+            self.ensure_visible(self.visible_height());
+        }
+        let scrollable = self.max_scroll(fs.entries.len(), self.visible_height()) > 0;
+        self.scrollbar_area = scrollable.then(|| Self::scrollbar_track(area));
+        let view = GridView {
+            selected: self.selected_index,
+            scroll: self.scroll_offset,
+            marked: &self.marked,
+            focused: true,
+            dir_sizes,
+        };
+        let slots = match &self.icon_grid {
+            Some(grid) => self.render_icon_grid(frame, area, fs, graphics, view, grid),
+            None => self.render_grid(frame, area, fs, graphics, view),
+        };
+        self.icon_slots.extend(slots);
+    }
+
+    /// Render the pane without focus in dual-pane mode
+    fn render_other_grid(&mut self, frame: &mut Frame, area: Rect, fs: &FileSystem, graphics: &GraphicsBackend, dir_sizes: &DirSizeCache) {
+        self.other_grid_area = area;
+        self.other_icon_grid = self.icon_grid_for(area, fs);
+        let columns = self.other_icon_grid.as_ref().map_or(1, |grid| grid.columns);
+        let view = GridView {
+            selected: self.other_pane.selected_index,
+            scroll: self.other_pane.scroll_offset - self.other_pane.scroll_offset % columns,
+            marked: &self.other_pane.marked,
+            focused: false,
+            dir_sizes,
+        };
+        let slots = match &self.other_icon_grid {
+            Some(grid) => self.render_icon_grid(frame, area, fs, graphics, view, grid),
+            None => self.render_grid(frame, area, fs, graphics, view),
+        };
+        self.icon_slots.extend(slots);
+    }
+
+    /// Icon cells for a file grid in `area`, when the icon view is on and fits
+    ///
+    /// The tree view keeps its rows, since the grid can't show nesting.
+    fn icon_grid_for(&self, area: Rect, fs: &FileSystem) -> Option<IconGrid> {
+        if !self.icon_view || fs.is_tree() {
+            return None;
+        }
+        IconGrid::fit(area)
+    }
+
+    /// Draw one file grid as icons with names below, returning where graphic icons go
+    fn render_icon_grid(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        fs: &FileSystem,
+        graphics: &GraphicsBackend,
+        view: GridView,
+        grid: &IconGrid,
+    ) -> Vec<IconSlot> {
+        if fs.is_loading_slowly() {
+            return self.render_grid(frame, area, fs, graphics, view);
+        }
+
+        let border = if view.focused { Color::Green } else { Color::DarkGray };
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.file_grid_title(fs, view.focused))
+            .title_top(Line::from(Self::sort_indicator(fs)).right_aligned())
+            .title_style(Style::default().fg(border));
+        if self.dual_pane {
+            block = block.border_style(Style::default().fg(border));
+        }
+        frame.render_widget(block, area);
+
+        let graphic_icons = IconManager::supported(graphics);
+        let mut icon_slots = Vec::new();
+        let visible = fs.entries.iter().enumerate().skip(view.scroll);
+        for (cell, (index, entry)) in grid.cells.iter().zip(visible) {
+            let is_selected = index == view.selected;
+            let is_marked = view.marked.contains(&entry.path);
+
+            let style = if is_selected && view.focused {
+                Style::default()
+                    .bg(self.theme.selection_bg)
+                    .fg(self.theme.selection_fg)
+                    .add_modifier(Modifier::BOLD)
+            } else if is_marked {
+                Style::default()
+                    .bg(self.theme.marked_bg)
+                    .fg(self.entry_color(entry))
+            } else if is_selected {
+                // The unfocused pane's selection, like its hollow ▷ in the list
+                Style::default()
+                    .fg(self.entry_color(entry))
+                    .add_modifier(Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(self.entry_color(entry))
+            };
+
+            // Centered over the name; left blank for a graphic icon
+            let icon = if graphic_icons {
+                icon_slots.push(IconSlot {
+                    x: cell.x + (cell.width - 2) / 2,
+                    y: cell.y,
+                    file_type: entry.path.extension()
+                        .map(|e| e.to_string_lossy().to_lowercase())
+                        .unwrap_or_default(),
+                    is_dir: entry.is_dir,
+                    is_image: entry.is_image,
+                    highlighted: (is_selected && view.focused) || is_marked,
+                });
+                "  "
+            } else {
+                Self::entry_icon(entry)
+            };
+
+            let name = Self::truncate_name(&entry.name, cell.width.saturating_sub(2) as usize);
+            let text = vec![Line::from(icon), Line::from(name)];
+            // The third row stays blank, spacing the rows apart
+            let label = Rect { height: cell.height.min(2), ..*cell };
+            frame.render_widget(
+                Paragraph::new(text).alignment(Alignment::Center).style(style),
+                label,
+            );
+        }
+        self.render_scrollbar(frame, area, fs.entries.len(), grid.cells.len(), view.scroll, grid.columns);
+        icon_slots
+    }
+
+    /// `name` cut to `width` columns, ending in "…" when shortened
+    fn truncate_name(name: &str, width: usize) -> String {
+        if Span::raw(name).width() <= width {
+            return name.to_string();
+        }
+        let mut truncated = String::new();
+        for c in name.chars() {
+            truncated.push(c);
+            if Span::raw(truncated.as_str()).width() >= width {
+                truncated.pop();
+                break;
             }
         }
+        truncated.push('…');
+        truncated
     }
 
-    /// Render the file grid
-    fn render_file_grid(&self, frame: &mut Frame, area: Rect, fs: &FileSystem, _graphics: &GraphicsBackend) {
-        let visible_height = area.height.saturating_sub(2) as usize; // Account for borders
+    /// Draw one file grid, returning where graphic icons go
+    fn render_grid(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        fs: &FileSystem,
+        graphics: &GraphicsBackend,
+        view: GridView,
+    ) -> Vec<IconSlot> {
+        let visible_height = area.height.saturating_sub(2) as usize;
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let border = if view.focused { Color::Green } else { Color::DarkGray };
+
+        if fs.is_loading_slowly() {
+            let loading = Paragraph::new("Loading directory…")
+                .style(Style::default().fg(Color::DarkGray))
+                .block(Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Files ")
+                    .title_style(Style::default().fg(border)));
+            frame.render_widget(loading, area);
+            return Vec::new();
+        }
         
+        let graphic_icons = IconManager::supported(graphics);
+        let mut icon_slots = Vec::new();
+        // As wide as the longest extension in the listing, so it stays put while scrolling
+        let ext_width = if self.split_extension {
+            fs.entries.iter()
+                .filter_map(|entry| entry.split_extension().1)
+                .map(|ext| ext.chars().count().min(EXT_COLUMN_MAX_WIDTH))
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let git_log = fs.git_log.as_ref().filter(|git_log| git_log.shows_column(&fs.current_path));
         let items: Vec<ListItem> = fs.entries
             .iter()
             .enumerate()
-            .skip(self.scroll_offset)
+            .skip(view.scroll)
             .take(visible_height)
             .map(|(index, entry)| {
-                let icon = if entry.is_dir { "📁" } else { Self::get_file_icon(&entry.name) };
-                let is_selected = index == self.selected_index;
+                let is_selected = index == view.selected;
+                let is_marked = view.marked.contains(&entry.path);
+
+                // Indentation and expand marker in the tree view
+                let tree_prefix = fs.tree_rows.get(index).map(|row| {
+                    let marker = if !entry.is_dir { "  " } else if row.expanded { "▾ " } else { "▸ " };
+                    format!("{}{}", "  ".repeat(row.depth), marker)
+                }).unwrap_or_default();
+
+                // Leave the emoji's two cells blank for a graphic icon
+                let icon = if graphic_icons {
+                    icon_slots.push(IconSlot {
+                        // Inside the border, after the indicator and tree prefix
+                        x: area.x + 3 + tree_prefix.chars().count() as u16,
+                        y: area.y + 1 + (index - view.scroll) as u16,
+                        file_type: entry.path.extension()
+                            .map(|e| e.to_string_lossy().to_lowercase())
+                            .unwrap_or_default(),
+                        is_dir: entry.is_dir,
+                        is_image: entry.is_image,
+                        highlighted: (is_selected && view.focused) || is_marked,
+                    });
+                    "  "
+                } else {
+                    Self::entry_icon(entry)
+                };
                 
                 // Selection indicator
-                let indicator = if is_selected { "▶" } else { " " };
+                let indicator = if is_marked {
+                    "✓"
+                } else if is_selected {
+                    if view.focused { "▶" } else { "▷" }
+                } else {
+                    " "
+                };
                 
-                let style = if is_selected {
+                let style = if is_selected && view.focused {
                     Style::default()
-                        .bg(Color::Rgb(80, 80, 160))
-                        .fg(Color::White)
+                        .bg(self.theme.selection_bg)
+                        .fg(self.theme.selection_fg)
                         .add_modifier(Modifier::BOLD)
+                } else if is_marked {
+                    Style::default()
+                        .bg(self.theme.marked_bg)
+                        .fg(self.entry_color(entry))
                 } else {
-                    Style::default().fg(if entry.is_dir { Color::Cyan } else { Color::White })
+                    Style::default().fg(self.entry_color(entry))
                 };
 
-                ListItem::new(Line::from(vec![
+                let (name, ext) = if self.split_extension {
+                    entry.split_extension()
+                } else {
+                    (entry.name.as_str(), None)
+                };
+                let mut line = Line::from(vec![
                     Span::styled(format!("{} ", indicator), Style::default().fg(Color::Yellow)),
+                    Span::styled(tree_prefix, Style::default().fg(Color::DarkGray)),
                     Span::raw(format!("{} ", icon)),
-                    Span::styled(&entry.name, style),
-                ]))
-                .style(style)
+                    Span::styled(name, style),
+                ]);
+
+                // Right-aligned columns: Ext, then the last commit, directory
+                // size, and modification time on rows where the name leaves
+                // room, dropped from the right where it doesn't
+                let mut columns = Vec::new();
+                if ext_width > 0 {
+                    let ext = ext.unwrap_or_default();
+                    let ext = if ext.chars().count() > ext_width {
+                        format!("{}…", ext.chars().take(ext_width - 1).collect::<String>())
+                    } else {
+                        ext.to_string()
+                    };
+                    columns.push(Span::styled(format!("{:<1$}", ext, ext_width), style));
+                }
+                let always_shown = columns.len();
+                if inner_width >= MODIFIED_COLUMN_MIN_WIDTH {
+                    if let Some(git_log) = git_log {
+                        // Only the listed directory's log is read, not those of expanded tree rows
+                        let label = match entry.path.parent() {
+                            Some(parent) if parent == fs.current_path => git_log.label(parent, &entry.name),
+                            _ => String::new(),
+                        };
+                        columns.push(Span::styled(format!("{:<1$}", label, GIT_COLUMN_WIDTH), Style::default().fg(Color::DarkGray)));
+                    }
+                    let mut modified = crate::fs::format_modified(entry.modified);
+                    if let Some(size) = Self::dir_size_label(view.dir_sizes, entry).filter(|_| entry.is_dir) {
+                        modified = format!("{}  {}", size, modified);
+                    }
+                    columns.push(Span::styled(modified, Style::default().fg(Color::DarkGray)));
+                }
+                // Every column takes two cells of space before it
+                let columns_width = |columns: &[Span]| columns.iter().map(|column| column.width() + 2).sum::<usize>();
+                while columns.len() > always_shown && line.width() + columns_width(&columns) > inner_width {
+                    columns.pop();
+                }
+                if !columns.is_empty() && line.width() + columns_width(&columns) <= inner_width {
+                    let gap = inner_width - line.width() - columns_width(&columns) + 2;
+                    line.spans.push(Span::raw(" ".repeat(gap)));
+                    for (index, column) in columns.into_iter().enumerate() {
+                        if index > 0 {
+                            line.spans.push(Span::raw("  "));
+                        }
+                        line.spans.push(column);
+                    }
+                }
+
+                ListItem::new(line).style(style)
             })
             .collect();
 
-        let list = List::new(items)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title(format!(" Files ({}) ", fs.entries.len()))
-                .title_style(Style::default().fg(Color::Green)));
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.file_grid_title(fs, view.focused))
+            .title_top(Line::from(Self::sort_indicator(fs)).right_aligned())
+            .title_style(Style::default().fg(border));
+        if self.dual_pane {
+            block = block.border_style(Style::default().fg(border));
+        }
+        let list = List::new(items).block(block);
 
         frame.render_widget(list, area);
+        self.render_scrollbar(frame, area, fs.entries.len(), visible_height, view.scroll, 1);
+        icon_slots
+    }
+
+    /// Draw a scrollbar over the grid's right border, unless every entry fits
+    fn render_scrollbar(&self, frame: &mut Frame, area: Rect, total: usize, visible: usize, scroll: usize, columns: usize) {
+        let max_scroll = (total.div_ceil(columns) * columns).saturating_sub(visible);
+        if max_scroll == 0 {
+            return;
+        }
+        // One position per scroll offset, so the thumb reaches the end at the last screenful
+        let mut state = ScrollbarState::new(max_scroll + 1)
+            .position(scroll)
+            .viewport_content_length(visible);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None);
+        frame.render_stateful_widget(scrollbar, area.inner(Margin { horizontal: 0, vertical: 1 }), &mut state);
+    }
+
+    /// The scrollbar's column: the grid's right border, between the corners
+    fn scrollbar_track(area: Rect) -> Rect {
+        Rect::new(area.right().saturating_sub(1), area.y + 1, 1, area.height.saturating_sub(2))
+    }
+
+    /// " Files (n) ", plus the filter while one is typed or applied
+    /// and "[marked only]" or "[marked hidden]" while the marks narrow the view
+    ///
+    /// In dual-pane mode the directory name replaces "Files", so the panes can be told apart.
+    fn file_grid_title(&self, fs: &FileSystem, focused: bool) -> String {
+        let name = if self.dual_pane {
+            fs.current_path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| fs.current_path.display().to_string())
+        } else {
+            "Files".to_string()
+        };
+        let name = match &fs.selection_filter {
+            Some(filter) => format!("{} [{}]", name, filter.label()),
+            None => name,
+        };
+        if self.mode == Mode::Search && focused {
+            format!(" {} ({}) /{}▏ ", name, fs.entries.len(), fs.filter)
+        } else if !fs.filter.is_empty() {
+            format!(" {} ({}) /{} ", name, fs.entries.len(), fs.filter)
+        } else {
+            format!(" {} ({}) ", name, fs.entries.len())
+        }
+    }
+
+    /// " Name ↑ " at the right of the grid's top border; clicking it cycles the sort mode
+    fn sort_indicator(fs: &FileSystem) -> String {
+        format!(" {} {} ", fs.sort_mode.label(), fs.sort_direction.arrow())
+    }
+
+    /// Whether a click lands on the focused grid's sort indicator
+    pub fn sort_indicator_at(&self, fs: &FileSystem, row: u16, column: u16) -> bool {
+        let area = self.file_grid_area;
+        let width = Self::sort_indicator(fs).chars().count() as u16;
+        let start = area.right().saturating_sub(1 + width);
+        area.height > 0 && row == area.y && column >= start && column < start + width
     }
 
     /// Render the status bar
-    fn render_status_bar(&self, frame: &mut Frame, area: Rect, fs: &FileSystem) {
+    ///
+    /// `focused_pane` is set in dual-pane mode and shown first.
+    fn render_status_bar(&self, frame: &mut Frame, area: Rect, fs: &FileSystem, focused_pane: Option<Pane>) {
+        if let Some((kind, progress)) = self.job_progress {
+            let mut label = format!(
+                "{} {} of {} files ({} of {})",
+                kind.label(),
+                progress.files,
+                progress.total_files,
+                crate::fs::format_size(progress.bytes),
+                crate::fs::format_size(progress.total_bytes),
+            );
+            if self.queued_jobs > 0 {
+                label.push_str(&format!(", {} queued", self.queued_jobs));
+            }
+            // Throughput and time left get their own space to the right of the bar,
+            // then any message, which would otherwise stay hidden until the job ends
+            let throughput = progress.throughput_label()
+                .map(|text| format!(" {} ", text))
+                .unwrap_or_default();
+            let message = self.status_message.as_ref().map(|message| {
+                let color = if message.is_error { Color::Red } else { Color::Yellow };
+                (format!(" {} ", message.text), Style::default().fg(color))
+            });
+            let message_width = message.as_ref().map_or(0, |(text, _)| text.chars().count() as u16).min(area.width / 2);
+            let [bar, rate, note] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(throughput.chars().count() as u16),
+                Constraint::Length(message_width),
+            ])
+            .areas(area);
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray))
+                .ratio(progress.ratio())
+                .label(label);
+            frame.render_widget(gauge, bar);
+            frame.render_widget(Paragraph::new(throughput).style(Style::default().fg(Color::Cyan)), rate);
+            if let Some((text, style)) = message {
+                frame.render_widget(Paragraph::new(text).style(style), note);
+            }
+            return;
+        }
+
         let selected_info = if let Some(entry) = fs.get_selected(self.selected_index) {
-            if entry.is_dir {
-                format!("📁 {}", entry.name)
+            if entry.is_dir || entry.file_type.is_special() {
+                format!("{} {}", Self::entry_icon(entry), entry.name)
             } else {
                 format!("{} ({})", entry.name, crate::fs::format_size(entry.size))
             }
@@ -330,9 +1475,33 @@ impl Ui {
             String::new()
         };
 
+        let count = if self.marked.is_empty() {
+            Span::styled(format!(" {} items ", fs.entries.len()), Style::default().fg(Color::DarkGray))
+        } else {
+            let marked_size: u64 = fs.entries.iter()
+                .filter(|e| !e.is_dir && self.marked.contains(&e.path))
+                .map(|e| e.size)
+                .sum();
+            Span::styled(
+                format!(" {} marked ({}) ", self.marked.len(), crate::fs::format_size(marked_size)),
+                Style::default().fg(Color::Yellow),
+            )
+        };
+
+        let pane = match focused_pane.filter(|_| self.dual_pane) {
+            Some(pane) => Span::styled(
+                format!(" [{}] ", pane.label()),
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+            ),
+            None => Span::raw(""),
+        };
+
         let status = Paragraph::new(Line::from(vec![
+            pane,
+            count,
+            Span::raw(" | "),
             Span::styled(
-                format!(" {} items ", fs.entries.len()),
+                format!("Sort: {} {}", fs.sort_mode.label(), fs.sort_direction.arrow()),
                 Style::default().fg(Color::DarkGray),
             ),
             Span::raw(" | "),
@@ -341,28 +1510,28 @@ impl Ui {
                 Style::default().fg(Color::Cyan),
             ),
             Span::raw(" | "),
-            Span::styled(
-                "↑↓:Nav Enter:Open Bksp:Back q:Quit",
-                Style::default().fg(Color::DarkGray),
-            ),
+            match &self.status_message {
+                Some(message) => Span::styled(
+                    message.text.as_str(),
+                    Style::default().fg(if message.is_error { Color::Red } else { Color::Yellow }),
+                ),
+                None => Span::styled(
+                    "↑↓:Nav Enter:Open Bksp:Back q:Quit",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            },
         ]));
 
         frame.render_widget(status, area);
     }
 
-    /// Render context menu
-    fn render_context_menu(&self, frame: &mut Frame) {
-        let menu_items = vec![
-            ("📂", "Open"),
-            ("📋", "Copy"),
-            ("✂️", "Cut"),
-            ("📄", "Paste"),
-            ("🗑️", "Delete"),
-            ("✏️", "Rename"),
-        ];
-
+    /// Render context menu, returning the row of each item
+    fn render_context_menu(&self, frame: &mut Frame, entry: Option<&FileEntry>) -> Vec<Rect> {
+        let menu_items = context_menu_items(entry);
         let menu_height = menu_items.len() as u16 + 2;
-        let menu_width = 16;
+        // Border, padding, and the two-cell icon around the longest label
+        let longest = menu_items.iter().map(|item| item.label().chars().count()).max().unwrap_or(0);
+        let menu_width = longest as u16 + 7;
 
         let area = Rect::new(
             self.context_menu_pos.0.min(frame.area().width.saturating_sub(menu_width)),
@@ -374,24 +1543,357 @@ impl Ui {
         let items: Vec<ListItem> = menu_items
             .iter()
             .enumerate()
-            .map(|(i, (icon, label))| {
+            .map(|(i, item)| {
                 let style = if i == self.context_menu_selected {
-                    Style::default().bg(Color::Rgb(80, 80, 160)).fg(Color::White)
+                    Style::default().bg(self.theme.selection_bg).fg(self.theme.selection_fg)
                 } else {
                     Style::default().fg(Color::White)
                 };
-                ListItem::new(Line::from(format!(" {} {} ", icon, label))).style(style)
+                ListItem::new(Line::from(format!(" {} {} ", item.icon(), item.label()))).style(style)
             })
             .collect();
 
         let menu = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().bg(Color::Rgb(40, 40, 60))));
+                .style(Style::default().bg(self.theme.dialog_bg)));
 
         // Clear the area first
         frame.render_widget(ratatui::widgets::Clear, area);
         frame.render_widget(menu, area);
+        let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+        (0..inner.height).map(|row| Rect::new(inner.x, inner.y + row, inner.width, 1)).collect()
+    }
+
+    /// Render the list of empty directories about to be removed
+    fn render_cleanup_preview(&self, frame: &mut Frame, fs: &FileSystem, dirs: &[PathBuf]) {
+        let screen = frame.area();
+        let width = (screen.width * 3 / 5).max(30).min(screen.width);
+        let height = (dirs.len() as u16 + 2).clamp(3, screen.height.saturating_sub(4).max(3));
+        let area = Rect::new(
+            screen.x + (screen.width - width) / 2,
+            screen.y + screen.height.saturating_sub(height) / 2,
+            width,
+            height.min(screen.height),
+        );
+
+        let items: Vec<ListItem> = dirs
+            .iter()
+            .map(|dir| {
+                let relative = dir.strip_prefix(&fs.current_path).unwrap_or(dir);
+                ListItem::new(Line::from(format!(" 📁 {}", relative.display())))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Remove {} empty directories? (y/n) ", dirs.len()))
+                .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(list, area);
+    }
+
+    /// Render the delete confirmation
+    fn render_delete_confirm(&self, frame: &mut Frame, confirm: &DeleteConfirm) {
+        /// Names listed before the rest are summarized
+        const MAX_NAMES: usize = 5;
+
+        let paths = &confirm.paths;
+        let mut lines = Vec::new();
+        if confirm.trash_failed {
+            lines.push(Line::from(Span::styled(" Could not move to Trash.", Style::default().fg(Color::Red))));
+        }
+        let what = if paths.len() == 1 { "this entry".to_string() } else { format!("{} entries", paths.len()) };
+        lines.push(Line::from(if confirm.permanent {
+            format!(" Permanently delete {}?", what)
+        } else {
+            format!(" Move {} to Trash?", what)
+        }));
+        for path in paths.iter().take(MAX_NAMES) {
+            let name = path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            lines.push(Line::from(Span::styled(format!("   {}", name), Style::default().fg(Color::Yellow))));
+        }
+        if paths.len() > MAX_NAMES {
+            lines.push(Line::from(Span::styled(
+                format!("   …and {} more", paths.len() - MAX_NAMES),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        if confirm.has_dirs && confirm.permanent {
+            lines.push(Line::from(Span::styled(
+                " Directories are removed with everything inside.",
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        let button = |label: &'static str, selected: bool| {
+            if selected {
+                Span::styled(label, Style::default().bg(self.theme.selection_bg).fg(self.theme.selection_fg))
+            } else {
+                Span::raw(label)
+            }
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::raw("    "),
+            button(" [Y]es ", confirm.yes_selected),
+            Span::raw("   "),
+            button(" [N]o ", !confirm.yes_selected),
+        ]));
+
+        let area = Self::centered_rect(frame.area(), 54, lines.len() as u16 + 2);
+        let dialog = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(" Confirm ")
+                .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(dialog, area);
+    }
+
+    /// Render the question asked when quitting would cut a job short
+    fn render_quit_confirm(&self, frame: &mut Frame) {
+        let mut lines = vec![Line::from(" Operation in progress, quit anyway? (y/n)")];
+        if let Some((kind, progress)) = self.job_progress {
+            lines.push(Line::from(Span::styled(
+                format!(" {} {} of {} files", kind.label(), progress.files, progress.total_files),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        lines.push(Line::from(Span::styled(
+            " Quitting stops it after the current file.",
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        let area = Self::centered_rect(frame.area(), 48, lines.len() as u16 + 2);
+        let dialog = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(" Quit ")
+                .title_style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(dialog, area);
+    }
+
+    /// Render a single-line text prompt
+    fn render_input_prompt(&self, frame: &mut Frame, prompt: &InputPrompt) {
+        let (before, after) = prompt.split_at_cursor();
+        let screen = frame.area();
+        let area = match prompt.kind {
+            // Over the header, where the path normally is
+            PromptKind::GoTo => Rect::new(screen.x, screen.y, screen.width, 3.min(screen.height)),
+            PromptKind::Rename | PromptKind::NewFile | PromptKind::NewDirectory => {
+                Self::centered_rect(screen, 50, 3)
+            }
+            // Wide enough for the count and size in the title
+            PromptKind::EmptyTrash => Self::centered_rect(screen, 64, 3),
+        };
+
+        let input = Paragraph::new(Line::from(vec![
+            Span::raw(" "),
+            Span::raw(before),
+            Span::styled("▏", Style::default().fg(Color::Yellow)),
+            Span::raw(after),
+        ]))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", prompt.title))
+            .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(input, area);
+    }
+
+    /// Render the command palette just above the status bar, returning its area
+    fn render_command_palette(&self, frame: &mut Frame, palette: &CommandPalette) -> Rect {
+        /// Matches listed at once; the list scrolls to keep the highlight in view
+        const MAX_ROWS: usize = 10;
+
+        let matches = palette.matches();
+        let screen = frame.area();
+        let rows = matches.len().clamp(1, MAX_ROWS);
+        let height = (rows as u16 + 3).min(screen.height.saturating_sub(1));
+        let area = Rect::new(screen.x, screen.y + screen.height.saturating_sub(height + 1), screen.width, height);
+        let inner_width = area.width.saturating_sub(2) as usize;
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled(" > ", Style::default().fg(Color::Yellow)),
+            Span::raw(palette.query.as_str()),
+            Span::styled("▏", Style::default().fg(Color::Yellow)),
+        ])];
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled("   No matching commands", Style::default().fg(Color::DarkGray))));
+        }
+        let skip = (palette.selected + 1).saturating_sub(rows);
+        for (index, entry) in matches.iter().enumerate().skip(skip).take(rows) {
+            let description = entry.action.description();
+            let gap = inner_width.saturating_sub(description.chars().count() + entry.keys.chars().count() + 4);
+            let style = if index == palette.selected {
+                Style::default().bg(self.theme.selection_bg).fg(self.theme.selection_fg)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::raw("   "),
+                Span::raw(description),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(entry.keys.as_str(), Style::default().fg(Color::DarkGray)),
+                Span::raw(" "),
+            ]).style(style));
+        }
+
+        let palette = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(" Commands ")
+                .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(palette, area);
+        area
+    }
+
+    /// Render the operation queue: running, waiting (highlighted one cancellable), and done
+    fn render_job_list(&self, frame: &mut Frame, jobs: &JobList) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let heading = |text: &'static str| Line::from(Span::styled(text, Style::default().fg(Color::Yellow)));
+
+        let mut lines = vec![heading(" Running")];
+        match &jobs.active {
+            Some(label) => lines.push(Line::from(format!("   {}", label))),
+            None => lines.push(Line::from(Span::styled("   Nothing", dim))),
+        }
+        lines.push(heading(" Queued"));
+        if jobs.pending.is_empty() {
+            lines.push(Line::from(Span::styled("   Nothing", dim)));
+        }
+        for (index, label) in jobs.pending.iter().enumerate() {
+            let style = if index == jobs.selected {
+                Style::default().bg(self.theme.selection_bg).fg(self.theme.selection_fg)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(format!("   {}", label)).style(style));
+        }
+        if !jobs.completed.is_empty() {
+            lines.push(heading(" Done"));
+        }
+        for (label, ok) in &jobs.completed {
+            let color = if *ok { Color::Green } else { Color::Red };
+            lines.push(Line::from(Span::styled(format!("   {}", label), Style::default().fg(color))));
+        }
+        lines.push(Line::from(Span::styled(" Del cancels the highlighted job, Esc closes", dim)));
+
+        let area = Self::centered_rect(frame.area(), 64, lines.len() as u16 + 2);
+        let dialog = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(" Operations ")
+                .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(dialog, area);
+    }
+
+    /// Render the clipboard: each path with its size, the highlighted one removable
+    fn render_clipboard_list(&self, frame: &mut Frame, list: &ClipboardList, dir_sizes: &DirSizeCache) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let width: u16 = 72;
+        // Borders, the two-space indent, and a gap before the size
+        let path_width = width as usize - 2 - 2 - 12;
+
+        let mut lines = Vec::new();
+        for (index, item) in list.items.iter().enumerate() {
+            let size = if item.missing {
+                "missing".to_string()
+            } else if item.is_dir {
+                match dir_sizes.get(&item.path, item.modified) {
+                    Some(size) => crate::fs::format_size(size.bytes),
+                    None => "dir".to_string(),
+                }
+            } else {
+                item.size.map(crate::fs::format_size).unwrap_or_default()
+            };
+            let path = item.path.display().to_string();
+            let path = if path.chars().count() > path_width {
+                let tail: String = path.chars().rev().take(path_width - 1).collect::<Vec<_>>().into_iter().rev().collect();
+                format!("…{}", tail)
+            } else {
+                path
+            };
+            let style = if index == list.selected {
+                Style::default().bg(self.theme.selection_bg).fg(self.theme.selection_fg)
+            } else if item.missing {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(format!("  {:<path_width$}{:>12}", path, size)).style(style));
+        }
+        lines.push(Line::from(Span::styled(" Del removes the highlighted item, Esc closes", dim)));
+
+        let title = format!(" Clipboard ({}, {}) ", if list.cut { "cut" } else { "copy" }, list.items.len());
+        let area = Self::centered_rect(frame.area(), width, lines.len() as u16 + 2);
+        let dialog = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(self.theme.dialog_bg)));
+
+        frame.render_widget(ratatui::widgets::Clear, area);
+        frame.render_widget(dialog, area);
+    }
+
+    /// A box `width` columns wide (clamped to the screen) centered on screen
+    fn centered_rect(screen: Rect, width: u16, height: u16) -> Rect {
+        let width = width.min(screen.width);
+        let height = height.min(screen.height);
+        Rect::new(
+            screen.x + (screen.width - width) / 2,
+            screen.y + (screen.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Get the icon for an entry, by type and then extension
+    fn entry_icon(entry: &FileEntry) -> &'static str {
+        if entry.is_symlink {
+            return "🔗";
+        }
+        match entry.file_type {
+            FileType::Directory => "📁",
+            FileType::Fifo => "🚰",
+            FileType::Socket => "🔌",
+            FileType::BlockDevice => "💽",
+            FileType::CharDevice => "🎛️",
+            FileType::Regular if entry.is_image => "🖼️",
+            FileType::Regular => Self::get_file_icon(&entry.name),
+        }
+    }
+
+    /// Get the list color for an entry
+    fn entry_color(&self, entry: &FileEntry) -> Color {
+        match entry.file_type {
+            FileType::Directory => self.theme.directory,
+            FileType::Fifo => Color::Yellow,
+            FileType::Socket => Color::Magenta,
+            FileType::BlockDevice | FileType::CharDevice => Color::LightRed,
+            FileType::Regular => self.theme.file,
+        }
     }
 
     /// Get file icon based on extension
@@ -404,15 +1906,15 @@ impl Ui {
             "tsx" | "jsx" => "⚛️",
             "md" => "📝",
             "toml" | "yaml" | "yml" | "json" => "⚙️",
-            "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" => "🖼️",
             "mp4" | "mov" | "avi" | "mkv" => "🎬",
             "mp3" | "wav" | "flac" | "ogg" => "🎵",
             "zip" | "tar" | "gz" | "rar" | "7z" => "📦",
+            "iso" | "img" => "💿",
             "pdf" => "📕",
             "doc" | "docx" => "📘",
             "xls" | "xlsx" => "📗",
             "ppt" | "pptx" => "📙",
-            "html" | "css" => "🌐",
+            "html" | "css" | "svg" => "🌐",
             "sh" | "bash" | "zsh" => "🖥️",
             "lock" => "🔒",
             "gitignore" | "git" => "📋",
@@ -423,19 +1925,123 @@ impl Ui {
         }
     }
 
-    /// Move selection up/down
+    /// Number of entries that fit in the file grid from the last render
+    pub fn visible_height(&self) -> usize {
+        match &self.icon_grid {
+            Some(grid) => grid.cells.len(),
+            None => self.file_grid_area.height.saturating_sub(2) as usize,
+        }
+    }
+
+    /// Entries per row: the icon grid's columns, or 1 for the list
+    pub fn columns(&self) -> usize {
+        self.icon_grid.as_ref().map_or(1, |grid| grid.columns)
+    }
+
+    /// Whether the preview pane is on screen; dual-pane mode leaves no room for it
+    pub fn preview_visible(&self) -> bool {
+        self.show_preview && !self.dual_pane
+    }
+
+    /// Swap the selection state with the pane without focus
+    pub fn swap_pane_state(&mut self) {
+        std::mem::swap(&mut self.selected_index, &mut self.other_pane.selected_index);
+        std::mem::swap(&mut self.scroll_offset, &mut self.other_pane.scroll_offset);
+        std::mem::swap(&mut self.marked, &mut self.other_pane.marked);
+    }
+
+    /// Keep the selection within the listing and inside the visible window
+    pub fn clamp_selection(&mut self, total: usize) {
+        self.selected_index = self.selected_index.min(total.saturating_sub(1));
+        self.ensure_visible(self.visible_height());
+    }
+
+    /// Scroll just enough that the selection is within the first `visible_height` entries
+    ///
+    /// In the icon view the offset stays at the start of a row.
+    pub fn ensure_visible(&mut self, visible_height: usize) {
+        let columns = self.columns();
+        let visible = visible_height.max(columns);
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index - self.selected_index % columns;
+        } else if self.selected_index >= self.scroll_offset + visible {
+            let row_end = (self.selected_index / columns + 1) * columns;
+            self.scroll_offset = row_end.saturating_sub(visible);
+        }
+    }
+
+    /// Move selection up/down, scrolling to keep it in view
     pub fn move_selection(&mut self, delta: i32, total_items: usize) {
         let new_index = self.selected_index as i32 + delta;
         if new_index >= 0 && new_index < total_items as i32 {
             self.selected_index = new_index as usize;
         }
+        self.ensure_visible(self.visible_height());
     }
 
-    /// Scroll the view
-    pub fn scroll(&mut self, delta: i32) {
-        let new_offset = self.scroll_offset as i32 + delta;
-        if new_offset >= 0 {
-            self.scroll_offset = new_offset as usize;
+    /// Scroll the view by `delta` rows, stopping when the last screenful is shown
+    pub fn scroll(&mut self, delta: i32, total_items: usize, visible_height: usize) {
+        let columns = self.columns();
+        let max_offset = self.max_scroll(total_items, visible_height);
+        let new_offset = (self.scroll_offset as i64 + delta as i64 * columns as i64).max(0) as usize;
+        self.scroll_offset = new_offset.min(max_offset);
+    }
+
+    /// Scroll in proportion to `row` along the scrollbar track, as when dragging its thumb
+    pub fn scroll_to_track_row(&mut self, row: u16, total_items: usize) {
+        let Some(track) = self.scrollbar_area else {
+            return;
+        };
+        let max_offset = self.max_scroll(total_items, self.visible_height());
+        let along = row.saturating_sub(track.y) as f64 / track.height.saturating_sub(1).max(1) as f64;
+        let offset = (along.min(1.0) * max_offset as f64).round() as usize;
+        self.scroll_offset = offset - offset % self.columns();
+    }
+
+    /// Largest scroll offset, which shows the last screenful
+    fn max_scroll(&self, total_items: usize, visible_height: usize) -> usize {
+        let columns = self.columns();
+        (total_items.div_ceil(columns) * columns).saturating_sub(visible_height)
+    }
+
+    /// Show an informational message until the next key press
+    pub fn set_status(&mut self, text: impl Into<String>) {
+        self.status_message = Some(StatusMessage {
+            text: text.into(),
+            is_error: false,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Show an error in red for `ERROR_DURATION`
+    pub fn set_error(&mut self, text: impl Into<String>) {
+        self.status_message = Some(StatusMessage {
+            text: text.into(),
+            is_error: true,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Drop the message on a key press, unless it is an error still on its timer
+    pub fn clear_status(&mut self) {
+        if self.status_message.as_ref().is_some_and(|m| !m.is_error) {
+            self.status_message = None;
+        }
+    }
+
+    /// Drop an error once it has been shown long enough
+    fn expire_status(&mut self) {
+        if self.status_message.as_ref()
+            .is_some_and(|m| m.is_error && m.shown_at.elapsed() >= ERROR_DURATION)
+        {
+            self.status_message = None;
+        }
+    }
+
+    /// Add or remove an entry from the marked set
+    pub fn toggle_mark(&mut self, path: &Path) {
+        if !self.marked.remove(path) {
+            self.marked.insert(path.to_path_buf());
         }
     }
 
@@ -449,17 +2055,276 @@ impl Ui {
         self.show_preview = !self.show_preview;
     }
 
+    /// Dimensions of the image `entry`, read once per file rather than every frame
+    fn image_info(&mut self, entry: &FileEntry) -> Option<String> {
+        let cached = self.image_info.as_ref()
+            .filter(|(path, modified, _)| *path == entry.path && *modified == entry.modified);
+        if let Some((_, _, info)) = cached {
+            return info.clone();
+        }
+        let info = crate::graphics::ThumbnailCache::get_image_info(&entry.path);
+        self.image_info = Some((entry.path.clone(), entry.modified, info.clone()));
+        info
+    }
+
+    /// The immutable flag (`chattr +i`) of `entry`, or None where it isn't supported
+    ///
+    /// Read for the previewed entry only, once per file: checking it opens
+    /// the file, which is too slow to do for a whole listing.
+    fn immutable_flag(&mut self, entry: &FileEntry) -> Option<bool> {
+        let cached = self.immutable_flag.as_ref()
+            .filter(|(path, modified, _)| *path == entry.path && *modified == entry.modified);
+        if let Some((_, _, immutable)) = cached {
+            return *immutable;
+        }
+        // Opening a device or FIFO could block or have side effects
+        let immutable = if entry.file_type.is_special() { None } else { crate::fs::is_immutable(&entry.path) };
+        self.immutable_flag = Some((entry.path.clone(), entry.modified, immutable));
+        immutable
+    }
+
+    /// Forget the cached immutable flag, which changes without touching the mtime
+    pub fn forget_immutable_flag(&mut self) {
+        self.immutable_flag = None;
+    }
+
+    /// Why the image at `path` failed, sniffed once rather than every frame
+    fn unsupported_reason(&mut self, path: &Path) -> Option<&'static str> {
+        match &self.unsupported_reason {
+            Some((cached, reason)) if cached == path => *reason,
+            _ => {
+                let reason = crate::graphics::thumbnails::unsupported_reason(path);
+                self.unsupported_reason = Some((path.to_path_buf(), reason));
+                reason
+            }
+        }
+    }
+
+    /// Read a text preview for the entry, if it looks like text and previews are on
+    ///
+    /// Read when the selection moves to the file or it is modified, not every frame.
+    fn text_preview(&mut self, fs: &FileSystem, entry: &FileEntry) -> Option<crate::fs::TextPreview> {
+        if !self.content_previews(fs) || !crate::fs::is_text_name(&entry.path) {
+            return None;
+        }
+        let cached = self.text_preview.as_ref()
+            .filter(|(path, modified, _)| *path == entry.path && *modified == entry.modified);
+        if let Some((_, _, text)) = cached {
+            return text.clone();
+        }
+        let text = FileSystem::read_preview(&entry.path, crate::fs::PREVIEW_MAX_BYTES);
+        self.text_preview = Some((entry.path.clone(), entry.modified, text.clone()));
+        text
+    }
+
+    /// Lines in the selected file's text preview, while one is shown
+    fn shown_text_lines(&self, fs: &FileSystem) -> Option<usize> {
+        let entry = fs.get_selected(self.selected_index)?;
+        if !self.preview_visible() || !self.content_previews(fs) {
+            return None;
+        }
+        let (path, modified, text) = self.text_preview.as_ref()?;
+        if *path != entry.path || *modified != entry.modified {
+            return None;
+        }
+        text.as_ref().map(|text| text.lines.len())
+    }
+
+    /// Scroll the selected file's text preview by `lines`, staying within it
+    pub fn scroll_preview(&mut self, fs: &FileSystem, lines: isize) {
+        let last = self.shown_text_lines(fs).unwrap_or(0).saturating_sub(1);
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(lines).min(last);
+    }
+
+    /// Line at the top of the selected file's scrolled text preview, for `edit`
+    pub fn preview_line(&self, fs: &FileSystem) -> Option<usize> {
+        self.shown_text_lines(fs)?;
+        (self.preview_scroll > 0).then_some(self.preview_scroll + 1)
+    }
+
+    /// First `hex_preview_bytes` of a binary file, for the hex dump
+    ///
+    /// Only asked once the text preview has passed, so text files never get here.
+    /// The file is read again only once it's modified.
+    fn hex_preview(&mut self, fs: &FileSystem, entry: &FileEntry) -> Option<Vec<u8>> {
+        if !self.content_previews(fs) || entry.file_type != FileType::Regular {
+            return None;
+        }
+        let cached = self.hex_preview.as_ref()
+            .filter(|(path, modified, _)| *path == entry.path && *modified == entry.modified);
+        if let Some((_, _, bytes)) = cached {
+            return bytes.clone();
+        }
+        let bytes = crate::fs::read_file_prefix(&entry.path, self.hex_preview_bytes).ok()
+            .filter(|bytes| !bytes.is_empty() && crate::fs::is_binary(bytes));
+        self.hex_preview = Some((entry.path.clone(), entry.modified, bytes.clone()));
+        bytes
+    }
+
+    /// Whether file contents should be read for thumbnails and previews
+    pub fn content_previews(&self, fs: &FileSystem) -> bool {
+        self.preview_override.unwrap_or(!fs.is_network)
+    }
+
+    /// Flip content previews relative to their current state
+    pub fn toggle_content_previews(&mut self, fs: &FileSystem) {
+        self.preview_override = Some(!self.content_previews(fs));
+    }
+
+    /// Enter or leave minimal mode
+    pub fn set_minimal(&mut self, minimal: bool) {
+        self.minimal = minimal;
+        self.show_sidebar = !minimal;
+        self.show_preview = !minimal;
+    }
+
+    /// Toggle between minimal mode and the full layout
+    pub fn toggle_minimal(&mut self) {
+        self.set_minimal(!self.minimal);
+    }
+
+    /// The entry the open context menu applies to, if it is still listed
+    pub fn context_menu_entry<'a>(&self, fs: &'a FileSystem) -> Option<&'a FileEntry> {
+        let target = self.context_menu_target.as_ref()?;
+        fs.entries.iter().find(|entry| &entry.path == target)
+    }
+
+    /// The bookmark the current directory is in, the deepest if several are
+    pub fn current_bookmark(&self, fs: &FileSystem) -> Option<usize> {
+        self.bookmarks
+            .iter()
+            .enumerate()
+            .filter(|(_, bookmark)| fs.current_path.starts_with(&bookmark.path))
+            .max_by_key(|(_, bookmark)| bookmark.path.components().count())
+            .map(|(index, _)| index)
+    }
+
+    /// Index of the bookmark at a mouse position, from the last render's sidebar
+    pub fn bookmark_at(&self, row: u16, column: u16) -> Option<usize> {
+        hit_test_grid(&self.sidebar_items, column, row)
+    }
+
+    /// Index of the context menu item at a mouse position
+    pub fn context_menu_item_at(&self, row: u16, column: u16) -> Option<usize> {
+        hit_test_grid(&self.context_menu_rows, column, row)
+    }
+
     /// Get item index at mouse position
-    pub fn get_item_at_position(&self, row: u16, _column: u16) -> Option<usize> {
-        // Layout:
-        // Row 0-2: Header (3 lines with borders)
-        // Row 3: Files panel top border
-        // Row 4+: File items (first item at row 4)
-        if row >= 4 {
-            let index = (row - 4) as usize + self.scroll_offset;
-            Some(index)
+    ///
+    /// Tests against the file grid from the last render, inside its borders;
+    /// clicks on the sidebar, preview, or chrome return `None`.
+    pub fn get_item_at_position(&self, row: u16, column: u16) -> Option<usize> {
+        if let Some(grid) = &self.icon_grid {
+            return hit_test_grid(&grid.cells, column, row).map(|cell| cell + self.scroll_offset);
+        }
+
+        let inner = self.file_grid_area.inner(Margin { horizontal: 1, vertical: 1 });
+        let inside = column >= inner.x && column < inner.x + inner.width
+            && row >= inner.y && row < inner.y + inner.height;
+        
+        if inside {
+            Some((row - inner.y) as usize + self.scroll_offset)
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_stops_at_last_screenful() {
+        let mut ui = Ui::new();
+        ui.scroll(100, 30, 10);
+        assert_eq!(ui.scroll_offset, 20);
+        ui.scroll(-3, 30, 10);
+        assert_eq!(ui.scroll_offset, 17);
+        ui.scroll(-100, 30, 10);
+        assert_eq!(ui.scroll_offset, 0);
+        ui.scroll(3, 5, 10);
+        assert_eq!(ui.scroll_offset, 0);
+    }
+
+    #[test]
+    fn test_icon_grid_moves_by_rows() {
+        let mut ui = Ui::new();
+        // Three columns and two rows of cells
+        ui.icon_grid = IconGrid::fit(Rect::new(0, 0, 50, 8));
+        assert_eq!((ui.columns(), ui.visible_height()), (3, 6));
+
+        ui.selected_index = 7;
+        ui.ensure_visible(ui.visible_height());
+        assert_eq!(ui.scroll_offset, 3);
+        assert_eq!(ui.get_item_at_position(4, 17), Some(7));
+        assert_eq!(ui.get_item_at_position(7, 17), None);
+
+        ui.scroll(5, 10, ui.visible_height());
+        assert_eq!(ui.scroll_offset, 6);
+    }
+
+    #[test]
+    fn test_scrollbar_drag_is_proportional() {
+        let mut ui = Ui::new();
+        ui.file_grid_area = Rect::new(0, 0, 40, 12);
+        ui.scrollbar_area = Some(Ui::scrollbar_track(ui.file_grid_area));
+
+        ui.scroll_to_track_row(10, 110);
+        assert_eq!(ui.scroll_offset, 100);
+        ui.scroll_to_track_row(0, 110);
+        assert_eq!(ui.scroll_offset, 0);
+        ui.scroll_to_track_row(6, 110);
+        assert_eq!(ui.scroll_offset, 56);
+    }
+
+    #[test]
+    fn test_sort_indicator_hit_test() {
+        let mut ui = Ui::new();
+        ui.file_grid_area = Rect::new(0, 0, 40, 12);
+        let mut fs = FileSystem::new(PathBuf::from("/"));
+        // " Name ↑ " sits just left of the top-right corner, columns 31..39
+        assert!(ui.sort_indicator_at(&fs, 0, 31));
+        assert!(ui.sort_indicator_at(&fs, 0, 38));
+        assert!(!ui.sort_indicator_at(&fs, 0, 39));
+        assert!(!ui.sort_indicator_at(&fs, 1, 35));
+        fs.sort_mode = crate::fs::SortMode::Modified;
+        assert!(ui.sort_indicator_at(&fs, 0, 27));
+    }
+
+    #[test]
+    fn test_preview_scroll_stays_within_the_shown_text() {
+        let dir = crate::test_support::TempDir::new("preview-scroll");
+        let text: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        std::fs::write(dir.path().join("notes.txt"), text).unwrap();
+        let mut fs = FileSystem::new(dir.path().to_path_buf());
+        fs.load_directory().unwrap();
+        let mut ui = Ui::new();
+        ui.text_preview(&fs, &fs.entries[0].clone());
+
+        ui.scroll_preview(&fs, 100);
+        assert_eq!((ui.preview_scroll, ui.preview_line(&fs)), (9, Some(10)));
+        ui.scroll_preview(&fs, -3);
+        assert_eq!(ui.preview_line(&fs), Some(7));
+
+        ui.toggle_preview();
+        assert_eq!(ui.preview_line(&fs), None);
+        ui.scroll_preview(&fs, 3);
+        assert_eq!(ui.preview_scroll, 0);
+    }
+
+    #[test]
+    fn test_prompt_editing_at_cursor() {
+        let mut prompt = InputPrompt::new(PromptKind::Rename, "Rename", "café.txt".to_string());
+        prompt.cursor = 4;
+        prompt.insert('s');
+        assert_eq!(prompt.value, "cafés.txt");
+        prompt.move_left();
+        prompt.backspace();
+        assert_eq!(prompt.value, "cafs.txt");
+        prompt.move_end();
+        prompt.delete();
+        prompt.backspace();
+        assert_eq!(prompt.split_at_cursor(), ("cafs.tx", ""));
+    }
+}
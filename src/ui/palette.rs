@@ -0,0 +1,121 @@
+//! Ctrl+P command palette: every action, searchable by name
+
+use crate::keymap::{Action, Keymap};
+
+/// An action as listed in the palette
+pub struct PaletteEntry {
+    pub action: Action,
+    /// Bound chords, e.g. "ctrl+p, F1"; empty when unbound
+    pub keys: String,
+}
+
+/// Open palette: the typed query and the highlighted match
+pub struct CommandPalette {
+    entries: Vec<PaletteEntry>,
+    pub query: String,
+    /// Index into `matches()`
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// List every action except the palette itself, with its current keys
+    pub fn new(keymap: &Keymap) -> Self {
+        let entries = Action::ALL
+            .iter()
+            .filter(|&&action| action != Action::CommandPalette)
+            .map(|&action| PaletteEntry {
+                action,
+                keys: keymap.chords_for(action)
+                    .iter()
+                    .map(|chord| chord.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+            .collect();
+        Self { entries, query: String::new(), selected: 0 }
+    }
+
+    /// Entries matching the query, best first; all of them for an empty query
+    pub fn matches(&self) -> Vec<&PaletteEntry> {
+        let mut scored: Vec<(i32, &PaletteEntry)> = self.entries
+            .iter()
+            .filter_map(|entry| {
+                let by_description = fuzzy_score(&self.query, entry.action.description());
+                let by_name = fuzzy_score(&self.query, entry.action.name());
+                by_description.max(by_name).map(|score| (score, entry))
+            })
+            .collect();
+        // Stable, so equal scores keep the documented order
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Action under the highlight
+    pub fn selected_action(&self) -> Option<Action> {
+        self.matches().get(self.selected).map(|entry| entry.action)
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let count = self.matches().len();
+        if count > 0 {
+            self.selected = self.selected.saturating_add_signed(delta).min(count - 1);
+        }
+    }
+}
+
+/// Score `text` against `query` as a case-insensitive subsequence, or `None` if
+/// the query's characters don't all appear in order
+///
+/// Matches at word starts and runs of consecutive characters score higher,
+/// so "th" ranks "Toggle hidden" above "Go to path".
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match = None;
+
+    for q in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let found = (position..text.len()).find(|&i| text[i] == q)?;
+        let word_start = found == 0 || !text[found - 1].is_alphanumeric();
+        score += 1;
+        if word_start {
+            score += 8;
+        }
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 4;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matching() {
+        assert!(fuzzy_score("thd", "Toggle hidden files").is_some());
+        assert!(fuzzy_score("xyz", "Toggle hidden files").is_none());
+        assert!(fuzzy_score("th", "Toggle hidden files") > fuzzy_score("th", "Go to path"));
+
+        let palette = CommandPalette {
+            query: "hidden".to_string(),
+            ..CommandPalette::new(&Keymap::default())
+        };
+        let first = &palette.matches()[0];
+        assert_eq!(first.action, Action::ToggleHidden);
+        assert_eq!(first.keys, ".");
+    }
+}
@@ -0,0 +1,36 @@
+//! Colors that can be changed in the config file
+
+use ratatui::style::Color;
+
+/// Colors used across the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Background of the selected row and menu item
+    pub selection_bg: Color,
+    /// Text of the selected row and menu item
+    pub selection_fg: Color,
+    /// Background of marked rows
+    pub marked_bg: Color,
+    /// Directory names
+    pub directory: Color,
+    /// Regular file names
+    pub file: Color,
+    /// Current path in the header
+    pub path: Color,
+    /// Background of dialogs and menus
+    pub dialog_bg: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            selection_bg: Color::Rgb(80, 80, 160),
+            selection_fg: Color::White,
+            marked_bg: Color::Rgb(70, 60, 20),
+            directory: Color::Cyan,
+            file: Color::White,
+            path: Color::Cyan,
+            dialog_bg: Color::Rgb(40, 40, 60),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Web addresses for files in cloud-synced folders, from the config's `path_url_map`
+
+use std::path::{Path, PathBuf};
+
+/// Files under `prefix` are at `template` on the web, with `{path}` standing
+/// for the part of their path after the prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlMapping {
+    pub prefix: PathBuf,
+    pub template: String,
+}
+
+/// The web address of `path` under the longest prefix that contains it
+///
+/// The relative path is joined with `/` and percent-encoded, so names with
+/// spaces or non-ASCII characters still make a valid URL.
+pub fn web_url(mappings: &[UrlMapping], path: &Path) -> Option<String> {
+    let (mapping, relative) = mappings
+        .iter()
+        .filter_map(|mapping| Some((mapping, path.strip_prefix(&mapping.prefix).ok()?)))
+        .max_by_key(|(mapping, _)| mapping.prefix.components().count())?;
+    let relative = relative
+        .components()
+        .map(|component| encode(&component.as_os_str().to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join("/");
+    Some(mapping.template.replace("{path}", &relative))
+}
+
+/// Percent-encode everything but the characters URLs allow unescaped
+fn encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_builds_the_url() {
+        let mappings = [
+            UrlMapping { prefix: PathBuf::from("/home/me/Dropbox"), template: "https://www.dropbox.com/home/{path}".to_string() },
+            UrlMapping { prefix: PathBuf::from("/home/me/Dropbox/Team"), template: "https://team.example/{path}?view".to_string() },
+        ];
+        let url = |path: &str| web_url(&mappings, Path::new(path));
+
+        assert_eq!(url("/home/me/Dropbox/notes/todo list.txt").as_deref(), Some("https://www.dropbox.com/home/notes/todo%20list.txt"));
+        assert_eq!(url("/home/me/Dropbox/Team/plan ü.md").as_deref(), Some("https://team.example/plan%20%C3%BC.md?view"));
+        assert_eq!(url("/home/me/Dropbox").as_deref(), Some("https://www.dropbox.com/home/"));
+        assert_eq!(url("/home/me/Dropboxes/a"), None);
+    }
+}